@@ -0,0 +1,61 @@
+//! Wide-path encoding for a 10k-file tree: a fresh `Vec<u16>` per file (what
+//! every delete used to allocate) against one reused buffer per thread.
+//!
+//! ```bash
+//! cargo bench --bench path_encoding
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rmx::winapi::normalize_path_wide;
+
+const FILES: usize = 10_000;
+
+fn tree_paths(separator: char) -> Vec<String> {
+    (0..FILES)
+        .map(|i| {
+            format!(
+                r"C:\work\app\node_modules\pkg_{}\lib\file_{}.js",
+                i / 100,
+                i
+            )
+            .replace('\\', &separator.to_string())
+        })
+        .collect()
+}
+
+fn bench_path_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("path_to_wide_10k");
+
+    for (name, separator) in [("backslash", '\\'), ("forward_slash", '/')] {
+        let paths = tree_paths(separator);
+
+        group.bench_with_input(BenchmarkId::new("fresh_vec", name), &paths, |b, paths| {
+            b.iter(|| {
+                for path in paths {
+                    let mut wide = Vec::new();
+                    normalize_path_wide(path, &mut wide);
+                    black_box(&wide);
+                }
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("reused_buffer", name),
+            &paths,
+            |b, paths| {
+                let mut wide = Vec::new();
+                b.iter(|| {
+                    for path in paths {
+                        normalize_path_wide(path, &mut wide);
+                        black_box(&wide);
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_path_encoding);
+criterion_main!(benches);
@@ -100,5 +100,6 @@ fn build_rmx_shell() {
     println!("cargo::rustc-env=RMX_SHELL_DLL_PATH={}", dll_path.display());
 
     println!("cargo::rerun-if-changed=rmx-shell/src/");
+    println!("cargo::rerun-if-changed=rmx-shell/assets/");
     println!("cargo::rerun-if-changed=rmx-shell/Cargo.toml");
 }
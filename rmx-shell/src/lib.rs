@@ -76,6 +76,7 @@ extern "system" fn DllGetClassObject(
 #[no_mangle]
 extern "system" fn DllCanUnloadNow() -> HRESULT {
     if OBJECT_COUNT.load(Ordering::SeqCst) == 0 && LOCK_COUNT.load(Ordering::SeqCst) == 0 {
+        menu::release_menu_bitmaps();
         S_OK
     } else {
         S_FALSE
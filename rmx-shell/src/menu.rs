@@ -1,16 +1,19 @@
 use std::cell::RefCell;
-use std::ffi::OsString;
+use std::ffi::{c_void, OsString};
 use std::os::windows::ffi::OsStringExt;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicPtr, Ordering};
 
 use windows::core::*;
 use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Gdi::*;
 use windows::Win32::System::Com::*;
 use windows::Win32::System::Ole::CF_HDROP;
 use windows::Win32::System::Registry::HKEY;
 use windows::Win32::System::Threading::{
     CreateProcessW, DETACHED_PROCESS, PROCESS_INFORMATION, STARTUPINFOW,
 };
+use windows::Win32::UI::HiDpi::{GetDpiForSystem, GetSystemMetricsForDpi};
 use windows::Win32::UI::Shell::Common::ITEMIDLIST;
 use windows::Win32::UI::Shell::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
@@ -22,6 +25,14 @@ const VERB_UNLOCK: &str = "rmxunlock";
 const CMD_ID_DELETE: u32 = 0;
 const CMD_ID_UNLOCK: u32 = 1;
 
+/// rmx's own icon, one 32bpp image per small-icon size from 100% to 300% DPI
+const RMX_ICON: &[u8] = include_bytes!("../assets/rmx.ico");
+
+/// Menu item bitmaps, built on the first right-click and kept until the
+/// DLL unloads (see [`release_menu_bitmaps`])
+static DELETE_BITMAP: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static UNLOCK_BITMAP: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
 #[implement(IShellExtInit, IContextMenu)]
 pub struct RmxContextMenu {
     selected_paths: RefCell<Vec<PathBuf>>,
@@ -104,6 +115,21 @@ impl IContextMenu_Impl for RmxContextMenu_Impl {
                 (idcmdfirst + CMD_ID_UNLOCK) as usize,
                 MENU_UNLOCK_TEXT,
             )?;
+
+            // Icons are cosmetic: one that fails to load leaves the item plain
+            let delete = cached_bitmap(&DELETE_BITMAP, || embedded_icon_bitmap(RMX_ICON));
+            let unlock = cached_bitmap(&UNLOCK_BITMAP, || stock_icon_bitmap(SIID_LOCK));
+            for (offset, bitmap) in [(0, delete), (1, unlock)] {
+                if let Some(bitmap) = bitmap {
+                    let item = MENUITEMINFOW {
+                        cbSize: std::mem::size_of::<MENUITEMINFOW>() as u32,
+                        fMask: MIIM_BITMAP,
+                        hbmpItem: bitmap,
+                        ..Default::default()
+                    };
+                    let _ = SetMenuItemInfoW(hmenu, indexmenu + offset, true, &item);
+                }
+            }
         }
 
         // Return item count = 2 (two menu items added)
@@ -223,6 +249,140 @@ fn build_command_line(exe_path: &str, path: &PathBuf, action: &MenuAction) -> St
     }
 }
 
+/// Return the bitmap cached in `slot`, creating it on first use. A thread
+/// that loses the race frees its copy and uses the winner's.
+fn cached_bitmap(
+    slot: &AtomicPtr<c_void>,
+    create: impl FnOnce() -> Option<HBITMAP>,
+) -> Option<HBITMAP> {
+    let cached = slot.load(Ordering::Acquire);
+    if !cached.is_null() {
+        return Some(HBITMAP(cached));
+    }
+    let bitmap = create()?;
+    match slot.compare_exchange(
+        std::ptr::null_mut(),
+        bitmap.0,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    ) {
+        Ok(_) => Some(bitmap),
+        Err(existing) => {
+            unsafe {
+                let _ = DeleteObject(HGDIOBJ(bitmap.0));
+            }
+            Some(HBITMAP(existing))
+        }
+    }
+}
+
+/// Free the cached menu bitmaps; called once no menu objects remain.
+pub fn release_menu_bitmaps() {
+    for slot in [&DELETE_BITMAP, &UNLOCK_BITMAP] {
+        let bitmap = slot.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        if !bitmap.is_null() {
+            unsafe {
+                let _ = DeleteObject(HGDIOBJ(bitmap));
+            }
+        }
+    }
+}
+
+/// Small-icon size at the system DPI, so menus on scaled displays get a
+/// crisp bitmap instead of a stretched 16x16 one.
+unsafe fn menu_icon_size() -> (i32, i32) {
+    let dpi = GetDpiForSystem();
+    (
+        GetSystemMetricsForDpi(SM_CXSMICON, dpi),
+        GetSystemMetricsForDpi(SM_CYSMICON, dpi),
+    )
+}
+
+/// Image data of the `.ico` entry best suited to `size`: the smallest one at
+/// least that large, else the largest available.
+fn ico_entry(ico: &[u8], size: u32) -> Option<&[u8]> {
+    let u16_at = |at: usize| Some(u16::from_le_bytes(ico.get(at..at + 2)?.try_into().ok()?));
+    let u32_at = |at: usize| Some(u32::from_le_bytes(ico.get(at..at + 4)?.try_into().ok()?));
+
+    if u16_at(2)? != 1 {
+        return None;
+    }
+    let entries = (0..u16_at(4)? as usize).filter_map(|i| {
+        let at = 6 + i * 16;
+        // A stored width of 0 means 256
+        let width = match *ico.get(at)? {
+            0 => 256,
+            w => w as u32,
+        };
+        let len = u32_at(at + 8)? as usize;
+        let offset = u32_at(at + 12)? as usize;
+        Some((width, ico.get(offset..offset.checked_add(len)?)?))
+    });
+    entries
+        .min_by_key(|&(width, _)| (width < size, width.abs_diff(size)))
+        .map(|(_, data)| data)
+}
+
+/// Render the embedded `.ico` at the menu icon size.
+unsafe fn embedded_icon_bitmap(ico: &[u8]) -> Option<HBITMAP> {
+    let (cx, cy) = menu_icon_size();
+    let data = ico_entry(ico, cx as u32)?;
+    let icon = CreateIconFromResourceEx(data, true, 0x0003_0000, cx, cy, LR_DEFAULTCOLOR).ok()?;
+    let bitmap = icon_bitmap(icon, cx, cy);
+    let _ = DestroyIcon(icon);
+    bitmap
+}
+
+/// Render a small stock shell icon at the menu icon size.
+unsafe fn stock_icon_bitmap(id: SHSTOCKICONID) -> Option<HBITMAP> {
+    let mut info = SHSTOCKICONINFO {
+        cbSize: std::mem::size_of::<SHSTOCKICONINFO>() as u32,
+        ..Default::default()
+    };
+    SHGetStockIconInfo(id, SHGSI_ICON | SHGSI_SMALLICON, &mut info).ok()?;
+
+    let (cx, cy) = menu_icon_size();
+    let bitmap = icon_bitmap(info.hIcon, cx, cy);
+    let _ = DestroyIcon(info.hIcon);
+    bitmap
+}
+
+/// Draw `icon` into a 32bpp top-down DIB, the format menus draw with alpha
+/// for `MENUITEMINFOW::hbmpItem`.
+unsafe fn icon_bitmap(icon: HICON, cx: i32, cy: i32) -> Option<HBITMAP> {
+    let bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: cx,
+            biHeight: -cy,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut bits = std::ptr::null_mut();
+    let bitmap = CreateDIBSection(
+        HDC::default(),
+        &bmi,
+        DIB_RGB_COLORS,
+        &mut bits,
+        HANDLE::default(),
+        0,
+    )
+    .ok();
+
+    if let Some(bitmap) = bitmap {
+        let dc = CreateCompatibleDC(HDC::default());
+        let old = SelectObject(dc, HGDIOBJ(bitmap.0));
+        let _ = DrawIconEx(dc, 0, 0, icon, cx, cy, 0, HBRUSH::default(), DI_NORMAL);
+        SelectObject(dc, old);
+        let _ = DeleteDC(dc);
+    }
+    bitmap
+}
+
 fn spawn_detached_process(cmdline: &str) {
     let mut cmdline_wide: Vec<u16> = cmdline.encode_utf16().chain(std::iter::once(0)).collect();
 
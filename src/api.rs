@@ -0,0 +1,1215 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use crossbeam_queue::SegQueue;
+use rayon::prelude::*;
+
+use crate::backend::Backend;
+use crate::broker::{Broker, BrokerConfig};
+use crate::error::{Error, ErrorKind, FailedItem, Result};
+use crate::journal::Journal;
+use crate::probe::LockGroup;
+use crate::profile::{Profiler, WorkerStats};
+use crate::tree::{self, DirectoryTree, ReparseCounts, ScanFilter, SkipReason};
+use crate::winapi::RetryPolicy;
+use crate::worker::{CallbackSink, ProgressEvent, ProgressSink, SinkSet};
+use crate::{probe, safety, scan_cache, winapi, worker};
+
+/// Outcome of a deletion (or a dry run of one)
+#[derive(Debug, Clone, Default)]
+pub struct DeletionStats {
+    pub dirs_deleted: usize,
+    pub files_deleted: usize,
+    pub total_bytes: u64,
+    pub total_time: Duration,
+    /// Bytes physically released, counting shared hard-link data once
+    /// (only computed with `hard_link_aware`)
+    pub freed_bytes: u64,
+    /// Entries intentionally left in place
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+    /// Directories left in place because something below them was
+    pub dirs_kept: usize,
+    /// Locked directories moved aside with `rename_locked` that could not be
+    /// deleted yet, as `(original, new location)`
+    pub deferred: Vec<(PathBuf, PathBuf)>,
+    /// Entries a dry run with `check` expects to fail, with the reason
+    pub blocked: Vec<(PathBuf, String)>,
+    /// Number of entries moved to the Recycle Bin rather than deleted
+    pub recycled: usize,
+    /// Cloud placeholder files deleted without downloading them
+    pub placeholders: usize,
+    /// Alternate data streams deleted one by one ahead of their files
+    /// ([`ScanFilter::streams`])
+    pub streams: usize,
+    /// Part of `total_bytes` held in alternate data streams; only measured
+    /// with [`ScanFilter::streams`] or [`ScanFilter::stream_bytes`]
+    pub stream_bytes: u64,
+    /// Disk space allocated to the deleted files; only measured with
+    /// [`ScanFilter::on_disk_size`]
+    pub on_disk_bytes: u64,
+    /// Links deleted as links, by kind
+    pub reparse_points: ReparseCounts,
+    /// Processes a dry run with `check_locks` found holding files open
+    pub locks: Vec<LockGroup>,
+    /// The tree a dry run with `list_tree` would delete, rendered for display
+    pub listing: Option<String>,
+    /// Per-directory timing and per-worker activity, rendered for display;
+    /// only with `profile` or `worker_stats`
+    pub timing_report: Option<String>,
+}
+
+impl DeletionStats {
+    pub fn merge(&mut self, other: &DeletionStats) {
+        self.dirs_deleted += other.dirs_deleted;
+        self.files_deleted += other.files_deleted;
+        self.total_bytes += other.total_bytes;
+        self.freed_bytes += other.freed_bytes;
+        self.total_time += other.total_time;
+        self.skipped.extend(other.skipped.iter().cloned());
+        self.dirs_kept += other.dirs_kept;
+        self.deferred.extend(other.deferred.iter().cloned());
+        self.blocked.extend(other.blocked.iter().cloned());
+        self.recycled += other.recycled;
+        self.placeholders += other.placeholders;
+        self.streams += other.streams;
+        self.stream_bytes += other.stream_bytes;
+        self.on_disk_bytes += other.on_disk_bytes;
+        self.reparse_points.merge(&other.reparse_points);
+        self.locks.extend(other.locks.iter().cloned());
+        append_text(&mut self.listing, &other.listing);
+        append_text(&mut self.timing_report, &other.timing_report);
+    }
+
+    pub fn total_items(&self) -> usize {
+        self.dirs_deleted + self.files_deleted
+    }
+}
+
+fn append_text(text: &mut Option<String>, more: &Option<String>) {
+    match (text.as_mut(), more) {
+        (Some(text), Some(more)) => text.push_str(more),
+        (None, Some(more)) => *text = Some(more.clone()),
+        (_, None) => {}
+    }
+}
+
+/// Options for [`delete_tree`].
+///
+/// ```no_run
+/// use rmx::DeleteOptions;
+///
+/// let options = DeleteOptions::new().force(true).threads(8);
+/// let stats = rmx::delete_tree("target".as_ref(), &options)?;
+/// println!("{} files removed", stats.files_deleted);
+/// # Ok::<(), rmx::error::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct DeleteOptions {
+    force: bool,
+    threads: Option<usize>,
+    kill_processes: bool,
+    graceful_kill: bool,
+    verbose: bool,
+    dry_run: bool,
+    check: bool,
+    check_locks: bool,
+    hard_link_aware: bool,
+    cache: bool,
+    progress_bar: bool,
+    profile: bool,
+    worker_stats: bool,
+    retry: RetryPolicy,
+    shred: Option<u8>,
+    fail_fast: bool,
+    rename_locked: bool,
+    verify: bool,
+    list_tree: bool,
+    max_list: Option<usize>,
+    batching: BrokerConfig,
+    parallel_threshold: Option<usize>,
+    min_chunk_size: Option<usize>,
+    backend: Backend,
+    scan_filter: ScanFilter,
+    cancel: Option<Arc<AtomicBool>>,
+    failure_sink: Option<Sender<FailedItem>>,
+    sinks: Vec<Arc<dyn ProgressSink>>,
+    journal: Option<Arc<Journal>>,
+}
+
+impl fmt::Debug for DeleteOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeleteOptions")
+            .field("force", &self.force)
+            .field("threads", &self.threads)
+            .field("kill_processes", &self.kill_processes)
+            .field("graceful_kill", &self.graceful_kill)
+            .field("verbose", &self.verbose)
+            .field("dry_run", &self.dry_run)
+            .field("check", &self.check)
+            .field("check_locks", &self.check_locks)
+            .field("hard_link_aware", &self.hard_link_aware)
+            .field("cache", &self.cache)
+            .field("progress_bar", &self.progress_bar)
+            .field("profile", &self.profile)
+            .field("worker_stats", &self.worker_stats)
+            .field("retry", &self.retry)
+            .field("shred", &self.shred)
+            .field("fail_fast", &self.fail_fast)
+            .field("rename_locked", &self.rename_locked)
+            .field("verify", &self.verify)
+            .field("list_tree", &self.list_tree)
+            .field("max_list", &self.max_list)
+            .field("batching", &self.batching)
+            .field("parallel_threshold", &self.parallel_threshold)
+            .field("min_chunk_size", &self.min_chunk_size)
+            .field("backend", &self.backend)
+            .field("scan_filter", &self.scan_filter)
+            .field("cancel", &self.cancel)
+            .field("failure_sink", &self.failure_sink)
+            .field("sinks", &self.sinks.len())
+            .field("journal", &self.journal)
+            .finish()
+    }
+}
+
+impl DeleteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat a missing path as success and allow deleting paths the safety
+    /// check only warns about
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Worker thread count (default: derived from CPU count and drive type)
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Kill processes holding locks on entries that can't be deleted
+    pub fn kill_processes(mut self, kill_processes: bool) -> Self {
+        self.kill_processes = kill_processes;
+        self
+    }
+
+    /// With `kill_processes`, ask each process to close its windows and
+    /// only terminate it if it is still running after a short grace period
+    pub fn graceful_kill(mut self, graceful_kill: bool) -> Self {
+        self.graceful_kill = graceful_kill;
+        self
+    }
+
+    fn kill_grace(&self) -> Option<Duration> {
+        self.graceful_kill.then_some(winapi::KILL_GRACE_PERIOD)
+    }
+
+    /// Print progress and per-entry messages
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Report what would be deleted without touching anything
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// With `dry_run`, probe every entry for locks and permissions
+    pub fn check(mut self, check: bool) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// With `dry_run`, list the processes holding files open (via the
+    /// Restart Manager) without killing them. Slow on large trees.
+    pub fn check_locks(mut self, check_locks: bool) -> Self {
+        self.check_locks = check_locks;
+        self
+    }
+
+    /// Compute [`DeletionStats::freed_bytes`]
+    pub fn hard_link_aware(mut self, hard_link_aware: bool) -> Self {
+        self.hard_link_aware = hard_link_aware;
+        self
+    }
+
+    /// Reuse cached scan results for dry runs
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Draw a single-line progress bar with throughput and ETA on stderr.
+    /// Does nothing when stderr is not a terminal.
+    pub fn progress_bar(mut self, progress_bar: bool) -> Self {
+        self.progress_bar = progress_bar;
+        self
+    }
+
+    /// Time every directory and print the slowest ones plus `remove_dir`
+    /// latency percentiles when done
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Print how many work items each worker thread processed and how long
+    /// it sat idle when done
+    pub fn worker_stats(mut self, worker_stats: bool) -> Self {
+        self.worker_stats = worker_stats;
+        self
+    }
+
+    /// How often to retry entries another process briefly holds open
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Overwrite every file `passes` times with random data before deleting
+    /// it (see [`winapi::overwrite_file`]). On SSDs and copy-on-write file
+    /// systems the old blocks may survive anyway.
+    pub fn shred(mut self, passes: u8) -> Self {
+        self.shred = Some(passes);
+        self
+    }
+
+    /// Stop scheduling new work after the first failure. Entries already in
+    /// flight finish, and the failures so far come back as
+    /// [`Error::PartialFailure`].
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Move directories that stay locked to a hidden `.rmx-pending` folder
+    /// at the volume root, freeing their path, and delete them from there
+    /// once possible (Windows only)
+    pub fn rename_locked(mut self, rename_locked: bool) -> Self {
+        self.rename_locked = rename_locked;
+        self
+    }
+
+    /// After deleting a directory, rescan it and report every entry that
+    /// is still there as a failure, unless it already failed
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// With `dry_run`, print every selected entry as an indented tree
+    pub fn list_tree(mut self, list_tree: bool) -> Self {
+        self.list_tree = list_tree;
+        self
+    }
+
+    /// With `list_tree`, list at most `max` entries per directory operand
+    pub fn max_list(mut self, max: usize) -> Self {
+        self.max_list = Some(max);
+        self
+    }
+
+    /// Split directories with more than `threshold` files into batches the
+    /// workers share (default: 1024)
+    pub fn batch_threshold(mut self, threshold: usize) -> Self {
+        self.batching.batch_threshold = threshold;
+        self
+    }
+
+    /// Files per batch when a large directory is split. By default 256,
+    /// scaled by the directory's average file size; setting it fixes it.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batching.batch_size = size.max(1);
+        self.batching.adaptive = false;
+        self
+    }
+
+    /// Delete a directory's files on the rayon pool once there are at least
+    /// this many (default: derived from CPU count)
+    pub fn parallel_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_threshold = Some(threshold);
+        self
+    }
+
+    /// Minimum files per rayon task in a parallel delete (default: derived
+    /// from CPU count)
+    pub fn min_chunk_size(mut self, size: usize) -> Self {
+        self.min_chunk_size = Some(size.max(1));
+        self
+    }
+
+    /// How each file and directory is removed (default: [`Backend::Posix`])
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Leave entries rejected by `filter` in place
+    pub fn scan_filter(mut self, filter: ScanFilter) -> Self {
+        self.scan_filter = filter;
+        self
+    }
+
+    /// Record finished directories in `journal` and skip the ones an earlier
+    /// run recorded, so an interrupted deletion can pick up where it stopped
+    pub fn journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Abort an in-progress deletion by setting `token` from another thread.
+    /// The deletion then returns [`Error::Cancelled`].
+    pub fn cancel_token(mut self, token: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Send each failed entry to `sink` as soon as it happens. Failures are
+    /// still collected into [`Error::PartialFailure`] at the end.
+    pub fn failure_sink(mut self, sink: Sender<FailedItem>) -> Self {
+        self.failure_sink = Some(sink);
+        self
+    }
+
+    /// Call `callback` for every deleted entry and failure. It runs on the
+    /// worker threads, inline with the deletion, so it should return quickly.
+    ///
+    /// Shorthand for a [`progress_sink`](Self::progress_sink) that only
+    /// sees entries; enough for logging or counting. Progress bars and
+    /// cancellable UIs want the sink itself.
+    pub fn progress_callback<F>(self, callback: F) -> Self
+    where
+        F: Fn(ProgressEvent<'_>) + Send + Sync + 'static,
+    {
+        self.progress_sink(Arc::new(CallbackSink(Arc::new(callback))))
+    }
+
+    /// Report every entry, failure and the directory progress to `sink`,
+    /// and stop when it asks to. Can be given more than once (callbacks
+    /// included); every sink sees every report.
+    pub fn progress_sink(mut self, sink: Arc<dyn ProgressSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Report a [`ProgressEvent::Notice`] to the sinks
+    fn notice(&self, path: &Path, message: &str) {
+        if let Some(sink) = self.sink() {
+            sink.on_event(ProgressEvent::Notice { path, message });
+        }
+    }
+
+    /// All sinks as one, `None` without any
+    fn sink(&self) -> Option<Arc<dyn ProgressSink>> {
+        match self.sinks.as_slice() {
+            [] => None,
+            [sink] => Some(sink.clone()),
+            sinks => Some(Arc::new(SinkSet(sinks.to_vec()))),
+        }
+    }
+}
+
+/// Delete a file or directory tree without prompting. Also exported as
+/// `rmx::delete`.
+///
+/// Safety checks always apply: paths that can never be deleted are rejected,
+/// and paths the CLI would ask about are only deleted with `force`.
+pub fn delete_tree(path: &Path, options: &DeleteOptions) -> Result<DeletionStats> {
+    if safety::is_protected_system_folder(path) {
+        return Ok(DeletionStats {
+            skipped: vec![(path.to_path_buf(), SkipReason::SystemFolder)],
+            ..Default::default()
+        });
+    }
+
+    if !winapi::path_exists(path) {
+        if options.force {
+            return Ok(DeletionStats::default());
+        }
+        return Err(Error::InvalidPath {
+            path: path.to_path_buf(),
+            reason: "No such file or directory".to_string(),
+        });
+    }
+
+    if !winapi::is_directory(path) {
+        if safety::is_user_protected(path) {
+            return Err(Error::InvalidPath {
+                path: path.to_path_buf(),
+                reason: format!("protected by {} or protected.txt", safety::PROTECT_ENV),
+            });
+        }
+        if options.scan_filter.is_active() {
+            let stat = winapi::stat_path(path);
+            if options.scan_filter.keeps(stat.modified, stat.size) {
+                return Ok(DeletionStats::default());
+            }
+        }
+        if options.dry_run {
+            return Ok(dry_run_file(path, options));
+        }
+        return delete_file(path, options);
+    }
+
+    if let safety::SafetyCheck::Dangerous {
+        reason,
+        can_override,
+    } = safety::check_path_safety(path)
+    {
+        if !can_override || !options.force {
+            return Err(Error::InvalidPath {
+                path: path.to_path_buf(),
+                reason,
+            });
+        }
+    }
+
+    if options.dry_run {
+        dry_run_directory(path, options)
+    } else {
+        delete_directory(path, options, None)
+    }
+}
+
+/// Dry run for a single file.
+pub fn dry_run_file(path: &Path, options: &DeleteOptions) -> DeletionStats {
+    let streams = if options.scan_filter.streams {
+        tree::file_streams(path).len()
+    } else {
+        0
+    };
+    let mut blocked = Vec::new();
+    if options.check {
+        if let Err(e) = winapi::probe_deletable(path, false) {
+            blocked.push((path.to_path_buf(), probe::describe_block(path, &e)));
+        }
+    }
+    let locks = if options.check_locks {
+        probe::find_file_locks(path)
+    } else {
+        Vec::new()
+    };
+    DeletionStats {
+        files_deleted: 1,
+        blocked,
+        streams,
+        locks,
+        ..Default::default()
+    }
+}
+
+/// Delete a single file, killing lock holders first if allowed.
+pub fn delete_file(path: &Path, options: &DeleteOptions) -> Result<DeletionStats> {
+    let start = Instant::now();
+
+    let streams = if options.scan_filter.streams {
+        delete_streams(&tree::file_streams(path), options)
+    } else {
+        0
+    };
+
+    let sink = options.sink();
+    if let Err(e) = delete_file_unlocking(path, options) {
+        if let Some(sink) = &sink {
+            sink.on_error(&FailedItem {
+                path: path.to_path_buf(),
+                error: e.to_string(),
+                is_dir: false,
+                kind: winapi::classify_error(&e),
+            });
+        }
+        return Err(Error::io_with_path(path.to_path_buf(), e));
+    }
+
+    let elapsed = start.elapsed();
+    if let Some(sink) = &sink {
+        sink.on_event(ProgressEvent::FileDeleted { path });
+    }
+
+    Ok(DeletionStats {
+        files_deleted: 1,
+        total_time: elapsed,
+        streams,
+        ..Default::default()
+    })
+}
+
+/// Delete each alternate data stream on its own, before its file goes.
+/// Failures are only notices: deleting the file removes its streams too.
+fn delete_streams(streams: &[PathBuf], options: &DeleteOptions) -> usize {
+    streams
+        .par_iter()
+        .filter(
+            |stream| match winapi::delete_file_with(stream, &options.retry) {
+                Ok(()) => true,
+                Err(e) if winapi::is_not_found_error(&e) => false,
+                Err(e) => {
+                    options.notice(
+                        stream,
+                        &format!("failed to delete stream {}: {}", stream.display(), e),
+                    );
+                    false
+                }
+            },
+        )
+        .count()
+}
+
+fn delete_file_unlocking(path: &Path, options: &DeleteOptions) -> io::Result<()> {
+    if let Some(passes) = options.shred {
+        winapi::overwrite_file(path, passes)?;
+    }
+    match winapi::delete_file_with(path, &options.retry) {
+        Err(e) if options.kill_processes && winapi::is_file_in_use_error(&e) => {
+            // Step 1: Restart Manager — 精准找到并杀掉占用进程（快速可靠）
+            let _ = winapi::kill_locking_processes(path, options.verbose, options.kill_grace());
+            if winapi::delete_file_with(path, &options.retry).is_ok() {
+                return Ok(());
+            }
+            // Step 2: 暴力句柄扫描兜底（慢，但能处理 RM 找不到的情况）
+            let paths = [path.to_path_buf()];
+            let _ = winapi::force_close_file_handles(&paths, options.verbose);
+            winapi::delete_file_with(path, &options.retry)
+        }
+        result => result,
+    }
+}
+
+/// Dry run for a directory tree.
+pub fn dry_run_directory(path: &Path, options: &DeleteOptions) -> Result<DeletionStats> {
+    let filter = scan_filter_for(options);
+    // The cache stores unfiltered trees, so it only applies without filters
+    let tree = if options.cache
+        && !filter.is_active()
+        && !filter.streams
+        && !filter.stream_bytes
+        && !filter.on_disk_size
+        && !filter.follow_junctions
+        && !filter.has_ignore_file(path)
+    {
+        scan_cache::discover_tree_cached(path)
+    } else {
+        tree::discover_tree_filtered(path, &filter)
+    }
+    .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
+
+    let listing = options
+        .list_tree
+        .then(|| tree::render_tree(&tree, path, options.max_list));
+
+    let freed_bytes = if options.hard_link_aware {
+        tree::unique_freed_bytes(&tree)
+    } else {
+        0
+    };
+
+    let blocked = if options.check {
+        probe::preflight(&tree)
+    } else {
+        Vec::new()
+    };
+
+    let locks = if options.check_locks {
+        probe::find_locks(&tree)
+    } else {
+        Vec::new()
+    };
+
+    Ok(DeletionStats {
+        dirs_deleted: tree.removable_dir_count(),
+        files_deleted: tree.file_count,
+        total_bytes: tree.total_bytes,
+        freed_bytes,
+        skipped: tree.skipped,
+        dirs_kept: tree.kept_dirs.len(),
+        blocked,
+        placeholders: tree.placeholders.len(),
+        streams: tree.streams.len(),
+        stream_bytes: tree.stream_bytes,
+        on_disk_bytes: tree.on_disk_bytes,
+        reparse_points: tree.reparse_points,
+        locks,
+        listing,
+        ..Default::default()
+    })
+}
+
+/// Delete a directory tree with the parallel broker/worker pipeline.
+/// `cached_tree` skips the scan when the caller already has one.
+pub fn delete_directory(
+    path: &Path,
+    options: &DeleteOptions,
+    cached_tree: Option<DirectoryTree>,
+) -> Result<DeletionStats> {
+    let result = delete_directory_impl(path, options, cached_tree);
+    if options.verify {
+        verify_removed(path, options, result)
+    } else {
+        result
+    }
+}
+
+/// `--verify`: rescan what is left of `path` with the same filter and turn
+/// every selected survivor into a failure. Entries that already failed, and
+/// the directories above them, are expected to survive and not reported
+/// twice. Costs one existence check when the tree is gone.
+fn verify_removed(
+    path: &Path,
+    options: &DeleteOptions,
+    result: Result<DeletionStats>,
+) -> Result<DeletionStats> {
+    if matches!(result, Err(Error::Cancelled)) || !winapi::path_exists(path) {
+        return result;
+    }
+    let filter = ScanFilter {
+        streams: false,
+        stream_bytes: false,
+        on_disk_size: false,
+        quiet: true,
+        ..options.scan_filter.clone()
+    };
+    let Ok(tree) = tree::discover_tree_filtered(path, &filter) else {
+        return result;
+    };
+
+    let known: HashSet<&Path> = match &result {
+        Err(Error::PartialFailure { errors, .. }) => errors
+            .iter()
+            .flat_map(|failure| failure.path.ancestors())
+            .collect(),
+        _ => HashSet::new(),
+    };
+    let files = tree.dir_files.values().flatten().map(|f| (f, false));
+    let dirs = tree
+        .dirs
+        .iter()
+        .filter(|d| !tree.kept_dirs.contains(*d))
+        .map(|d| (d, true));
+    let survivors: Vec<FailedItem> = files
+        .chain(dirs)
+        .filter(|(p, _)| !known.contains(p.as_path()))
+        .map(|(p, is_dir)| FailedItem {
+            path: p.clone(),
+            error: "still exists after deletion".to_string(),
+            is_dir,
+            kind: ErrorKind::Other(0),
+        })
+        .collect();
+    if survivors.is_empty() {
+        return result;
+    }
+
+    if let Some(sink) = &options.failure_sink {
+        for survivor in &survivors {
+            let _ = sink.send(survivor.clone());
+        }
+    }
+    match result {
+        Ok(stats) => Err(Error::PartialFailure {
+            total: stats.total_items(),
+            failed: survivors.len(),
+            errors: survivors,
+        }),
+        Err(Error::PartialFailure {
+            total,
+            failed,
+            mut errors,
+        }) => {
+            let failed = failed + survivors.len();
+            errors.extend(survivors);
+            Err(Error::PartialFailure {
+                total,
+                failed,
+                errors,
+            })
+        }
+        other => other,
+    }
+}
+
+fn worker_count(path: &Path, options: &DeleteOptions) -> usize {
+    if let Some(t) = options.threads {
+        return t;
+    }
+    let base = tree::cpu_count();
+    if winapi::is_ssd_drive(path) {
+        (base * 5 / 4).max(base + 1)
+    } else {
+        (base * 2).min(64)
+    }
+}
+
+const PROGRESS_BAR_WIDTH: usize = 24;
+
+/// One frame of the `--progress` bar: percent, directories per second, ETA.
+fn progress_bar_line(completed: usize, total: usize, elapsed: Duration) -> String {
+    let fraction = if total == 0 {
+        1.0
+    } else {
+        (completed as f64 / total as f64).min(1.0)
+    };
+    let filled = (fraction * PROGRESS_BAR_WIDTH as f64) as usize;
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 {
+        completed as f64 / secs
+    } else {
+        0.0
+    };
+    let eta = if rate > 0.0 {
+        let remaining = (total.saturating_sub(completed) as f64 / rate).ceil() as u64;
+        format!("{}:{:02}", remaining / 60, remaining % 60)
+    } else {
+        "--:--".to_string()
+    };
+    format!(
+        "[{}{}] {:>3}% {:>9} dirs/s  ETA {:>6}",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled),
+        (fraction * 100.0) as u32,
+        rate as u64,
+        eta
+    )
+}
+
+/// The scan filter, also skipping what the journal already recorded
+fn scan_filter_for(options: &DeleteOptions) -> ScanFilter {
+    let mut filter = options.scan_filter.clone();
+    if let Some(journal) = &options.journal {
+        filter.journaled = Some(journal.completed());
+    }
+    filter
+}
+
+fn delete_directory_impl(
+    path: &Path,
+    options: &DeleteOptions,
+    cached_tree: Option<DirectoryTree>,
+) -> Result<DeletionStats> {
+    let start = Instant::now();
+
+    // A truncated tree only has counts; deleting needs the full scan. A
+    // journal has to prune the scan, which a cached tree predates.
+    let cached_tree = cached_tree.filter(|t| !t.truncated && options.journal.is_none());
+    let mut tree = match cached_tree {
+        Some(t) => {
+            options.notice(
+                path,
+                &format!("reusing cached tree for '{}'...", path.display()),
+            );
+            t
+        }
+        None => {
+            options.notice(path, &format!("scanning '{}'...", path.display()));
+            tree::discover_tree_filtered(path, &scan_filter_for(options))
+                .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?
+        }
+    };
+    let scan_time = start.elapsed();
+
+    let dir_count = tree.removable_dir_count();
+    let dirs_kept = tree.kept_dirs.len();
+    let file_count = tree.file_count;
+    let total_bytes = tree.total_bytes;
+    let stream_bytes = tree.stream_bytes;
+    let on_disk_bytes = tree.on_disk_bytes;
+    let reparse_points = tree.reparse_points;
+    let mut skipped = std::mem::take(&mut tree.skipped);
+    let tree_placeholders: HashSet<PathBuf> =
+        std::mem::take(&mut tree.placeholders).into_iter().collect();
+    let placeholders_deleted = Arc::new(AtomicUsize::new(0));
+    // Must be measured before anything is deleted
+    let freed_bytes = if options.hard_link_aware {
+        tree::unique_freed_bytes(&tree)
+    } else {
+        0
+    };
+
+    let streams = delete_streams(&std::mem::take(&mut tree.streams), options);
+
+    let worker_count = worker_count(path, options);
+
+    let cancel = options.cancel.clone().unwrap_or_default();
+    let (broker, rx) = Broker::with_config(tree, worker_count, options.batching);
+    let broker = Arc::new(broker.with_cancel(cancel.clone()));
+
+    let sink = options.sink();
+    let error_tracker = Arc::new(match &options.failure_sink {
+        Some(sink) => worker::ErrorTracker::with_sink(sink.clone()),
+        None => worker::ErrorTracker::new(),
+    });
+    let worker_config = worker::WorkerConfig {
+        verbose: options.verbose,
+        ignore_errors: !options.fail_fast,
+        kill_processes: options.kill_processes,
+        kill_grace: options.kill_grace(),
+        handle_closer: Arc::new(winapi::HandleCloser::new(options.verbose)),
+        cancel: cancel.clone(),
+        profiler: options.profile.then(|| Arc::new(Profiler::new())),
+        worker_stats: options
+            .worker_stats
+            .then(|| Arc::new(WorkerStats::new(worker_count))),
+        retry: options.retry.clone(),
+        shred: options.shred,
+        sink: sink.clone(),
+        journal: options.journal.clone(),
+        deferred: options.rename_locked.then(|| Arc::new(SegQueue::new())),
+        placeholders: Arc::new(tree_placeholders),
+        placeholders_deleted: placeholders_deleted.clone(),
+        parallel_threshold: options
+            .parallel_threshold
+            .unwrap_or_else(worker::parallel_threshold),
+        min_chunk_size: options
+            .min_chunk_size
+            .unwrap_or_else(worker::min_chunk_size),
+        backend: options.backend.instance(),
+    };
+    let profiler = worker_config.profiler.clone();
+    let worker_stats = worker_config.worker_stats.clone();
+    let deferred = worker_config.deferred.clone();
+
+    let handles = worker::spawn_workers(
+        worker_count,
+        rx,
+        broker.clone(),
+        worker_config,
+        error_tracker.clone(),
+    );
+    let show_bar = options.progress_bar && io::stderr().is_terminal();
+    let progress_handle = if show_bar {
+        let total = broker.total_dirs();
+        let broker_clone = broker.clone();
+        Some(thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(200));
+            let completed = broker_clone.completed_count();
+            if completed >= total || broker_clone.is_stopped() {
+                break;
+            }
+            eprint!("\r{}", progress_bar_line(completed, total, start.elapsed()));
+            io::stderr().flush().ok();
+        }))
+    } else {
+        None
+    };
+
+    let finished = Arc::new(AtomicBool::new(false));
+    let sink_handle = sink.clone().map(|sink| {
+        let broker = broker.clone();
+        let cancel = cancel.clone();
+        let finished = finished.clone();
+        let total = broker.total_dirs();
+        thread::spawn(move || {
+            let mut reported = None;
+            loop {
+                // Unparked once the workers are done, so joining never waits
+                // out the poll interval
+                thread::park_timeout(Duration::from_millis(50));
+                if sink.is_cancelled() {
+                    // 取消: stop workers from scheduling further directories
+                    cancel.store(true, Ordering::Relaxed);
+                }
+                let completed = broker.completed_count();
+                if reported != Some(completed) {
+                    sink.on_dir_complete(completed, total);
+                    reported = Some(completed);
+                }
+                if completed >= total
+                    || cancel.load(Ordering::Relaxed)
+                    || finished.load(Ordering::Acquire)
+                {
+                    break;
+                }
+            }
+        })
+    });
+
+    for handle in handles {
+        handle.join().expect("Worker thread panicked");
+    }
+
+    if let Some(handle) = progress_handle {
+        handle.join().ok();
+        eprintln!(
+            "\r{}",
+            progress_bar_line(
+                broker.completed_count(),
+                broker.total_dirs(),
+                start.elapsed()
+            )
+        );
+    }
+
+    let timing_report = match (profiler, worker_stats) {
+        (None, None) => None,
+        (profiler, worker_stats) => Some(
+            profiler.map_or_else(String::new, |p| p.report(scan_time).to_string())
+                + &worker_stats.map_or_else(String::new, |w| w.report().to_string()),
+        ),
+    };
+
+    let placeholders = placeholders_deleted.load(Ordering::Relaxed);
+    let deferred = deferred.map_or_else(Vec::new, |queue| {
+        let mut pending = Vec::new();
+        while let Some((original, moved)) = queue.pop() {
+            // Its files are gone; only the directory handle held it, which
+            // may have been closed by now
+            if winapi::remove_dir_with(&moved, &options.retry).is_err() {
+                pending.push((original, moved));
+            }
+        }
+        pending
+    });
+    skipped.extend(
+        deferred
+            .iter()
+            .map(|(original, _)| (original.clone(), SkipReason::LockedSkipped)),
+    );
+
+    let elapsed = start.elapsed();
+    let failures = error_tracker.get_failures();
+    if let Some(journal) = &options.journal {
+        let _ = journal.flush();
+    }
+
+    finished.store(true, Ordering::Release);
+    if let Some(handle) = sink_handle {
+        handle.thread().unpark();
+        handle.join().ok();
+    }
+    if let Some(sink) = &sink {
+        sink.on_dir_complete(broker.completed_count(), broker.total_dirs());
+        sink.on_finish();
+    }
+
+    if cancel.load(Ordering::Relaxed) {
+        return Err(Error::Cancelled);
+    }
+
+    if !failures.is_empty() {
+        if broker.is_aborted() {
+            options.notice(
+                path,
+                &format!(
+                    "stopped after the first error ({} of {} directories done)",
+                    broker.completed_count(),
+                    broker.total_dirs()
+                ),
+            );
+        }
+        return Err(Error::PartialFailure {
+            total: dir_count + file_count,
+            failed: failures.len(),
+            errors: failures,
+        });
+    }
+
+    Ok(DeletionStats {
+        dirs_deleted: dir_count,
+        files_deleted: file_count,
+        total_bytes,
+        total_time: elapsed,
+        freed_bytes,
+        skipped,
+        dirs_kept,
+        deferred,
+        placeholders,
+        streams,
+        stream_bytes,
+        on_disk_bytes,
+        reparse_points,
+        timing_report,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_delete_tree_dry_run_then_delete() {
+        let temp = std::env::temp_dir().join("rmx_api_delete_tree");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("a/b")).unwrap();
+        fs::write(temp.join("a/one.txt"), "1234").unwrap();
+        fs::write(temp.join("a/b/two.txt"), "56").unwrap();
+
+        let options = DeleteOptions::new().force(true).threads(2);
+
+        let stats = delete_tree(&temp, &options.clone().dry_run(true)).unwrap();
+        assert_eq!(stats.files_deleted, 2);
+        assert_eq!(stats.total_bytes, 6);
+        assert!(temp.join("a/b/two.txt").exists());
+
+        let stats = delete_tree(&temp, &options).unwrap();
+        assert_eq!(stats.files_deleted, 2);
+        assert_eq!(stats.dirs_deleted, 3);
+        assert!(!temp.exists());
+
+        // Missing paths are only an error without force
+        assert!(delete_tree(&temp, &options).is_ok());
+        assert!(delete_tree(&temp, &DeleteOptions::new()).is_err());
+    }
+
+    #[test]
+    fn test_verify_reports_untracked_survivors() {
+        let temp = std::env::temp_dir().join("rmx_api_verify");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(&temp).unwrap();
+        let file = temp.join("left.txt");
+        fs::write(&file, "x").unwrap();
+        let options = DeleteOptions::new().verify(true);
+
+        match verify_removed(&temp, &options, Ok(DeletionStats::default())) {
+            Err(Error::PartialFailure { failed, errors, .. }) => {
+                assert_eq!(failed, 2);
+                assert!(errors.iter().any(|e| e.path == file && !e.is_dir));
+                assert!(errors.iter().any(|e| e.path == temp && e.is_dir));
+            }
+            other => panic!("expected survivors, got {:?}", other),
+        }
+
+        // A tracked failure explains itself and every directory above it
+        let tracked = Err(Error::PartialFailure {
+            total: 2,
+            failed: 1,
+            errors: vec![FailedItem {
+                path: file.clone(),
+                error: "locked".to_string(),
+                is_dir: false,
+                kind: ErrorKind::Locked,
+            }],
+        });
+        match verify_removed(&temp, &options, tracked) {
+            Err(Error::PartialFailure { failed, .. }) => assert_eq!(failed, 1),
+            other => panic!("expected the original failure, got {:?}", other),
+        }
+
+        fs::remove_dir_all(&temp).unwrap();
+        assert!(verify_removed(&temp, &options, Ok(DeletionStats::default())).is_ok());
+    }
+
+    #[test]
+    fn test_delete_alias_matches_delete_tree() {
+        let temp = std::env::temp_dir().join("rmx_api_delete_alias");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("x")).unwrap();
+        fs::write(temp.join("x/f.txt"), "f").unwrap();
+
+        let stats = crate::delete(&temp, &DeleteOptions::new().threads(1)).unwrap();
+        assert_eq!(stats.total_items(), 3);
+        assert!(!temp.exists());
+    }
+
+    #[test]
+    fn test_progress_bar_line() {
+        let line = progress_bar_line(50, 200, Duration::from_secs(10));
+        assert!(
+            line.starts_with("[######------------------]  25%"),
+            "{}",
+            line
+        );
+        assert!(line.contains(" 5 dirs/s"), "{}", line);
+        assert!(line.ends_with("ETA   0:30"), "{}", line);
+
+        let line = progress_bar_line(0, 10, Duration::ZERO);
+        assert!(line.ends_with("ETA  --:--"), "{}", line);
+    }
+
+    #[test]
+    fn test_progress_callback_sees_every_entry() {
+        use std::sync::atomic::AtomicUsize;
+
+        let temp = std::env::temp_dir().join("rmx_api_progress_callback");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("a/b")).unwrap();
+        for i in 0..40 {
+            fs::write(temp.join(format!("a/{}.txt", i)), "x").unwrap();
+        }
+        fs::write(temp.join("a/b/last.txt"), "x").unwrap();
+
+        let files = Arc::new(AtomicUsize::new(0));
+        let dirs = Arc::new(AtomicUsize::new(0));
+        let (f, d) = (files.clone(), dirs.clone());
+        let options = DeleteOptions::new()
+            .threads(2)
+            .progress_callback(move |event| match event {
+                ProgressEvent::FileDeleted { .. } => {
+                    f.fetch_add(1, Ordering::Relaxed);
+                }
+                ProgressEvent::DirCompleted { .. } => {
+                    d.fetch_add(1, Ordering::Relaxed);
+                }
+                ProgressEvent::Failed { path, error } => {
+                    panic!("{}: {}", path.display(), error)
+                }
+                ProgressEvent::Notice { .. } => {}
+            });
+
+        delete_tree(&temp, &options).unwrap();
+        assert_eq!(files.load(Ordering::Relaxed), 41);
+        assert_eq!(dirs.load(Ordering::Relaxed), 3);
+        assert!(!temp.exists());
+    }
+
+    #[test]
+    fn test_progress_sink_reports_completion() {
+        use parking_lot::Mutex;
+
+        #[derive(Default)]
+        struct Recorder {
+            last: Mutex<Option<(usize, usize)>>,
+            finished: AtomicBool,
+        }
+
+        impl ProgressSink for Recorder {
+            fn on_dir_complete(&self, completed: usize, total: usize) {
+                *self.last.lock() = Some((completed, total));
+            }
+
+            fn on_error(&self, failure: &FailedItem) {
+                panic!("{}: {}", failure.path.display(), failure.error)
+            }
+
+            fn on_finish(&self) {
+                self.finished.store(true, Ordering::Relaxed);
+            }
+        }
+
+        let temp = std::env::temp_dir().join("rmx_api_progress_sink");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("a/b/c")).unwrap();
+        fs::write(temp.join("a/b/file.txt"), "x").unwrap();
+
+        // A callback next to the sink: both see the run
+        let files = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = files.clone();
+        let recorder = Arc::new(Recorder::default());
+        let options = DeleteOptions::new()
+            .threads(2)
+            .progress_sink(recorder.clone())
+            .progress_callback(move |event| {
+                if let ProgressEvent::FileDeleted { .. } = event {
+                    counted.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        delete_tree(&temp, &options).unwrap();
+
+        assert_eq!(files.load(Ordering::Relaxed), 1);
+        assert_eq!(*recorder.last.lock(), Some((4, 4)));
+        assert!(recorder.finished.load(Ordering::Relaxed));
+        assert!(!temp.exists());
+    }
+}
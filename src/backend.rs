@@ -0,0 +1,82 @@
+//! How workers remove a single file or an emptied directory. The scan, the
+//! scheduling and the lock handling around it are the same for every
+//! backend.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::winapi::{self, RetryPolicy};
+
+/// Removes one entry at a time, from any worker thread
+pub trait DeleteBackend: Send + Sync {
+    fn delete_file(&self, path: &Path, policy: &RetryPolicy) -> io::Result<()>;
+
+    /// `path` is already empty of everything selected
+    fn remove_dir(&self, path: &Path, policy: &RetryPolicy) -> io::Result<()>;
+}
+
+/// Raw handles with POSIX delete semantics (the default)
+pub struct PosixBackend;
+
+impl DeleteBackend for PosixBackend {
+    fn delete_file(&self, path: &Path, policy: &RetryPolicy) -> io::Result<()> {
+        winapi::delete_file_with(path, policy)
+    }
+
+    fn remove_dir(&self, path: &Path, policy: &RetryPolicy) -> io::Result<()> {
+        winapi::remove_dir_with(path, policy)
+    }
+}
+
+/// Explorer's `IFileOperation`: sends shell notifications and lets the
+/// shell prompt for elevation, at the cost of one shell operation per entry
+pub struct ShellBackend;
+
+impl DeleteBackend for ShellBackend {
+    fn delete_file(&self, path: &Path, policy: &RetryPolicy) -> io::Result<()> {
+        winapi::shell_delete_with(path, policy)
+    }
+
+    fn remove_dir(&self, path: &Path, policy: &RetryPolicy) -> io::Result<()> {
+        winapi::shell_delete_with(path, policy)
+    }
+}
+
+/// Backend selected with `--backend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Backend {
+    #[default]
+    Posix,
+    /// Windows only
+    Shell,
+}
+
+impl Backend {
+    pub fn instance(self) -> Arc<dyn DeleteBackend> {
+        match self {
+            Backend::Posix => Arc::new(PosixBackend),
+            Backend::Shell => Arc::new(ShellBackend),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_posix_backend_removes_file_then_dir() {
+        let temp = std::env::temp_dir().join("rmx_backend_posix");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(&temp).unwrap();
+        fs::write(temp.join("file.txt"), "x").unwrap();
+
+        let backend = Backend::default().instance();
+        let policy = RetryPolicy::default();
+        backend.delete_file(&temp.join("file.txt"), &policy).unwrap();
+        backend.remove_dir(&temp, &policy).unwrap();
+        assert!(!temp.exists());
+    }
+}
@@ -1,14 +1,56 @@
 use crate::tree::DirectoryTree;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use dashmap::DashMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
 /// Threshold: directories with more files than this get split into batches
 const BATCH_THRESHOLD: usize = 1024;
 /// Number of files per batch when splitting large directories
 const BATCH_SIZE: usize = 256;
+/// Deleting a file costs about as much as this much of its data, so a
+/// batch of files this size holds `batch_size` files
+const REFERENCE_FILE_BYTES: u64 = 64 * 1024;
+
+/// Overrides the batch size, like the hidden `--batch-size`
+pub const BATCH_SIZE_ENV: &str = "RMX_BATCH_SIZE";
+
+/// How the broker splits large directories into `DeleteFiles` batches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrokerConfig {
+    /// Directories with more files than this are split into batches
+    pub batch_threshold: usize,
+    /// Files per batch; at least 1
+    pub batch_size: usize,
+    /// Scale `batch_size` by the directory's average file size: up to twice
+    /// as many tiny files per batch, down to a quarter as many large ones
+    pub adaptive: bool,
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        Self {
+            batch_threshold: BATCH_THRESHOLD,
+            batch_size: BATCH_SIZE,
+            adaptive: true,
+        }
+    }
+}
+
+impl BrokerConfig {
+    /// Files per batch for a directory of `files` files holding `bytes`
+    fn batch_size_for(&self, files: usize, bytes: Option<u64>) -> usize {
+        let (Some(bytes), true) = (bytes, self.adaptive && files > 0) else {
+            return self.batch_size;
+        };
+        let average = bytes / files as u64;
+        let scaled =
+            self.batch_size as u64 * 2 * REFERENCE_FILE_BYTES / (REFERENCE_FILE_BYTES + average);
+        (scaled as usize).clamp((self.batch_size / 4).max(1), self.batch_size * 2)
+    }
+}
 
 /// Work item dispatched through the broker channel.
 pub enum WorkItem {
@@ -33,6 +75,10 @@ pub struct Broker {
     /// Plain HashMap avoids DashMap overhead for read-only data.
     parent_map: HashMap<PathBuf, PathBuf>,
     dir_files: DashMap<PathBuf, Vec<PathBuf>>,
+    /// File bytes per directory, for [`BrokerConfig::adaptive`]; read-only
+    dir_bytes: HashMap<PathBuf, u64>,
+    /// Directories that still contain filtered-out entries and must not be removed.
+    kept_dirs: HashSet<PathBuf>,
     /// Tracks in-flight file batches per directory.
     pending_batches: DashMap<PathBuf, AtomicUsize>,
     /// Direct sender — no Mutex wrapper. crossbeam Sender is already thread-safe.
@@ -40,12 +86,26 @@ pub struct Broker {
     total_dirs: usize,
     /// Number of worker threads, used to send Shutdown sentinels.
     worker_count: usize,
+    config: BrokerConfig,
     completed: AtomicUsize,
     done: AtomicBool,
+    /// Once set, completions no longer schedule parent directories.
+    cancel: Arc<AtomicBool>,
+    /// Set on the first failure with fail-fast; like `cancel`, nothing new is
+    /// scheduled, but the run reports its failures instead of `Cancelled`.
+    abort: AtomicBool,
 }
 
 impl Broker {
     pub fn new(tree: DirectoryTree, worker_count: usize) -> (Self, Receiver<WorkItem>) {
+        Self::with_config(tree, worker_count, BrokerConfig::default())
+    }
+
+    pub fn with_config(
+        tree: DirectoryTree,
+        worker_count: usize,
+        config: BrokerConfig,
+    ) -> (Self, Receiver<WorkItem>) {
         let (tx, rx) = unbounded();
 
         let child_counts = DashMap::new();
@@ -69,12 +129,20 @@ impl Broker {
             child_counts,
             parent_map,
             dir_files,
+            dir_bytes: tree.dir_bytes,
+            kept_dirs: tree.kept_dirs,
             pending_batches: DashMap::new(),
             work_tx: tx,
             total_dirs,
             worker_count,
+            config: BrokerConfig {
+                batch_size: config.batch_size.max(1),
+                ..config
+            },
             completed: AtomicUsize::new(0),
             done: AtomicBool::new(false),
+            cancel: Arc::new(AtomicBool::new(false)),
+            abort: AtomicBool::new(false),
         };
 
         // Schedule initial leaf directories (may batch large ones)
@@ -85,10 +153,40 @@ impl Broker {
         (broker, rx)
     }
 
+    /// Share a cancellation flag with the workers.
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Stop scheduling new work; workers drain the queue and exit.
+    pub fn abort(&self) {
+        self.abort.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.abort.load(Ordering::Relaxed)
+    }
+
+    /// Cancelled or aborted: the remaining directories will never complete.
+    pub fn is_stopped(&self) -> bool {
+        self.is_cancelled() || self.is_aborted()
+    }
+
     pub fn take_files(&self, dir: &PathBuf) -> Option<Vec<PathBuf>> {
         self.dir_files.remove(dir).map(|(_, files)| files)
     }
 
+    /// Returns `true` if `dir` holds entries excluded from deletion, so only
+    /// its selected contents are removed and the directory itself stays.
+    pub fn is_kept(&self, dir: &PathBuf) -> bool {
+        self.kept_dirs.contains(dir)
+    }
+
     pub fn new_dirs_only(tree: DirectoryTree, worker_count: usize) -> (Self, Receiver<WorkItem>) {
         let (tx, rx) = unbounded();
 
@@ -108,12 +206,17 @@ impl Broker {
             child_counts,
             parent_map,
             dir_files: DashMap::new(),
+            dir_bytes: HashMap::new(),
+            kept_dirs: tree.kept_dirs,
             pending_batches: DashMap::new(),
             work_tx: tx.clone(),
             total_dirs,
             worker_count,
+            config: BrokerConfig::default(),
             completed: AtomicUsize::new(0),
             done: AtomicBool::new(false),
+            cancel: Arc::new(AtomicBool::new(false)),
+            abort: AtomicBool::new(false),
         };
 
         for leaf in tree.leaves {
@@ -125,20 +228,30 @@ impl Broker {
 
     /// Decide how to dispatch a directory that is ready for processing.
     ///
-    /// - Small directory (≤ BATCH_THRESHOLD files): send a single `ProcessDir`.
-    /// - Large directory (> BATCH_THRESHOLD files): split files into batches,
-    ///   send `DeleteFiles` for each chunk, and defer `ProcessDir` until all
-    ///   batches complete.
+    /// Only called for leaves or once every child directory has completed, so
+    /// file batches of a directory never run ahead of its subdirectories and
+    /// the trailing `ProcessDir` always finds the directory empty.
+    ///
+    /// - Small directory (≤ `batch_threshold` files): send a single `ProcessDir`.
+    /// - Large directory (> `batch_threshold` files): split files into batches
+    ///   (see [`BrokerConfig::adaptive`]), send `DeleteFiles` for each chunk,
+    ///   and defer `ProcessDir` until all batches complete.
     fn schedule_directory(&self, dir: &PathBuf) {
+        if self.is_aborted() {
+            return;
+        }
         let file_count = self.dir_files.get(dir).map(|f| f.len()).unwrap_or(0);
 
-        if file_count > BATCH_THRESHOLD {
+        if file_count > self.config.batch_threshold {
             if let Some((_, files)) = self.dir_files.remove(dir) {
-                let batch_count = files.len().div_ceil(BATCH_SIZE);
+                let batch_size = self
+                    .config
+                    .batch_size_for(files.len(), self.dir_bytes.get(dir).copied());
+                let batch_count = files.len().div_ceil(batch_size);
                 self.pending_batches
                     .insert(dir.clone(), AtomicUsize::new(batch_count));
 
-                for chunk in files.chunks(BATCH_SIZE) {
+                for chunk in files.chunks(batch_size) {
                     self.work_tx
                         .send(WorkItem::DeleteFiles {
                             files: chunk.to_vec(),
@@ -168,6 +281,10 @@ impl Broker {
     }
 
     pub fn mark_complete(&self, dir: PathBuf) {
+        if self.is_stopped() {
+            return;
+        }
+
         let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
 
         if completed == self.total_dirs {
@@ -209,3 +326,139 @@ impl Broker {
         self.total_dirs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_batched_dir_with_subdirs_waits_for_children() {
+        let root = PathBuf::from("root");
+        let subdirs: Vec<PathBuf> = (0..10).map(|i| root.join(format!("sub{}", i))).collect();
+        let files: Vec<PathBuf> = (0..5000)
+            .map(|i| root.join(format!("file{}.txt", i)))
+            .collect();
+
+        let mut tree = DirectoryTree::new();
+        tree.dirs.push(root.clone());
+        tree.dirs.extend(subdirs.iter().cloned());
+        tree.children.insert(root.clone(), subdirs.clone());
+        tree.leaves = subdirs.clone();
+        tree.file_count = files.len();
+        tree.dir_files.insert(root.clone(), files);
+
+        let (broker, rx) = Broker::new(tree, 1);
+
+        let mut removed: HashSet<PathBuf> = HashSet::new();
+        let mut root_batches = 0;
+        loop {
+            match rx.try_recv().expect("broker stalled before shutdown") {
+                WorkItem::DeleteFiles { files, parent_dir } => {
+                    assert_eq!(parent_dir, root);
+                    assert!(files.len() <= BATCH_SIZE);
+                    assert!(
+                        subdirs.iter().all(|d| removed.contains(d)),
+                        "root batched before its subdirectories completed"
+                    );
+                    root_batches += 1;
+                    broker.mark_batch_complete(&parent_dir);
+                }
+                WorkItem::ProcessDir(dir) => {
+                    if dir == root {
+                        assert!(subdirs.iter().all(|d| removed.contains(d)));
+                        assert_eq!(root_batches, 5000usize.div_ceil(BATCH_SIZE));
+                        assert!(broker.take_files(&dir).is_none());
+                    }
+                    removed.insert(dir.clone());
+                    broker.mark_complete(dir);
+                }
+                WorkItem::Shutdown => break,
+            }
+        }
+
+        assert!(removed.contains(&root));
+        assert_eq!(broker.completed_count(), 11);
+    }
+
+    #[test]
+    fn test_batch_config_controls_splitting() {
+        let root = PathBuf::from("root");
+        let files: Vec<PathBuf> = (0..100)
+            .map(|i| root.join(format!("file{}.txt", i)))
+            .collect();
+        let tree = || {
+            let mut tree = DirectoryTree::new();
+            tree.dirs.push(root.clone());
+            tree.leaves.push(root.clone());
+            tree.file_count = files.len();
+            tree.dir_files.insert(root.clone(), files.clone());
+            tree
+        };
+
+        // Below the default threshold: a single ProcessDir
+        let (_broker, rx) = Broker::new(tree(), 1);
+        assert!(matches!(rx.try_recv(), Ok(WorkItem::ProcessDir(_))));
+
+        let config = BrokerConfig {
+            batch_threshold: 10,
+            batch_size: 30,
+            adaptive: false,
+        };
+        let (_broker, rx) = Broker::with_config(tree(), 1, config);
+        let sizes: Vec<usize> = rx
+            .try_iter()
+            .map(|item| match item {
+                WorkItem::DeleteFiles { files, .. } => files.len(),
+                _ => panic!("expected only file batches"),
+            })
+            .collect();
+        assert_eq!(sizes, vec![30, 30, 30, 10]);
+    }
+
+    #[test]
+    fn test_cancel_stops_scheduling_parents() {
+        let root = PathBuf::from("root");
+        let child = root.join("child");
+
+        let mut tree = DirectoryTree::new();
+        tree.dirs = vec![root.clone(), child.clone()];
+        tree.children.insert(root.clone(), vec![child.clone()]);
+        tree.leaves = vec![child.clone()];
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (broker, rx) = Broker::new(tree, 1);
+        let broker = broker.with_cancel(cancel.clone());
+
+        assert!(matches!(rx.try_recv(), Ok(WorkItem::ProcessDir(d)) if d == child));
+        cancel.store(true, Ordering::Relaxed);
+        broker.mark_complete(child);
+
+        assert!(rx.try_recv().is_err(), "parent scheduled after cancel");
+        assert_eq!(broker.completed_count(), 0);
+    }
+
+    #[test]
+    fn test_adaptive_batch_size_follows_file_size() {
+        let config = BrokerConfig::default();
+        let files = 2000;
+        // Tiny files: twice the batch, large ones: a quarter
+        assert_eq!(config.batch_size_for(files, Some(files as u64 * 10)), 511);
+        assert_eq!(config.batch_size_for(files, Some(0)), 512);
+        assert_eq!(
+            config.batch_size_for(files, Some(files as u64 * REFERENCE_FILE_BYTES)),
+            BATCH_SIZE
+        );
+        assert_eq!(
+            config.batch_size_for(files, Some(files as u64 * 8 * 1024 * 1024)),
+            BATCH_SIZE / 4
+        );
+        // No size hint, or a fixed size
+        assert_eq!(config.batch_size_for(files, None), BATCH_SIZE);
+        let fixed = BrokerConfig {
+            adaptive: false,
+            ..config
+        };
+        assert_eq!(fixed.batch_size_for(files, Some(0)), BATCH_SIZE);
+    }
+}
@@ -0,0 +1,87 @@
+//! `rmx clean`: find well-known build and cache directories below a root so
+//! they can be deleted in one go.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ::glob::Pattern;
+
+use crate::{glob, safety, winapi};
+
+/// Directory names `rmx clean` looks for unless `--pattern` is given
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    "node_modules",
+    "target",
+    ".venv",
+    "__pycache__",
+    "dist",
+    "build",
+];
+
+/// Directories below `root` whose name matches one of `patterns`, sorted.
+/// Matches are not searched any further, and links and system folders are
+/// never entered. Only an unreadable `root` is an error.
+pub fn find_artifacts(root: &Path, patterns: &[Pattern]) -> io::Result<Vec<PathBuf>> {
+    let options = glob::match_options();
+    let mut found = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let result = winapi::enumerate_files(&dir, |entry| {
+            if !entry.is_dir || entry.is_symlink {
+                return Ok(());
+            }
+            let name = entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if patterns.iter().any(|p| p.matches_with(&name, options)) {
+                found.push(entry.path);
+            } else if !safety::is_protected_system_folder(&entry.path) {
+                pending.push(entry.path);
+            }
+            Ok(())
+        });
+        if dir == root {
+            result?;
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_artifacts_stops_at_matches() {
+        let temp = std::env::temp_dir().join("rmx_clean_find");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("web/node_modules/pkg/node_modules")).unwrap();
+        fs::create_dir_all(temp.join("crate/target/debug")).unwrap();
+        fs::create_dir_all(temp.join("crate/src")).unwrap();
+        fs::write(temp.join("dist"), "a file, not a directory").unwrap();
+
+        let patterns: Vec<Pattern> = DEFAULT_PATTERNS
+            .iter()
+            .map(|p| Pattern::new(p).unwrap())
+            .collect();
+        let found = find_artifacts(&temp, &patterns).unwrap();
+        assert_eq!(
+            found,
+            vec![temp.join("crate/target"), temp.join("web/node_modules")]
+        );
+
+        let custom = [Pattern::new("s*").unwrap()];
+        assert_eq!(
+            find_artifacts(&temp, &custom).unwrap(),
+            vec![temp.join("crate/src")]
+        );
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+}
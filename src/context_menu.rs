@@ -172,7 +172,7 @@ fn cleanup_legacy_entries() {
 }
 
 fn get_shell_dll_path() -> io::Result<PathBuf> {
-    let exe_dir = std::env::current_exe()?
+    let exe_dir = crate::upgrade::current_exe_path()?
         .parent()
         .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "Cannot determine exe directory"))?
         .to_path_buf();
@@ -209,7 +209,7 @@ where
     F: Fn() -> io::Result<()>,
 {
     // Step 1: Restart Manager 快速查找占用进程并杀掉（毫秒级）
-    let _ = winapi::kill_locking_processes(dll_path, false);
+    let _ = winapi::kill_locking_processes(dll_path, false, None);
     std::thread::sleep(std::time::Duration::from_millis(50));
 
     match op() {
@@ -19,6 +19,16 @@ pub enum Error {
         failed: usize,
         errors: Vec<FailedItem>,
     },
+    /// `--recycle` was requested but the volume has no Recycle Bin
+    NoRecycleBin {
+        path: PathBuf,
+    },
+    /// Stopped via the cancellation flag before finishing
+    Cancelled,
+    InvalidPattern {
+        pattern: String,
+        reason: String,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -26,8 +36,60 @@ pub struct FailedItem {
     pub path: PathBuf,
     pub error: String,
     pub is_dir: bool,
+    pub kind: ErrorKind,
+}
+
+/// Why an item could not be deleted, from its OS error code
+/// (see [`classify_error`](crate::winapi::classify_error)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Another process holds the file open (sharing or lock violation)
+    Locked,
+    AccessDenied,
+    NotFound,
+    /// A directory still had entries when it was removed
+    DirNotEmpty,
+    PathTooLong,
+    /// Any other failure, with its raw OS error code (0 if there was none)
+    Other(i32),
+}
+
+impl ErrorKind {
+    /// Stable name used in `--json` output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Locked => "locked",
+            ErrorKind::AccessDenied => "access_denied",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::DirNotEmpty => "dir_not_empty",
+            ErrorKind::PathTooLong => "path_too_long",
+            ErrorKind::Other(_) => "other",
+        }
+    }
 }
 
+/// Payload of the `io::Error` returned for a path longer than Windows can
+/// address even with the `\\?\` prefix; classified as
+/// [`ErrorKind::PathTooLong`]
+#[derive(Debug)]
+pub struct PathTooLongError {
+    /// Length of the offending path in UTF-16 code units
+    pub len: usize,
+    pub max: usize,
+}
+
+impl fmt::Display for PathTooLongError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "path is {} characters long; Windows allows at most {}",
+            self.len, self.max
+        )
+    }
+}
+
+impl std::error::Error for PathTooLongError {}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -48,6 +110,17 @@ impl fmt::Display for Error {
                     failed, total
                 )
             }
+            Error::NoRecycleBin { path } => {
+                write!(
+                    f,
+                    "'{}' is on a volume without a Recycle Bin; refusing to delete permanently (drop --recycle to delete)",
+                    path.display()
+                )
+            }
+            Error::Cancelled => write!(f, "operation cancelled"),
+            Error::InvalidPattern { pattern, reason } => {
+                write!(f, "Invalid pattern '{}': {}", pattern, reason)
+            }
         }
     }
 }
@@ -70,6 +143,41 @@ impl From<io::Error> for Error {
     }
 }
 
+/// Exit statuses of the `rmx` binary. The values are part of its interface
+/// and don't change between releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success = 0,
+    /// A missing or refused operand, or invalid arguments
+    InvalidInput = 1,
+    /// An operand failed as a whole with an I/O error
+    IoError = 2,
+    /// Some entries were deleted and others were not
+    PartialFailure = 3,
+    /// Entries failed and none were deleted
+    AllFailed = 4,
+    /// Interrupted with Ctrl+C or cancelled from the progress window
+    Cancelled = 130,
+}
+
+impl ExitCode {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Status for the outcome of a whole run. With `partial_ok`
+    /// (`--summary-on-partial`) a partial failure counts as success.
+    pub fn for_result(result: &Result<()>, partial_ok: bool) -> Self {
+        match result {
+            Ok(()) => ExitCode::Success,
+            Err(e) if partial_ok && e.exit_status() == ExitCode::PartialFailure => {
+                ExitCode::Success
+            }
+            Err(e) => e.exit_status(),
+        }
+    }
+}
+
 impl Error {
     pub fn io_with_path(path: PathBuf, source: io::Error) -> Self {
         Error::Io {
@@ -78,11 +186,80 @@ impl Error {
         }
     }
 
-    pub fn exit_code(&self) -> i32 {
+    /// Process exit status for the CLI (see [`ExitCode`])
+    pub fn exit_status(&self) -> ExitCode {
         match self {
-            Error::Io { .. } => 2,
-            Error::InvalidPath { .. } => 1,
-            Error::PartialFailure { .. } => 1,
+            Error::Io { .. } => ExitCode::IoError,
+            Error::InvalidPath { .. } => ExitCode::InvalidInput,
+            Error::PartialFailure { total, failed, .. } if failed >= total => ExitCode::AllFailed,
+            Error::PartialFailure { .. } => ExitCode::PartialFailure,
+            Error::NoRecycleBin { .. } => ExitCode::InvalidInput,
+            Error::Cancelled => ExitCode::Cancelled,
+            Error::InvalidPattern { .. } => ExitCode::InvalidInput,
         }
     }
+
+    /// [`Self::exit_status`] as a number; see [`ExitCode`] for the values.
+    pub fn exit_code(&self) -> i32 {
+        self.exit_status().code()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let invalid = Error::InvalidPath {
+            path: PathBuf::from("missing"),
+            reason: "No such file or directory".to_string(),
+        };
+        let io = Error::from(io::Error::other("disk gone"));
+        let partial = Error::PartialFailure {
+            total: 2,
+            failed: 1,
+            errors: Vec::new(),
+        };
+        assert_eq!(invalid.exit_code(), 1);
+        assert_eq!(io.exit_code(), 2);
+        assert_eq!(partial.exit_code(), 3);
+        let all = Error::PartialFailure {
+            total: 2,
+            failed: 2,
+            errors: Vec::new(),
+        };
+        assert_eq!(all.exit_code(), 4);
+        assert_eq!(Error::Cancelled.exit_code(), 130);
+    }
+
+    #[test]
+    fn test_summary_on_partial_only_forgives_partial_failures() {
+        let partial = || {
+            Err(Error::PartialFailure {
+                total: 2,
+                failed: 1,
+                errors: Vec::new(),
+            })
+        };
+        assert_eq!(ExitCode::for_result(&Ok(()), false), ExitCode::Success);
+        assert_eq!(
+            ExitCode::for_result(&partial(), false),
+            ExitCode::PartialFailure
+        );
+        assert_eq!(ExitCode::for_result(&partial(), true), ExitCode::Success);
+        // Nothing deleted at all is not a partial result
+        let all_failed = Err(Error::PartialFailure {
+            total: 3,
+            failed: 3,
+            errors: Vec::new(),
+        });
+        assert_eq!(ExitCode::for_result(&all_failed, true), ExitCode::AllFailed);
+        let io = Err(Error::from(io::Error::other("disk gone")));
+        assert_eq!(ExitCode::for_result(&io, true), ExitCode::IoError);
+        assert_eq!(
+            ExitCode::for_result(&Err(Error::Cancelled), true).code(),
+            130
+        );
+    }
 }
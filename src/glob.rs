@@ -0,0 +1,131 @@
+//! Wildcard expansion for path operands. cmd.exe and PowerShell pass `*`,
+//! `?` and `[...]` through unexpanded, so rmx expands them itself.
+
+use std::path::{Component, Path, PathBuf};
+
+use ::glob::{MatchOptions, Pattern};
+
+use crate::winapi;
+
+pub fn has_wildcards(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+pub(crate) fn match_options() -> MatchOptions {
+    MatchOptions {
+        case_sensitive: !cfg!(windows),
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    }
+}
+
+/// Expand wildcards in every path. Paths without wildcards are kept as
+/// given, and so are patterns that match nothing, so the caller reports them
+/// like any other missing path.
+pub fn expand_paths(patterns: &[PathBuf]) -> Vec<PathBuf> {
+    let mut expanded = Vec::new();
+    for pattern in patterns {
+        let matches = expand_pattern(pattern);
+        if matches.is_empty() {
+            expanded.push(pattern.clone());
+        } else {
+            expanded.extend(matches);
+        }
+    }
+    expanded
+}
+
+/// Matches are sorted by name within each directory.
+fn expand_pattern(pattern: &Path) -> Vec<PathBuf> {
+    if !has_wildcards(&pattern.to_string_lossy()) {
+        return vec![pattern.to_path_buf()];
+    }
+
+    let options = match_options();
+    let mut current = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let compiled = match component {
+            Component::Normal(name) if has_wildcards(&name.to_string_lossy()) => {
+                // An unbalanced `[` is just part of the name
+                Pattern::new(&name.to_string_lossy()).ok()
+            }
+            _ => None,
+        };
+
+        let Some(compiled) = compiled else {
+            for path in &mut current {
+                path.push(component);
+            }
+            continue;
+        };
+
+        let mut next = Vec::new();
+        for dir in &current {
+            let search = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir.as_path()
+            };
+            let mut names = Vec::new();
+            // Unreadable directories and files in the middle of a pattern
+            // simply match nothing
+            let _ = winapi::enumerate_files(search, |entry| {
+                if let Some(name) = entry.path.file_name() {
+                    if compiled.matches_with(&name.to_string_lossy(), options) {
+                        names.push(dir.join(name));
+                    }
+                }
+                Ok(())
+            });
+            names.sort();
+            next.extend(names);
+        }
+        current = next;
+        if current.is_empty() {
+            break;
+        }
+    }
+
+    current.retain(|p| winapi::path_exists(p));
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_expand_paths() {
+        let temp = std::env::temp_dir().join("rmx_glob_expand");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("build_1/out")).unwrap();
+        fs::create_dir_all(temp.join("build_2/out")).unwrap();
+        fs::write(temp.join("build_1/a.log"), "").unwrap();
+        fs::write(temp.join("build_1/b.log"), "").unwrap();
+        fs::write(temp.join("build_1/c.txt"), "").unwrap();
+
+        let expanded = expand_paths(&[temp.join("build_1/*.log")]);
+        assert_eq!(
+            expanded,
+            vec![temp.join("build_1/a.log"), temp.join("build_1/b.log")]
+        );
+
+        // Wildcards in the middle of the path
+        let expanded = expand_paths(&[temp.join("build_[0-9]/out")]);
+        assert_eq!(
+            expanded,
+            vec![temp.join("build_1/out"), temp.join("build_2/out")]
+        );
+
+        // Literal paths and unmatched patterns pass through unchanged
+        let literal = temp.join("missing.txt");
+        let unmatched = temp.join("*.none");
+        assert_eq!(
+            expand_paths(&[literal.clone(), unmatched.clone()]),
+            vec![literal, unmatched]
+        );
+
+        fs::remove_dir_all(&temp).ok();
+    }
+}
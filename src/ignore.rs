@@ -0,0 +1,157 @@
+//! `.rmxignore`: per-project glob patterns for entries a recursive delete
+//! leaves in place, in the spirit of `.gitignore`.
+//!
+//! One pattern per line. Blank lines and lines starting with `#` are
+//! ignored, `!pattern` deletes an entry again that an earlier pattern kept,
+//! and the last matching line wins. Patterns containing `/` match the path
+//! relative to the directory holding the file, others match the name at any
+//! depth.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ::glob::Pattern;
+
+pub const IGNORE_FILE: &str = ".rmxignore";
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    anchored: bool,
+    negated: bool,
+}
+
+/// Rules from a single `.rmxignore`, relative to `base`
+#[derive(Debug, Clone)]
+struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+/// Compiled `.rmxignore` rules for one deletion root
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    /// Outermost first, so files closer to the entry take precedence
+    files: Vec<IgnoreFile>,
+}
+
+impl IgnoreRules {
+    /// Read `.rmxignore` from `root`, and with `parents` also from every
+    /// ancestor up to the drive root. Missing files are not an error.
+    pub fn load(root: &Path, parents: bool) -> io::Result<Self> {
+        let mut files = Vec::new();
+        let dirs: Vec<&Path> = if parents {
+            root.ancestors().collect()
+        } else {
+            vec![root]
+        };
+        for dir in dirs.into_iter().rev() {
+            let path = dir.join(IGNORE_FILE);
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let rules = parse(&text).map_err(|(line, e)| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: invalid pattern '{}': {}", path.display(), line, e.msg),
+                )
+            })?;
+            files.push(IgnoreFile {
+                base: dir.to_path_buf(),
+                rules,
+            });
+        }
+        Ok(Self { files })
+    }
+
+    /// Build rules from the contents of a `.rmxignore` in `base`.
+    pub fn from_text(base: &Path, text: &str) -> Result<Self, ::glob::PatternError> {
+        let rules = parse(text).map_err(|(_, e)| e)?;
+        Ok(Self {
+            files: vec![IgnoreFile {
+                base: base.to_path_buf(),
+                rules,
+            }],
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.iter().all(|f| f.rules.is_empty())
+    }
+
+    /// Returns `true` if `path` must be left in place.
+    pub fn ignores(&self, path: &Path) -> bool {
+        let options = crate::glob::match_options();
+        let mut ignored = false;
+        for file in &self.files {
+            let Ok(relative) = path.strip_prefix(&file.base) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let name = relative.rsplit('/').next().unwrap_or(&relative);
+            for rule in &file.rules {
+                let target = if rule.anchored { &relative[..] } else { name };
+                if rule.pattern.matches_with(target, options) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+fn parse(text: &str) -> Result<Vec<Rule>, (String, ::glob::PatternError)> {
+    let mut rules = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (negated, body) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        // Directory markers don't change matching; a leading `/` anchors
+        let body = body.trim_end_matches('/');
+        let anchored = body.contains('/');
+        let body = body.trim_start_matches('/');
+        let pattern = Pattern::new(body).map_err(|e| (line.to_string(), e))?;
+        rules.push(Rule {
+            pattern,
+            anchored,
+            negated,
+        });
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negation_comments_and_anchoring() {
+        let base = Path::new("root");
+        let rules = IgnoreRules::from_text(
+            base,
+            "# keep lock files\n*.lock\n!old.lock\n\n/build/\ndocs/*.md\n",
+        )
+        .unwrap();
+
+        assert!(rules.ignores(&base.join("a.lock")));
+        assert!(rules.ignores(&base.join("deep/b.lock")));
+        assert!(!rules.ignores(&base.join("deep/old.lock")));
+        assert!(rules.ignores(&base.join("build")));
+        assert!(!rules.ignores(&base.join("src/build")));
+        assert!(rules.ignores(&base.join("docs/readme.md")));
+        assert!(!rules.ignores(&base.join("readme.md")));
+        assert!(!rules.ignores(Path::new("elsewhere/a.lock")));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        assert!(IgnoreRules::from_text(Path::new("root"), "[z-a\n").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Resumable deletion log for `--journal`. Every directory a run finishes is
+//! appended to the journal, one path per line; a later run with the same
+//! journal leaves those directories unscanned (see [`ScanFilter::journaled`]).
+//!
+//! Writes are buffered and flushed at most [`FLUSH_INTERVAL`] apart, on
+//! drop, and from a panic hook and (on Windows) a console Ctrl+C handler, so
+//! a killed run loses little more than the directories in flight.
+//!
+//! [`ScanFilter::journaled`]: crate::tree::ScanFilter::journaled
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Once, Weak};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the panic hook and Ctrl+C handler wait for a busy writer
+const EMERGENCY_LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+struct JournalWriter {
+    out: BufWriter<File>,
+    last_flush: Instant,
+}
+
+/// Writers of every open journal, for the panic hook and Ctrl+C handler
+static OPEN_WRITERS: Mutex<Vec<Weak<Mutex<JournalWriter>>>> = Mutex::new(Vec::new());
+static INSTALL_HOOKS: Once = Once::new();
+
+pub struct Journal {
+    /// Directories recorded by earlier runs
+    completed: Arc<HashSet<PathBuf>>,
+    writer: Arc<Mutex<JournalWriter>>,
+    /// Ancestors of failed entries; never recorded, so the next run retries them
+    tainted: Mutex<HashSet<PathBuf>>,
+}
+
+impl Journal {
+    /// Load the directories already recorded in `path` and open it for
+    /// appending, creating it if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let completed = match fs::read_to_string(path) {
+            Ok(text) => text
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let writer = Arc::new(Mutex::new(JournalWriter {
+            out: BufWriter::new(file),
+            last_flush: Instant::now(),
+        }));
+
+        install_hooks();
+        let mut open = OPEN_WRITERS.lock();
+        open.retain(|w| w.strong_count() > 0);
+        open.push(Arc::downgrade(&writer));
+
+        Ok(Self {
+            completed: Arc::new(completed),
+            writer,
+            tainted: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Directories finished by earlier runs
+    pub fn completed(&self) -> Arc<HashSet<PathBuf>> {
+        self.completed.clone()
+    }
+
+    /// Record `dir` as finished unless something below it failed.
+    pub fn record(&self, dir: &Path) {
+        if self.tainted.lock().contains(dir) {
+            return;
+        }
+        let mut writer = self.writer.lock();
+        // A full disk only costs resumability, never the deletion itself
+        let _ = writeln!(writer.out, "{}", dir.to_string_lossy());
+        if writer.last_flush.elapsed() >= FLUSH_INTERVAL {
+            let _ = writer.out.flush();
+            writer.last_flush = Instant::now();
+        }
+    }
+
+    /// Keep every ancestor of the failed `path` out of the journal.
+    pub fn record_failure(&self, path: &Path) {
+        let mut tainted = self.tainted.lock();
+        for ancestor in path.ancestors() {
+            if !tainted.insert(ancestor.to_path_buf()) {
+                break;
+            }
+        }
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        let mut writer = self.writer.lock();
+        writer.last_flush = Instant::now();
+        writer.out.flush()
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl std::fmt::Debug for Journal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Journal")
+            .field("completed", &self.completed.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Best-effort flush of every open journal. A writer stuck mid-write is
+/// skipped rather than waited on forever.
+fn flush_all() {
+    let Some(open) = OPEN_WRITERS.try_lock_for(EMERGENCY_LOCK_TIMEOUT) else {
+        return;
+    };
+    for writer in open.iter().filter_map(Weak::upgrade) {
+        if let Some(mut writer) = writer.try_lock_for(EMERGENCY_LOCK_TIMEOUT) {
+            let _ = writer.out.flush();
+        }
+    }
+}
+
+/// Release builds abort on panic, so `Drop` never runs; flush from the hook.
+fn install_hooks() {
+    INSTALL_HOOKS.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            flush_all();
+            previous(info);
+        }));
+
+        #[cfg(windows)]
+        unsafe {
+            use windows::Win32::System::Console::SetConsoleCtrlHandler;
+            let _ = SetConsoleCtrlHandler(Some(on_console_ctrl), true);
+        }
+    });
+}
+
+/// Flush, then let the default handler terminate the process.
+#[cfg(windows)]
+unsafe extern "system" fn on_console_ctrl(_ctrl_type: u32) -> windows::Win32::Foundation::BOOL {
+    flush_all();
+    windows::Win32::Foundation::FALSE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_resumes_and_skips_failed_ancestors() {
+        let temp = std::env::temp_dir().join("rmx_journal_test");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(&temp).unwrap();
+        let path = temp.join("journal.txt");
+        let root = Path::new("/data/root");
+
+        let journal = Journal::open(&path).unwrap();
+        assert!(journal.completed().is_empty());
+        journal.record_failure(&root.join("b/locked.txt"));
+        journal.record(&root.join("a"));
+        journal.record(&root.join("b"));
+        journal.record(root);
+        drop(journal);
+
+        let journal = Journal::open(&path).unwrap();
+        let completed = journal.completed();
+        assert!(completed.contains(&root.join("a")));
+        assert!(!completed.contains(&root.join("b")));
+        assert!(!completed.contains(root));
+
+        // Reopening appends rather than truncating
+        journal.record(&root.join("c"));
+        drop(journal);
+        assert_eq!(Journal::open(&path).unwrap().completed().len(), 2);
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+}
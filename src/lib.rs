@@ -1,11 +1,25 @@
+pub mod api;
+pub mod backend;
 pub mod broker;
+pub mod clean;
 #[cfg(windows)]
 pub mod context_menu;
 pub mod error;
+pub mod glob;
+pub mod ignore;
+pub mod journal;
+pub mod probe;
+pub mod profile;
 #[cfg(windows)]
 pub mod progress_ui;
 pub mod safety;
+pub mod scan_cache;
+pub mod size;
 pub mod tree;
+pub mod ui_strings;
 pub mod upgrade;
 pub mod winapi;
 pub mod worker;
+
+pub use api::{delete_tree, delete_tree as delete, DeleteOptions, DeletionStats};
+pub use worker::{ProgressEvent, ProgressSink};
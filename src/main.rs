@@ -3,14 +3,22 @@
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 use clap::{Parser, Subcommand};
-use glob::glob;
-use rmx::{broker::Broker, error::Error, safety, tree, worker};
-use std::io::Write;
+use rmx::api::{self, DeleteOptions, DeletionStats};
+use rmx::backend::Backend;
+use rmx::error::{Error, ErrorKind, ExitCode, FailedItem};
+use rmx::probe::ProbeFormat;
+use rmx::size::{format_bytes, format_count};
+use rmx::tree::SkipReason;
+use rmx::winapi::{KillOutcome, RetryPolicy};
+use rmx::ProgressEvent;
+use rmx::{safety, tree};
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 #[cfg(windows)]
 use rmx::progress_ui::{self, DeleteProgress};
@@ -22,6 +30,12 @@ const SKIP_CONFIRM_VALUE: &str = "SkipDeleteConfirm";
 
 const APP_VERSION: &str = env!("APP_VERSION");
 
+/// The confirmation prompt stops counting after this many items
+const CONFIRM_SCAN_LIMIT: usize = 100_000;
+
+/// `-I` only asks when removing more arguments than this (or recursing)
+const INTERACTIVE_ONCE_THRESHOLD: usize = 3;
+
 #[derive(Parser, Debug)]
 #[command(name = "rmx")]
 #[command(version = APP_VERSION)]
@@ -36,14 +50,46 @@ const APP_VERSION: &str = env!("APP_VERSION");
   rmx -f *.log                    Delete all .log files (glob pattern)\n  \
   rmx -f temp_*                   Delete files starting with temp_\n  \
   rmx -rf build_[0-9]*            Delete directories matching pattern\n  \
+  rmx -rf --from-file list.txt    Delete every path listed in list.txt\n  \
+  rmx -rf --json ./target         Print a machine-readable JSON summary\n  \
+   rmx probe --files-from list.txt Classify paths without deleting\n  \
+   rmx size --by-dir ./node_modules Show size per subdirectory\n  \
    rmx init                        Initialize rmx shell extension (install/reinstall)\n  \
-   rmx uninstall                   Remove rmx shell extension")]
+   rmx uninstall                   Remove rmx shell extension\n\n\
+EXIT STATUS:\n  \
+  0    Everything was removed\n  \
+  1    A path was missing or invalid, or was refused\n  \
+  2    An I/O error affected a whole path\n  \
+  3    Some entries were removed but others failed\n  \
+  4    Entries failed and none were removed\n  \
+  130  Cancelled")]
 struct Args {
     #[command(subcommand)]
     command: Option<Command>,
 
+    /// Paths to remove; `-` reads more paths from stdin
     paths: Vec<PathBuf>,
 
+    #[arg(
+        long = "from-file",
+        value_name = "PATH",
+        help = "Read paths to remove from PATH, one per line (UTF-8 or UTF-16)"
+    )]
+    from_file: Option<PathBuf>,
+
+    #[arg(
+        long = "from-stdin",
+        help = "Read paths to remove from stdin, in addition to any PATH operands (same as '-')"
+    )]
+    from_stdin: bool,
+
+    #[arg(
+        short = '0',
+        long = "null",
+        help = "Path lists from --from-file or stdin are NUL-separated instead of newline-separated"
+    )]
+    null: bool,
+
     #[arg(
         short = 'f',
         long = "force",
@@ -51,6 +97,29 @@ struct Args {
     )]
     force: bool,
 
+    #[arg(
+        long = "ignore-nonexistent",
+        help = "Treat missing paths as already deleted, but still ask for confirmation"
+    )]
+    ignore_nonexistent: bool,
+
+    #[arg(
+        short = 'i',
+        long = "interactive",
+        overrides_with = "interactive_once",
+        help = "Prompt before every removal (overridden by --force)"
+    )]
+    interactive: bool,
+
+    #[arg(
+        short = 'I',
+        long = "interactive-once",
+        overrides_with = "interactive",
+        conflicts_with = "gui",
+        help = "Prompt once before removing more than three arguments or recursing"
+    )]
+    interactive_once: bool,
+
     #[arg(
         short = 'r',
         short_alias = 'R',
@@ -66,31 +135,261 @@ struct Args {
     )]
     threads: Option<usize>,
 
+    #[arg(
+        long = "shred",
+        value_name = "PASSES",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = clap::value_parser!(u8).range(1..),
+        help = "Overwrite file contents with random data before deleting (default: 1 pass). Sparse, compressed and hard-linked files are skipped; SSDs and copy-on-write file systems may keep the old data"
+    )]
+    shred: Option<u8>,
+
+    #[arg(
+        long = "max-retries",
+        visible_alias = "retries",
+        value_name = "N",
+        help = "Retries for files and directories another process briefly holds open; 0 fails immediately (default: 3)"
+    )]
+    max_retries: Option<u32>,
+
+    #[arg(
+        long = "retry-delay",
+        value_name = "MS",
+        conflicts_with = "retry_backoff",
+        help = "Fixed delay between retries in milliseconds"
+    )]
+    retry_delay: Option<u64>,
+
+    #[arg(
+        long = "retry-backoff",
+        value_name = "MS,MS,...",
+        value_delimiter = ',',
+        help = "Delay before each retry in milliseconds, the last repeating (default: 0,1,5,10)"
+    )]
+    retry_backoff: Vec<u64>,
+
+    #[arg(
+        long = "fail-fast",
+        overrides_with = "continue_on_error",
+        help = "Stop scheduling new deletions after the first error and skip remaining paths"
+    )]
+    fail_fast: bool,
+
+    #[arg(
+        long = "continue-on-error",
+        overrides_with = "fail_fast",
+        help = "Attempt every entry even after errors (default)"
+    )]
+    continue_on_error: bool,
+
     #[arg(
         short = 'n',
         long = "dry-run",
-        help = "Dry run - show what would be deleted"
+        help = "Dry run - show what would be deleted (with --unlock, the processes and handles that would be killed and closed)"
     )]
     dry_run: bool,
 
+    #[arg(
+        long = "check",
+        requires = "dry_run",
+        help = "With --dry-run, probe each entry for locks/permissions and report what would fail"
+    )]
+    check: bool,
+
+    #[arg(
+        long = "verify",
+        conflicts_with_all = ["dry_run", "recycle"],
+        help = "After deleting a directory, rescan it and report anything that survived as a failure"
+    )]
+    verify: bool,
+
+    #[arg(
+        long = "tree",
+        requires = "dry_run",
+        conflicts_with_all = ["json", "json_lines"],
+        help = "With --dry-run, list everything that would be deleted as a sorted, indented tree"
+    )]
+    tree: bool,
+
+    #[arg(
+        long = "max-list",
+        value_name = "N",
+        requires = "tree",
+        help = "With --tree, list at most N entries per operand and summarize the rest"
+    )]
+    max_list: Option<usize>,
+
+    #[arg(
+        long = "check-locks",
+        requires = "dry_run",
+        help = "With --dry-run, list the processes holding scanned files open (slow on large trees; nothing is killed)"
+    )]
+    check_locks: bool,
+
     #[arg(short = 'v', long = "verbose", help = "Explain what is being done")]
     verbose: bool,
 
-    #[arg(long = "stats", help = "Show detailed statistics")]
-    stats: bool,
+    #[arg(
+        short = 'q',
+        long,
+        conflicts_with = "verbose",
+        help = "Suppress warnings and the partial-failure summary; the exit code still reports failures"
+    )]
+    quiet: bool,
+
+    #[arg(
+        long = "stats",
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "summary",
+        value_enum,
+        help = "Show detailed statistics; --stats=per-path adds a row for each path"
+    )]
+    stats: Option<StatsMode>,
+
+    #[arg(
+        long = "profile",
+        conflicts_with_all = ["json", "json_lines"],
+        help = "Time each directory and report the slowest plus remove_dir latency percentiles (also on with --stats -v)"
+    )]
+    profile: bool,
+
+    #[arg(
+        long = "worker-stats",
+        conflicts_with_all = ["json", "json_lines"],
+        help = "Report how many work items each worker thread processed and how long it sat idle"
+    )]
+    worker_stats: bool,
+
+    // Hidden tuning knobs for benchmarking, see `DeleteOptions::batch_threshold`;
+    // the batch size can also come from $RMX_BATCH_SIZE
+    #[arg(long = "batch-threshold", value_name = "N", hide = true)]
+    batch_threshold: Option<usize>,
+
+    #[arg(
+        long = "batch-size",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        hide = true
+    )]
+    batch_size: Option<u32>,
+
+    #[arg(long = "parallel-threshold", value_name = "N", hide = true)]
+    parallel_threshold: Option<usize>,
+
+    #[arg(
+        long = "min-chunk-size",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        hide = true
+    )]
+    min_chunk_size: Option<u32>,
+
+    #[arg(
+        long = "backend",
+        value_enum,
+        default_value_t = Backend::Posix,
+        help = "How entries are removed: posix (raw handles, fastest) or shell (IFileOperation like Explorer: shell notifications and elevation prompts; Windows only)"
+    )]
+    backend: Backend,
+
+    #[arg(
+        long = "progress",
+        help = "Show a progress bar with throughput and ETA (only when stderr is a terminal)"
+    )]
+    progress: bool,
+
+    #[arg(
+        long = "json",
+        conflicts_with_all = ["json_lines", "verbose", "stats", "check", "check_locks", "gui"],
+        help = "Print a JSON summary (counts, elapsed_ms, failures) to stdout instead of text"
+    )]
+    json: bool,
+
+    #[arg(
+        long = "json-lines",
+        conflicts_with_all = ["verbose", "stats", "check", "check_locks", "gui"],
+        help = "Like --json, but stream one JSON object per failure, then a summary line"
+    )]
+    json_lines: bool,
+
+    #[arg(
+        long = "events",
+        conflicts_with_all = ["progress", "gui"],
+        help = "Stream one JSON object per deleted file or directory and per failure to stderr"
+    )]
+    events: bool,
+
+    #[arg(
+        long = "summary-only",
+        conflicts_with_all = ["json", "json_lines", "stats"],
+        help = "Print only one final line (files, dirs, size, time, failures), even with -v"
+    )]
+    summary_only: bool,
 
-    #[arg(long = "no-preserve-root", help = "Do not treat '/' specially")]
+    #[arg(
+        long = "summary-on-partial",
+        help = "When only some entries could not be deleted, report them but exit 0 (other failures keep their exit code)"
+    )]
+    summary_on_partial: bool,
+
+    #[arg(
+        long = "no-preserve-root",
+        overrides_with = "preserve_root",
+        help = "Do not treat '/' specially"
+    )]
     no_preserve_root: bool,
 
+    #[arg(
+        long = "preserve-root",
+        value_name = "all",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "root",
+        value_enum,
+        overrides_with = "no_preserve_root",
+        help = "Refuse to remove '/' and drive roots (default); with 'all', also refuse operands on a different volume than their parent and stay on each operand's volume"
+    )]
+    preserve_root: Option<PreserveRoot>,
+
     #[arg(
         long = "kill-processes",
         help = "Kill processes that are locking files (use with caution)"
     )]
     kill_processes: bool,
 
+    #[arg(
+        long = "graceful-kill",
+        help = "With --kill-processes or --unlock, ask locking processes to close their windows and only kill those still running after 2s"
+    )]
+    graceful_kill: bool,
+
+    #[arg(
+        long = "rename-locked",
+        help = "Move directories that stay locked to .rmx-pending at the volume root so their path is freed, and delete them once possible"
+    )]
+    rename_locked: bool,
+
     #[arg(long = "gui", help = "Show GUI progress window (used by context menu)")]
     gui: bool,
 
+    #[arg(
+        long = "lang",
+        value_enum,
+        help = "Language of the GUI windows (default: $RMX_LANG, then the system UI language)"
+    )]
+    lang: Option<rmx::ui_strings::Lang>,
+
+    #[arg(
+        long = "list-locks",
+        conflicts_with_all = ["unlock", "gui", "kill_processes"],
+        help = "Only list processes locking files/directories, without deleting or killing anything"
+    )]
+    list_locks: bool,
+
     #[arg(
         long = "unlock",
         help = "Only unlock files/directories (close handles) without deleting"
@@ -102,6 +401,324 @@ struct Args {
         help = "Reset skip-confirmation setting, restore delete confirmation dialog"
     )]
     reset_confirm: bool,
+
+    #[arg(
+        long = "older-than-file",
+        value_name = "REF",
+        help = "Only delete entries last modified before REF's modification time"
+    )]
+    older_than_file: Option<PathBuf>,
+
+    #[arg(
+        long = "older-than",
+        value_name = "AGE",
+        value_parser = tree::parse_age,
+        conflicts_with = "older_than_file",
+        help = "Only delete entries last modified more than AGE ago (e.g. 90m, 24h, 7d, 2w)"
+    )]
+    older_than: Option<Duration>,
+
+    #[arg(
+        long = "larger-than",
+        value_name = "SIZE",
+        value_parser = tree::parse_size,
+        help = "Only delete files larger than SIZE (e.g. 512K, 10M, 1G)"
+    )]
+    larger_than: Option<u64>,
+
+    #[arg(
+        long = "smaller-than",
+        value_name = "SIZE",
+        value_parser = tree::parse_size,
+        help = "Only delete files smaller than SIZE (e.g. 512K, 10M, 1G)"
+    )]
+    smaller_than: Option<u64>,
+
+    #[arg(
+        long = "no-recurse-hidden",
+        help = "Delete hidden files but leave hidden directories and their contents in place"
+    )]
+    no_recurse_hidden: bool,
+
+    #[arg(
+        long = "keep",
+        alias = "preserve-top-level-names",
+        value_name = "NAME",
+        value_delimiter = ',',
+        help = "Keep the immediate child NAME of each directory being cleared (repeatable)"
+    )]
+    keep: Vec<String>,
+
+    #[arg(
+        long = "empty-only",
+        conflicts_with_all = ["recycle", "interactive"],
+        help = "Only remove directories that contain nothing but empty directories; never delete files"
+    )]
+    empty_only: bool,
+
+    #[arg(
+        long = "contents-only",
+        conflicts_with = "recycle",
+        help = "Delete everything inside each directory but keep the directory itself"
+    )]
+    contents_only: bool,
+
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        help = "Keep entries matching GLOB, relative to each directory being deleted (repeatable)"
+    )]
+    exclude: Vec<String>,
+
+    #[arg(
+        long = "no-ignore",
+        help = "Do not read .rmxignore files; delete everything not otherwise excluded"
+    )]
+    no_ignore: bool,
+
+    #[arg(
+        long = "ignore-parents",
+        conflicts_with = "no_ignore",
+        help = "Also honor .rmxignore files in parent directories, up to the drive root"
+    )]
+    ignore_parents: bool,
+
+    #[arg(
+        long = "max-depth",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Only descend N levels below each operand; deeper directories are kept"
+    )]
+    max_depth: Option<u32>,
+
+    #[arg(
+        long = "one-file-system",
+        help = "Skip directories on a different volume than the operand (mount points, junctions)"
+    )]
+    one_file_system: bool,
+
+    #[arg(
+        long = "follow-junctions",
+        help = "Recurse into directory junctions and delete their targets' contents instead of only removing the junction"
+    )]
+    follow_junctions: bool,
+
+    #[arg(
+        long = "streams",
+        help = "Delete each file's NTFS alternate data streams (e.g. Zone.Identifier) explicitly before the file"
+    )]
+    streams: bool,
+
+    #[arg(
+        long = "include-streams",
+        help = "Count the bytes in NTFS alternate data streams toward the reported size"
+    )]
+    include_streams: bool,
+
+    #[arg(
+        long = "on-disk-size",
+        help = "Also measure the disk space allocated to each file (cluster slack, compression) and report it next to the logical size"
+    )]
+    on_disk_size: bool,
+
+    #[arg(
+        long = "recycle",
+        visible_alias = "trash",
+        conflicts_with_all = [
+            "older_than_file",
+            "older_than",
+            "larger_than",
+            "smaller_than",
+            "shred",
+            "no_recurse_hidden",
+            "keep",
+            "exclude",
+            "one_file_system",
+            "follow_junctions",
+            "max_depth",
+            "streams",
+            "journal",
+            "kill_processes",
+            "interactive"
+        ],
+        help = "Move to the Recycle Bin instead of deleting permanently (not -R, which is --recursive)"
+    )]
+    recycle: bool,
+
+    #[arg(
+        long = "hard-link-aware",
+        help = "Count hard-linked data only once when reporting freed space"
+    )]
+    hard_link_aware: bool,
+
+    #[arg(
+        long = "cache",
+        overrides_with = "no_cache",
+        help = "Reuse cached scan results for dry runs (never used for deletion)"
+    )]
+    cache: bool,
+
+    #[arg(
+        long = "no-cache",
+        overrides_with = "cache",
+        help = "Always rescan, ignoring cached scan results (default)"
+    )]
+    no_cache: bool,
+
+    #[arg(
+        long = "journal",
+        value_name = "PATH",
+        help = "Record finished directories in PATH and skip those already recorded, to resume an interrupted run"
+    )]
+    journal: Option<PathBuf>,
+
+    /// Scan filter resolved from the filtering options above
+    #[arg(skip)]
+    scan_filter: tree::ScanFilter,
+
+    /// Streams failures to the `--json-lines` printer
+    #[arg(skip)]
+    failure_sink: Option<crossbeam_channel::Sender<FailedItem>>,
+
+    /// Streams actions to the `--events` printer
+    #[arg(skip)]
+    event_sink: Option<crossbeam_channel::Sender<ActionEvent>>,
+
+    /// The opened `--journal`, shared by every operand
+    #[arg(skip)]
+    journal_log: Option<Arc<rmx::journal::Journal>>,
+
+    #[arg(skip)]
+    interrupt: Arc<Interrupt>,
+}
+
+/// One `--events` line, captured on the thread that did the work and
+/// printed by a single printer thread so lines never interleave
+#[derive(Debug)]
+struct ActionEvent {
+    event: &'static str,
+    path: PathBuf,
+    error: Option<String>,
+    thread: String,
+    time: SystemTime,
+}
+
+impl ActionEvent {
+    fn new(event: &'static str, path: &Path, error: Option<&str>) -> Self {
+        let current = thread::current();
+        Self {
+            event,
+            path: path.to_path_buf(),
+            error: error.map(str::to_owned),
+            thread: current
+                .name()
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("{:?}", current.id())),
+            time: SystemTime::now(),
+        }
+    }
+
+    fn from_progress(event: ProgressEvent<'_>) -> Option<Self> {
+        Some(match event {
+            ProgressEvent::FileDeleted { path } => Self::new("file_deleted", path, None),
+            ProgressEvent::DirCompleted { path } => Self::new("dir_deleted", path, None),
+            ProgressEvent::Failed { path, error } => Self::new("failed", path, Some(error)),
+            ProgressEvent::Notice { .. } => return None,
+        })
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let timestamp_ms = self
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut value = serde_json::json!({
+            "event": self.event,
+            "path": self.path.to_string_lossy(),
+            "timestamp_ms": timestamp_ms,
+            "thread": self.thread,
+        });
+        if let Some(error) = &self.error {
+            value["error"] = error.as_str().into();
+        }
+        value
+    }
+}
+
+/// Ctrl+C state: the flag cancels the running deletion, the counters feed the
+/// summary printed when it stops early.
+#[derive(Debug, Default)]
+struct Interrupt {
+    flag: Arc<AtomicBool>,
+    files: AtomicUsize,
+    dirs: AtomicUsize,
+}
+
+impl Interrupt {
+    fn is_set(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+/// Turn the first Ctrl+C into a cooperative cancel: nothing new is
+/// scheduled, in-flight deletions finish and workers are joined. A second
+/// Ctrl+C falls through to the default handler and ends rmx at once.
+#[cfg(windows)]
+fn install_interrupt_handler(flag: Arc<AtomicBool>) {
+    use std::sync::OnceLock;
+    use windows::Win32::Foundation::{BOOL, FALSE, TRUE};
+    use windows::Win32::System::Console::SetConsoleCtrlHandler;
+
+    static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+    unsafe extern "system" fn on_ctrl(ctrl_type: u32) -> BOOL {
+        const CTRL_C_EVENT: u32 = 0;
+        const CTRL_BREAK_EVENT: u32 = 1;
+        if ctrl_type != CTRL_C_EVENT && ctrl_type != CTRL_BREAK_EVENT {
+            return FALSE;
+        }
+        match FLAG.get() {
+            Some(flag) if !flag.swap(true, Ordering::SeqCst) => TRUE,
+            _ => FALSE,
+        }
+    }
+
+    if FLAG.set(flag).is_ok() {
+        unsafe {
+            let _ = SetConsoleCtrlHandler(Some(on_ctrl), true);
+        }
+    }
+}
+
+/// Ctrl+C keeps its default behavior here (rmx is Windows-first).
+#[cfg(not(windows))]
+fn install_interrupt_handler(_flag: Arc<AtomicBool>) {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum StatsMode {
+    /// Totals over all paths
+    Summary,
+    /// A table with one row per path, then the totals
+    PerPath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum PreserveRoot {
+    /// Refuse drive and share roots (the default)
+    #[value(hide = true)]
+    Root,
+    /// Also refuse mount points and don't cross into other volumes
+    All,
+}
+
+/// One row of `--stats=per-path`
+struct PathStats {
+    path: PathBuf,
+    items: usize,
+    /// Unknown when the path only partially failed
+    bytes: Option<u64>,
+    time: Duration,
+    failed: usize,
 }
 
 #[derive(Subcommand, Debug)]
@@ -119,15 +736,95 @@ enum Command {
         #[arg(
             short = 'f',
             long,
-            help = "Force upgrade, bypass package manager detection"
+            help = "Force upgrade, bypass package manager detection and downgrade confirmation"
         )]
         force: bool,
+        #[arg(
+            long = "version",
+            value_name = "TAG",
+            help = "Install this release (e.g. v0.2.9) instead of the latest, even if older"
+        )]
+        version: Option<String>,
+        #[arg(
+            long = "pre",
+            conflicts_with = "version",
+            help = "Include prereleases when looking for the newest version"
+        )]
+        pre: bool,
+        #[arg(
+            long,
+            value_name = "URL",
+            help = "Proxy to use instead of HTTPS_PROXY / HTTP_PROXY (e.g. http://proxy:8080)"
+        )]
+        proxy: Option<String>,
+    },
+    #[command(about = "Restore the rmx binary that the last upgrade replaced")]
+    Rollback,
+    #[command(
+        about = "Classify paths (exists, file/dir, symlink, size) without deleting anything"
+    )]
+    Probe {
+        #[arg(
+            long = "files-from",
+            value_name = "FILE",
+            help = "Read paths to probe from FILE, one per line"
+        )]
+        files_from: PathBuf,
+        #[arg(long, value_enum, default_value_t = ProbeFormat::Text, help = "Output format")]
+        format: ProbeFormat,
+    },
+    #[command(
+        about = "Run the safety checks on paths and print a verdict for each without deleting anything"
+    )]
+    Check {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    #[command(
+        about = "Find build and cache directories (node_modules, target, ...) below ROOT and delete them"
+    )]
+    Clean {
+        #[arg(value_name = "ROOT", default_value = ".")]
+        root: PathBuf,
+        #[arg(
+            long = "pattern",
+            value_name = "NAME",
+            help = "Directory name or glob to look for instead of the presets (repeatable)"
+        )]
+        patterns: Vec<String>,
+        #[arg(short = 'f', long, help = "Delete without asking")]
+        force: bool,
+        #[arg(
+            short = 'n',
+            long = "dry-run",
+            help = "Only list what would be deleted"
+        )]
+        dry_run: bool,
+    },
+    #[command(about = "Show how much space a path uses without deleting anything")]
+    Size {
+        path: PathBuf,
+        #[arg(long = "on-disk", help = "Also report allocated size on disk")]
+        on_disk: bool,
+        #[arg(
+            long = "by-dir",
+            help = "List each immediate subdirectory, largest first"
+        )]
+        by_dir: bool,
     },
 }
 
 fn main() {
-    rmx::upgrade::cleanup_old_binary();
     let mut args = Args::parse();
+    // The one summary line replaces all other output, -v included
+    if args.summary_only {
+        args.verbose = false;
+        args.quiet = true;
+    }
+    // `rollback` needs the `.old` binary this would remove
+    if !matches!(args.command, Some(Command::Rollback)) {
+        rmx::upgrade::cleanup_old_binary();
+    }
 
     #[cfg(windows)]
     if args.gui {
@@ -136,6 +833,10 @@ fn main() {
         }
     }
 
+    if let Some(lang) = args.lang {
+        rmx::ui_strings::set_lang(lang);
+    }
+
     if let Some(command) = args.command {
         if let Err(e) = run_command(command) {
             eprintln!("rmx: {}", e);
@@ -157,23 +858,61 @@ fn main() {
         return;
     }
 
-    if args.paths.is_empty() {
+    let listed = match read_listed_paths(&mut args) {
+        Ok(listed) => listed,
+        Err(e) => {
+            eprintln!("rmx: {}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    if args.paths.is_empty() && listed.is_empty() {
+        print_empty_json(&args);
         eprintln!("rmx: missing operand");
         eprintln!("Try 'rmx --help' for more information.");
         process::exit(1);
     }
 
     // Windows shell 不展开 glob，需要应用层自行处理
-    let had_glob = args
-        .paths
-        .iter()
-        .any(|p| contains_glob_chars(&p.to_string_lossy()));
-    args.paths = expand_globs(&args.paths, args.force);
-    if args.paths.is_empty() {
-        if had_glob && args.force {
-            return;
+    args.paths = rmx::glob::expand_paths(&args.paths);
+    // Listed paths are taken literally, never glob-expanded
+    args.paths.extend(listed);
+
+    match build_scan_filter(&args) {
+        Ok(filter) => args.scan_filter = filter,
+        Err(e) => {
+            eprintln!("rmx: {}", e);
+            process::exit(e.exit_code());
         }
-        process::exit(1);
+    }
+
+    if let Some(path) = &args.journal {
+        match rmx::journal::Journal::open(path) {
+            Ok(journal) => {
+                if args.verbose && !journal.completed().is_empty() {
+                    println!(
+                        "resuming from '{}' ({} directories already done)",
+                        path.display(),
+                        journal.completed().len()
+                    );
+                }
+                args.scan_filter.journaled = Some(journal.completed());
+                args.journal_log = Some(Arc::new(journal));
+            }
+            Err(e) => {
+                let e = Error::io_with_path(path.clone(), e);
+                eprintln!("rmx: {}", e);
+                process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if args.list_locks {
+        if let Err(e) = run_list_locks(&args) {
+            eprintln!("rmx: {}", e);
+            process::exit(1);
+        }
+        return;
     }
 
     if args.unlock {
@@ -184,9 +923,25 @@ fn main() {
         return;
     }
 
-    if let Err(e) = run(args) {
-        eprintln!("rmx: {}", e);
-        process::exit(e.exit_code());
+    install_interrupt_handler(args.interrupt.flag.clone());
+
+    let quiet = args.quiet;
+    let partial_ok = args.summary_on_partial;
+    let result = run(args);
+    if let Err(e) = &result {
+        let silent = match e {
+            // An interruption was already summarized by `run`
+            Error::Cancelled => true,
+            Error::PartialFailure { .. } => quiet,
+            _ => false,
+        };
+        if !silent {
+            eprintln!("rmx: {}", e);
+        }
+    }
+    let status = ExitCode::for_result(&result, partial_ok);
+    if status != ExitCode::Success {
+        process::exit(status.code());
     }
 }
 
@@ -206,16 +961,161 @@ fn run_command(command: Command) -> Result<(), std::io::Error> {
             println!("rmx shell extension has been removed.");
             Ok(())
         }
-        Command::Upgrade { check, force } => rmx::upgrade::run_upgrade(check, force)
-            .map_err(|e| std::io::Error::other(e.to_string())),
+        Command::Upgrade {
+            check,
+            force,
+            version,
+            pre,
+            proxy,
+        } => rmx::upgrade::run_upgrade(
+            check,
+            force,
+            &upgrade_target(version, pre),
+            proxy.as_deref(),
+        )
+        .map_err(|e| std::io::Error::other(e.to_string())),
+        Command::Rollback => {
+            rmx::upgrade::rollback().map_err(|e| std::io::Error::other(e.to_string()))
+        }
+        Command::Probe { files_from, format } => rmx::probe::run_probe(&files_from, format),
+        Command::Check { paths } => rmx::probe::run_check(&paths),
+        Command::Clean {
+            root,
+            patterns,
+            force,
+            dry_run,
+        } => run_clean(&root, &patterns, force, dry_run),
+        Command::Size {
+            path,
+            on_disk,
+            by_dir,
+        } => rmx::size::run_size(&path, on_disk, by_dir),
+    }
+}
+
+/// `rmx clean`: list the matching directories, then delete them with `-f`
+/// or once confirmed. Without a terminal to ask on, only the list is shown.
+fn run_clean(
+    root: &Path,
+    patterns: &[String],
+    force: bool,
+    dry_run: bool,
+) -> Result<(), std::io::Error> {
+    use std::io::IsTerminal;
+
+    let patterns = if patterns.is_empty() {
+        rmx::clean::DEFAULT_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    } else {
+        patterns.to_vec()
+    };
+    let compiled = patterns
+        .iter()
+        .map(|p| {
+            glob::Pattern::new(p).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("invalid pattern '{}': {}", p, e.msg),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let root = safety::absolute_operand(root);
+    let found = rmx::clean::find_artifacts(&root, &compiled)?;
+    if found.is_empty() {
+        println!("nothing to clean under '{}'", root.display());
+        return Ok(());
+    }
+    for dir in &found {
+        println!("{}", dir.display());
+    }
+    println!("{} director(ies) found", found.len());
+
+    if dry_run {
+        return Ok(());
+    }
+    if !force {
+        if !std::io::stdin().is_terminal() {
+            eprintln!("rmx: nothing deleted; pass -f to delete them");
+            return Ok(());
+        }
+        eprint!("rmx: delete these {} director(ies)? [y/N] ", found.len());
+        std::io::stderr().flush().ok();
+        if !confirm_yes().map_err(|e| std::io::Error::other(e.to_string()))? {
+            return Ok(());
+        }
+    }
+
+    let options = DeleteOptions::new().force(true);
+    let mut total = DeletionStats::default();
+    let mut failed = 0;
+    for dir in &found {
+        match api::delete_tree(dir, &options) {
+            Ok(stats) => total.merge(&stats),
+            Err(e) => {
+                eprintln!("rmx: cannot remove '{}': {}", dir.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "removed {} director(ies) ({} files, {})",
+        found.len() - failed,
+        total.files_deleted,
+        format_bytes(total.total_bytes)
+    );
+    if failed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{} director(ies) could not be removed",
+            failed
+        )));
+    }
+    Ok(())
+}
+
+fn upgrade_target(version: Option<String>, pre: bool) -> rmx::upgrade::UpgradeTarget {
+    match version {
+        Some(tag) => rmx::upgrade::UpgradeTarget::Tag(tag),
+        None if pre => rmx::upgrade::UpgradeTarget::Prerelease,
+        None => rmx::upgrade::UpgradeTarget::Latest,
     }
 }
 
 #[cfg(not(windows))]
 fn run_command(command: Command) -> Result<(), std::io::Error> {
     match command {
-        Command::Upgrade { check, force } => rmx::upgrade::run_upgrade(check, force)
-            .map_err(|e| std::io::Error::other(e.to_string())),
+        Command::Upgrade {
+            check,
+            force,
+            version,
+            pre,
+            proxy,
+        } => rmx::upgrade::run_upgrade(
+            check,
+            force,
+            &upgrade_target(version, pre),
+            proxy.as_deref(),
+        )
+        .map_err(|e| std::io::Error::other(e.to_string())),
+        Command::Rollback => {
+            rmx::upgrade::rollback().map_err(|e| std::io::Error::other(e.to_string()))
+        }
+        Command::Probe { files_from, format } => rmx::probe::run_probe(&files_from, format),
+        Command::Check { paths } => rmx::probe::run_check(&paths),
+        Command::Clean {
+            root,
+            patterns,
+            force,
+            dry_run,
+        } => run_clean(&root, &patterns, force, dry_run),
+        Command::Size {
+            path,
+            on_disk,
+            by_dir,
+        } => rmx::size::run_size(&path, on_disk, by_dir),
         _ => Err(std::io::Error::new(
             std::io::ErrorKind::Unsupported,
             "Shell extension is only available on Windows",
@@ -223,114 +1123,368 @@ fn run_command(command: Command) -> Result<(), std::io::Error> {
     }
 }
 
-fn run(args: Args) -> Result<(), Error> {
+fn run(mut args: Args) -> Result<(), Error> {
+    if args.interactive_once && !args.force && !args.dry_run && !confirm_once(&args)? {
+        print_empty_json(&args);
+        return Ok(());
+    }
+
+    let start = Instant::now();
     let mut total_stats = DeletionStats::default();
     let mut all_failures = Vec::new();
     let mut failed_paths = Vec::new();
+    // Whole paths that failed outright, for the JSON failure list
+    let mut path_failures = Vec::new();
+    // The whole-path error with the highest exit code decides the exit code
+    // when no path failed only partially
+    let mut path_error: Option<Error> = None;
+    let mut per_path = Vec::new();
+
+    let printer = if args.json_lines {
+        let (tx, rx) = crossbeam_channel::unbounded::<FailedItem>();
+        args.failure_sink = Some(tx);
+        Some(thread::spawn(move || {
+            for item in rx {
+                println!("{}", failure_json(&item, Some("failure")));
+            }
+        }))
+    } else {
+        None
+    };
+    let event_printer = if args.events {
+        let (tx, rx) = crossbeam_channel::unbounded::<ActionEvent>();
+        args.event_sink = Some(tx);
+        Some(thread::spawn(move || {
+            for event in rx {
+                eprintln!("{}", event.to_json());
+            }
+        }))
+    } else {
+        None
+    };
 
     for path in &args.paths {
+        if args.interrupt.is_set() {
+            break;
+        }
+        let path_start = Instant::now();
         match process_path(path, &args) {
-            Ok(stats) => total_stats.merge(&stats),
+            Ok(stats) => {
+                per_path.push(PathStats {
+                    path: path.clone(),
+                    items: stats.total_items(),
+                    bytes: Some(stats.total_bytes),
+                    time: stats.total_time,
+                    failed: 0,
+                });
+                total_stats.merge(&stats);
+            }
+            Err(Error::Cancelled) => {
+                // Ctrl+C or the progress window's cancel button
+                path_error = Some(Error::Cancelled);
+                break;
+            }
             Err(e) => {
-                eprintln!("rmx: cannot remove '{}': {}", path.display(), e);
+                if !(args.quiet && matches!(e, Error::PartialFailure { .. })) {
+                    eprintln!("rmx: cannot remove '{}': {}", path.display(), e);
+                }
                 failed_paths.push(path.clone());
-                if let Error::PartialFailure { errors, .. } = e {
-                    all_failures.extend(errors);
+                let (items, failed) = match &e {
+                    Error::PartialFailure { total, failed, .. } => {
+                        (total.saturating_sub(*failed), *failed)
+                    }
+                    _ => (0, 1),
+                };
+                per_path.push(PathStats {
+                    path: path.clone(),
+                    items,
+                    bytes: None,
+                    time: path_start.elapsed(),
+                    failed,
+                });
+                match e {
+                    // Individual failures were already streamed as they happened
+                    Error::PartialFailure { errors, .. } => all_failures.extend(errors),
+                    e => {
+                        let item = FailedItem {
+                            path: path.clone(),
+                            error: e.to_string(),
+                            is_dir: rmx::winapi::is_directory(path),
+                            kind: error_kind(&e, path),
+                        };
+                        if let Some(sink) = &args.failure_sink {
+                            let _ = sink.send(item.clone());
+                        }
+                        if let Some(sink) = &args.event_sink {
+                            let _ = sink.send(ActionEvent::new("failed", path, Some(&item.error)));
+                        }
+                        path_failures.push(item);
+                        if path_error
+                            .as_ref()
+                            .is_none_or(|worst| e.exit_code() > worst.exit_code())
+                        {
+                            path_error = Some(e);
+                        }
+                    }
+                }
+                if args.fail_fast {
+                    break;
                 }
             }
         }
     }
 
-    if args.stats {
-        print_summary(&total_stats, &args);
+    // Closing the last sender ends the printer once it has drained
+    args.failure_sink = None;
+    if let Some(printer) = printer {
+        printer.join().ok();
+    }
+    args.event_sink = None;
+    if let Some(printer) = event_printer {
+        printer.join().ok();
+    }
+
+    if args.json || args.json_lines {
+        let failures: Vec<FailedItem> = path_failures
+            .into_iter()
+            .chain(all_failures.iter().cloned())
+            .collect();
+        print_json_summary(&total_stats, &failures, start.elapsed(), args.json_lines);
+    } else if args.stats.is_some() {
+        print_summary(&total_stats, &per_path, &args);
+    } else if args.summary_only {
+        let failed = path_failures.len() + all_failures.len();
+        println!(
+            "{}",
+            summary_line(&total_stats, failed, start.elapsed(), args.dry_run)
+        );
+    } else if !args.quiet {
+        // Filtered entries are expected; OS-owned folders are worth a note
+        for (path, reason) in &total_stats.skipped {
+            if *reason == tree::SkipReason::SystemFolder {
+                eprintln!("rmx: skipped '{}' ({})", path.display(), reason);
+            }
+        }
     }
 
-    if !failed_paths.is_empty() || !all_failures.is_empty() {
+    if args.interrupt.is_set() {
+        eprintln!(
+            "rmx: interrupted; removed {} file(s) and {} director(ies) before stopping",
+            args.interrupt.files.load(Ordering::Relaxed),
+            args.interrupt.dirs.load(Ordering::Relaxed)
+        );
+        Err(Error::Cancelled)
+    } else if !all_failures.is_empty() {
+        let failed = all_failures.len() + failed_paths.len();
         Err(Error::PartialFailure {
-            total: total_stats.total_items(),
-            failed: all_failures.len() + failed_paths.len(),
+            total: total_stats.total_items() + failed,
+            failed,
             errors: all_failures,
         })
+    } else if let Some(e) = path_error {
+        Err(e)
     } else {
         Ok(())
     }
 }
 
-#[derive(Default)]
-struct DeletionStats {
-    dirs_deleted: usize,
-    files_deleted: usize,
-    total_bytes: u64,
-    total_time: std::time::Duration,
+/// The `--summary-only` line:
+/// `rmx: deleted 5,231 files, 842 dirs, 1.20 GB in 3.4s (2 failed)`
+fn summary_line(stats: &DeletionStats, failed: usize, elapsed: Duration, dry_run: bool) -> String {
+    let mut line = format!(
+        "rmx: {} {} files, {} dirs, {} in {:.1}s",
+        if dry_run { "would delete" } else { "deleted" },
+        format_count(stats.files_deleted as u64),
+        format_count(stats.dirs_deleted as u64),
+        format_bytes(stats.total_bytes),
+        elapsed.as_secs_f64()
+    );
+    if failed > 0 {
+        line.push_str(&format!(" ({} failed)", format_count(failed as u64)));
+    }
+    line
 }
 
-impl DeletionStats {
-    fn merge(&mut self, other: &DeletionStats) {
-        self.dirs_deleted += other.dirs_deleted;
-        self.files_deleted += other.files_deleted;
-        self.total_bytes += other.total_bytes;
-        self.total_time += other.total_time;
+/// Categorize a whole-path failure; only I/O errors carry an OS code.
+fn error_kind(e: &Error, path: &Path) -> ErrorKind {
+    match e {
+        Error::Io { source, .. } => rmx::winapi::classify_error(source),
+        _ if path.symlink_metadata().is_err() => ErrorKind::NotFound,
+        _ => ErrorKind::Other(0),
     }
+}
 
-    fn total_items(&self) -> usize {
-        self.dirs_deleted + self.files_deleted
+fn failure_json(item: &FailedItem, kind: Option<&str>) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "path": item.path.to_string_lossy(),
+        "error": item.error,
+        "is_dir": item.is_dir,
+        "kind": item.kind.as_str(),
+    });
+    if let Some(kind) = kind {
+        value["type"] = kind.into();
     }
+    value
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-    const TB: u64 = GB * 1024;
-
-    if bytes >= TB {
-        format!("{:.2} TB", bytes as f64 / TB as f64)
-    } else if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
+/// `--json` prints one object with every failure; `--json-lines` has already
+/// streamed the failures and ends with a summary line carrying their count.
+fn print_json_summary(
+    stats: &DeletionStats,
+    failures: &[FailedItem],
+    elapsed: Duration,
+    lines: bool,
+) {
+    let mut value = serde_json::json!({
+        "dirs_deleted": stats.dirs_deleted,
+        "files_deleted": stats.files_deleted,
+        "total_bytes": stats.total_bytes,
+        "placeholders": stats.placeholders,
+        "streams": stats.streams,
+        "stream_bytes": stats.stream_bytes,
+        "on_disk_bytes": stats.on_disk_bytes,
+        "elapsed_ms": elapsed.as_millis() as u64,
+        "skipped": stats
+            .skipped
+            .iter()
+            .map(|(path, reason)| serde_json::json!({
+                "path": path.to_string_lossy(),
+                "reason": reason,
+            }))
+            .collect::<Vec<_>>(),
+    });
+    if lines {
+        value["type"] = "summary".into();
+        value["failed"] = failures.len().into();
     } else {
-        format!("{} B", bytes)
+        value["failures"] = failures
+            .iter()
+            .map(|item| failure_json(item, None))
+            .collect();
+    }
+    println!("{}", value);
+}
+
+/// Keeps stdout valid JSON when there is nothing to delete
+fn print_empty_json(args: &Args) {
+    if args.json || args.json_lines {
+        print_json_summary(
+            &DeletionStats::default(),
+            &[],
+            Duration::ZERO,
+            args.json_lines,
+        );
     }
 }
 
-fn print_summary(stats: &DeletionStats, args: &Args) {
-    if args.stats {
+fn print_summary(stats: &DeletionStats, per_path: &[PathStats], args: &Args) {
+    if args.stats == Some(StatsMode::PerPath) {
+        print_per_path(per_path);
+    }
+    if args.stats.is_some() {
         println!("\nStatistics:");
+        if !args.dry_run {
+            let action = if stats.recycled > 0 {
+                "moved to Recycle Bin"
+            } else {
+                "deleted"
+            };
+            println!("  Action:      {}", action);
+        }
+        if stats.recycled > 0 {
+            println!("  Recycled:    {}", stats.recycled);
+        }
         println!("  Directories: {}", stats.dirs_deleted);
         println!("  Files:       {}", stats.files_deleted);
         println!("  Total:       {}", stats.total_items());
         println!("  Size:        {}", format_bytes(stats.total_bytes));
+        if args.on_disk_size {
+            println!("  On disk:     {}", format_bytes(stats.on_disk_bytes));
+        }
+        if stats.placeholders > 0 {
+            println!("  Cloud-only:  {} (not downloaded)", stats.placeholders);
+        }
+        let links = &stats.reparse_points;
+        if links.total() > 0 {
+            println!(
+                "  Links:       {} ({} symlinks, {} junctions, {} mount points, {} other)",
+                links.total(),
+                links.symlinks,
+                links.junctions,
+                links.mount_points,
+                links.other
+            );
+        }
+        if args.streams {
+            println!("  Streams:     {}", stats.streams);
+        }
+        if stats.stream_bytes > 0 {
+            println!("  In streams:  {}", format_bytes(stats.stream_bytes));
+        }
+        if args.hard_link_aware {
+            println!("  Freed:       {}", format_bytes(stats.freed_bytes));
+        }
         println!("  Time:        {:.2?}", stats.total_time);
         if stats.total_time.as_secs_f64() > 0.0 {
             let throughput = stats.total_items() as f64 / stats.total_time.as_secs_f64();
             println!("  Throughput:  {:.0} items/sec", throughput);
         }
+        if !stats.skipped.is_empty() {
+            println!("  Skipped:     {}", stats.skipped.len());
+            for (path, reason) in stats.skipped.iter().take(10) {
+                println!("    skipped '{}' ({})", path.display(), reason);
+            }
+            if stats.skipped.len() > 10 {
+                println!("    ... and {} more", stats.skipped.len() - 10);
+            }
+        }
+        if args.check {
+            println!("  Blocked:     {}", stats.blocked.len());
+        }
+        if args.check_locks {
+            // Groups are per operand, so one process may appear repeatedly
+            let locked: usize = stats.locks.iter().map(|g| g.paths.len()).sum();
+            let processes: std::collections::HashSet<u32> =
+                stats.locks.iter().map(|g| g.process.pid).collect();
+            println!("  Locked:      {} ({} processes)", locked, processes.len());
+        }
+    }
+}
+
+fn print_per_path(rows: &[PathStats]) {
+    println!("\nPer path:");
+    println!(
+        "  {:>10} {:>10} {:>10} {:>7}  Path",
+        "Items", "Size", "Time", "Failed"
+    );
+    for row in rows {
+        let bytes = row.bytes.map_or_else(|| "-".to_string(), format_bytes);
+        let time = format!("{:.2?}", row.time);
+        println!(
+            "  {:>10} {:>10} {:>10} {:>7}  {}",
+            row.items,
+            bytes,
+            time,
+            row.failed,
+            row.path.display()
+        );
     }
 }
 
 fn process_path(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
-    // Relative paths don't get the \\?\ prefix in path_to_wide(), hitting the 260-char MAX_PATH
-    // limit on deeply nested trees (e.g. pnpm node_modules). Resolve to absolute here.
-    let canonical;
-    let path = if path.is_relative() {
-        if let Ok(abs) = std::fs::canonicalize(path) {
-            let s = abs.to_string_lossy();
-            // canonicalize returns \\?\C:\... on Windows; strip it so path_to_wide() can re-add it
-            // and safety checks in safety.rs can match against plain paths like "C:\Windows".
-            canonical = match s.strip_prefix(r"\\?\") {
-                Some(stripped) => PathBuf::from(stripped),
-                None => abs,
-            };
-            canonical.as_path()
-        } else {
-            path
-        }
-    } else {
-        path
-    };
+    let absolute = safety::absolute_operand(path);
+    let path = absolute.as_path();
+
+    if safety::is_protected_system_folder(path) {
+        return Ok(DeletionStats {
+            skipped: vec![(path.to_path_buf(), tree::SkipReason::SystemFolder)],
+            ..Default::default()
+        });
+    }
+
+    if args.verbose && rmx::winapi::is_network_path(path) {
+        println!("using network strategy for '{}'", path.display());
+    }
 
     let exists = rmx::winapi::path_exists(path);
     let is_dir = rmx::winapi::is_directory(path);
@@ -339,31 +1493,73 @@ fn process_path(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
         if args.force {
             return try_force_delete_file(path, args);
         }
+        if args.ignore_nonexistent {
+            if args.verbose {
+                println!("skipped '{}' (not found)", path.display());
+            }
+            return Ok(DeletionStats::default());
+        }
         return Err(Error::InvalidPath {
             path: path.to_path_buf(),
             reason: "No such file or directory".to_string(),
         });
     }
 
-    if is_dir {
+    if is_dir && args.empty_only {
+        let stats = process_directory(path, args)?;
+        if !args.quiet {
+            println!(
+                "{} {} empty director(ies) under '{}', {} non-empty left",
+                if args.dry_run {
+                    "would remove"
+                } else {
+                    "removed"
+                },
+                stats.dirs_deleted,
+                path.display(),
+                stats.dirs_kept
+            );
+        }
+        Ok(stats)
+    } else if is_dir {
         process_directory(path, args)
+    } else if args.contents_only {
+        Err(Error::InvalidPath {
+            path: path.to_path_buf(),
+            reason: "Not a directory (--contents-only)".to_string(),
+        })
     } else {
         process_file(path, args)
     }
 }
 
 fn process_file(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
-    if args.dry_run {
-        if args.verbose {
-            println!("would remove '{}'", path.display());
-        }
-        return Ok(DeletionStats {
-            files_deleted: 1,
-            ..Default::default()
+    // Files below a protected `dir\*` entry; directories go through
+    // `check_path_safety`
+    if !args.no_preserve_root && safety::is_user_protected(path) {
+        return Err(Error::InvalidPath {
+            path: path.to_path_buf(),
+            reason: format!("protected by {} or protected.txt", safety::PROTECT_ENV),
         });
     }
 
-    if !args.force {
+    if args.scan_filter.is_active() {
+        let stat = rmx::winapi::stat_path(path);
+        if args.scan_filter.keeps(stat.modified, stat.size) {
+            if args.verbose {
+                println!("skipped '{}' (filtered)", path.display());
+            }
+            return Ok(DeletionStats::default());
+        }
+    }
+
+    if args.dry_run {
+        let stats = api::dry_run_file(path, &delete_options(args, path));
+        print_dry_run(path, args, &stats, false);
+        return Ok(stats);
+    }
+
+    if !args.force && !args.interactive_once {
         #[cfg(windows)]
         if args.gui {
             if !read_skip_confirm() {
@@ -382,46 +1578,27 @@ fn process_file(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
                 }
             }
         } else if !confirm_deletion(path, false)? {
-            return Ok(DeletionStats::default());
+            return Ok(declined(path));
         }
 
         #[cfg(not(windows))]
         if !confirm_deletion(path, false)? {
-            return Ok(DeletionStats::default());
+            return Ok(declined(path));
         }
     }
 
-    let start = Instant::now();
-
-    match rmx::winapi::delete_file(path) {
-        Ok(()) => {}
-        Err(e) if args.kill_processes && rmx::winapi::is_file_in_use_error(&e) => {
-            // Step 1: Restart Manager — 精准找到并杀掉占用进程（快速可靠）
-            let _ = rmx::winapi::kill_locking_processes(path, args.verbose);
-            if rmx::winapi::delete_file(path).is_err() {
-                // Step 2: 暴力句柄扫描兜底（慢，但能处理 RM 找不到的情况）
-                let paths = [path.to_path_buf()];
-                let _ = rmx::winapi::force_close_file_handles(&paths, args.verbose);
-                rmx::winapi::delete_file(path)
-                    .map_err(|e2| Error::io_with_path(path.to_path_buf(), e2))?;
-            }
-        }
-        Err(e) => {
-            return Err(Error::io_with_path(path.to_path_buf(), e));
-        }
+    if args.recycle {
+        return recycle_entry(path, args, None);
     }
 
-    let elapsed = start.elapsed();
-
-    if args.verbose {
+    let result = api::delete_file(path, &delete_options(args, path));
+    if let (true, Ok(stats)) = (args.verbose, &result) {
+        if stats.streams > 0 {
+            println!("removed {} alternate data stream(s)", stats.streams);
+        }
         println!("removed '{}'", path.display());
     }
-
-    Ok(DeletionStats {
-        files_deleted: 1,
-        total_time: elapsed,
-        ..Default::default()
-    })
+    result
 }
 
 fn try_force_delete_file(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
@@ -437,7 +1614,7 @@ fn try_force_delete_file(path: &Path, args: &Args) -> Result<DeletionStats, Erro
 
     let start = Instant::now();
 
-    match rmx::winapi::delete_file(path) {
+    match rmx::winapi::delete_file_with(path, &retry_policy(args, path)) {
         Ok(()) => {
             let elapsed = start.elapsed();
             if args.verbose {
@@ -476,11 +1653,21 @@ fn process_directory(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
                 reason,
                 can_override: true,
             } => {
-                if !args.force {
+                if !args.force && !args.quiet {
                     eprintln!("rmx: warning: {}", reason);
                 }
             }
         }
+
+        if args.preserve_root == Some(PreserveRoot::All)
+            && safety::is_on_other_volume_than_parent(path)
+        {
+            return Err(Error::InvalidPath {
+                path: path.to_path_buf(),
+                reason: "is on a different volume than its parent (--preserve-root=all)"
+                    .to_string(),
+            });
+        }
     }
 
     if !args.recursive {
@@ -491,24 +1678,30 @@ fn process_directory(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
     }
 
     if args.dry_run {
-        return dry_run_directory(path, args);
+        let stats = api::dry_run_directory(path, &delete_options(args, path))?;
+        print_dry_run(path, args, &stats, true);
+        return Ok(stats);
     }
 
-    if !args.force {
-        let tree =
-            tree::discover_tree(path).map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
-        let dir_count = tree.dirs.len();
-        let file_count = tree.file_count;
+    if args.interactive && !args.force && !args.gui {
+        return interactive_delete_directory(path, args);
+    }
 
+    if !args.force && !args.interactive_once {
         #[cfg(windows)]
         if args.gui {
+            let tree = tree::discover_tree_filtered(path, &args.scan_filter)
+                .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
             if !read_skip_confirm() {
-                let result =
-                    progress_ui::run_confirmation_dialog(path.to_path_buf(), file_count, dir_count)
-                        .unwrap_or(progress_ui::ConfirmResult {
-                            confirmed: false,
-                            skip_next_confirm: false,
-                        });
+                let result = progress_ui::run_confirmation_dialog(
+                    path.to_path_buf(),
+                    tree.file_count,
+                    tree.removable_dir_count(),
+                )
+                .unwrap_or(progress_ui::ConfirmResult {
+                    confirmed: false,
+                    skip_next_confirm: false,
+                });
 
                 if result.confirmed && result.skip_next_confirm {
                     write_skip_confirm(true);
@@ -519,73 +1712,471 @@ fn process_directory(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
                 }
             }
             return delete_directory(path, args, Some(tree));
+        }
+
+        // Huge trees take seconds to walk; the prompt only needs a rough size
+        let tree = tree::discover_tree_with_limit(path, &args.scan_filter, CONFIRM_SCAN_LIMIT)
+            .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
+        let size = if tree.truncated {
+            format!("more than {} items", CONFIRM_SCAN_LIMIT)
         } else {
-            eprint!(
-                "rmx: descend into directory '{}' ({} files, {} directories)? [y/N] ",
-                path.display(),
-                file_count,
-                dir_count
-            );
-            std::io::stderr().flush().ok();
+            format!(
+                "{} files, {} directories",
+                tree.file_count,
+                tree.removable_dir_count()
+            )
+        };
+        eprint!(
+            "rmx: descend into directory '{}' ({})? [y/N] ",
+            path.display(),
+            size
+        );
+        std::io::stderr().flush().ok();
+
+        if !confirm_yes()? {
+            return Ok(DeletionStats::default());
+        }
+
+        return delete_directory(path, args, (!tree.truncated).then_some(tree));
+    }
+
+    delete_directory(path, args, None)
+}
+
+/// `rm -i` for a directory: asks before descending into each directory, for
+/// every file, and again before removing each emptied directory. Sequential
+/// by nature, so it bypasses the broker, but walks the same filtered scan.
+fn interactive_delete_directory(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
+    let start = Instant::now();
+    let tree = tree::discover_tree_filtered(path, &args.scan_filter)
+        .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
+    let walk = InteractiveWalk {
+        tree: &tree,
+        args,
+        backend: args.backend.instance(),
+        retry: retry_policy(args, path),
+    };
+    let mut failures = Vec::new();
+    let mut stats = walk.directory(path, args.contents_only, &mut failures)?;
+    stats.skipped.extend(tree.skipped.iter().cloned());
+    stats.total_time = start.elapsed();
+
+    if !failures.is_empty() {
+        return Err(Error::PartialFailure {
+            total: stats.total_items() + failures.len(),
+            failed: failures.len(),
+            errors: failures,
+        });
+    }
+    Ok(stats)
+}
+
+struct InteractiveWalk<'a> {
+    tree: &'a tree::DirectoryTree,
+    args: &'a Args,
+    backend: Arc<dyn rmx::backend::DeleteBackend>,
+    retry: RetryPolicy,
+}
+
+impl InteractiveWalk<'_> {
+    /// Returns the stats for `dir`; `dir` itself is only removed if
+    /// everything inside it was, and never with `keep_dir` or when the scan
+    /// kept something below it.
+    fn directory(
+        &self,
+        dir: &Path,
+        keep_dir: bool,
+        failures: &mut Vec<FailedItem>,
+    ) -> Result<DeletionStats, Error> {
+        let args = self.args;
+        eprint!("rmx: descend into directory '{}'? [y/N] ", dir.display());
+        std::io::stderr().flush().ok();
+        if !confirm_yes()? {
+            return Ok(declined(dir));
+        }
+
+        // (path, is_dir), in name order like `ls`
+        let mut entries: Vec<(&PathBuf, bool)> = Vec::new();
+        if let Some(children) = self.tree.children.get(dir) {
+            entries.extend(children.iter().map(|c| (c, true)));
+        }
+        if let Some(files) = self.tree.dir_files.get(dir) {
+            entries.extend(files.iter().map(|f| (f, false)));
+        }
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut stats = DeletionStats::default();
+        let mut emptied = !self.tree.kept_dirs.contains(dir);
+        for (path, is_dir) in entries {
+            let stat = rmx::winapi::stat_path(path);
+            // Links are leaves unless the scan followed them (junctions)
+            let entered = self.tree.children.contains_key(path.as_path())
+                || self.tree.dir_files.contains_key(path.as_path());
+            if is_dir && (!stat.is_symlink || entered) {
+                let sub = self.directory(path, false, failures)?;
+                emptied &= !rmx::winapi::path_exists(path);
+                stats.merge(&sub);
+                continue;
+            }
 
-            if !confirm_yes()? {
-                return Ok(DeletionStats::default());
+            if !confirm_deletion(path, false)? {
+                stats.skipped.push((path.clone(), SkipReason::Declined));
+                emptied = false;
+                continue;
+            }
+            // Directory links are removed as directories, never followed
+            let result = if is_dir {
+                self.backend.remove_dir(path, &self.retry)
+            } else {
+                args.shred
+                    .map_or(Ok(false), |passes| {
+                        rmx::winapi::overwrite_file(path, passes)
+                    })
+                    .and_then(|_| self.backend.delete_file(path, &self.retry))
+            };
+            match result {
+                Ok(()) => {
+                    if args.verbose {
+                        println!("removed '{}'", path.display());
+                    }
+                    if is_dir {
+                        stats.dirs_deleted += 1;
+                    } else {
+                        stats.files_deleted += 1;
+                    }
+                    if !stat.is_symlink {
+                        stats.total_bytes += stat.size;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("rmx: cannot remove '{}': {}", path.display(), e);
+                    self.record_failure(
+                        failures,
+                        FailedItem {
+                            path: path.clone(),
+                            error: e.to_string(),
+                            is_dir,
+                            kind: rmx::winapi::classify_error(&e),
+                        },
+                    );
+                    emptied = false;
+                }
             }
         }
 
-        #[cfg(not(windows))]
-        {
-            eprint!(
-                "rmx: descend into directory '{}' ({} files, {} directories)? [y/N] ",
-                path.display(),
-                file_count,
-                dir_count
-            );
-            std::io::stderr().flush().ok();
+        if emptied && keep_dir {
+            if args.verbose {
+                println!("emptied '{}' (kept directory)", dir.display());
+            }
+        } else if emptied && !confirm_deletion(dir, true)? {
+            stats
+                .skipped
+                .push((dir.to_path_buf(), SkipReason::Declined));
+        } else if emptied {
+            match self.backend.remove_dir(dir, &self.retry) {
+                Ok(()) => {
+                    if args.verbose {
+                        println!("removed directory '{}'", dir.display());
+                    }
+                    if let Some(journal) = &args.journal_log {
+                        journal.record(dir);
+                    }
+                    stats.dirs_deleted += 1;
+                }
+                Err(e) => {
+                    eprintln!("rmx: cannot remove '{}': {}", dir.display(), e);
+                    self.record_failure(
+                        failures,
+                        FailedItem {
+                            path: dir.to_path_buf(),
+                            error: e.to_string(),
+                            is_dir: true,
+                            kind: rmx::winapi::classify_error(&e),
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn record_failure(&self, failures: &mut Vec<FailedItem>, item: FailedItem) {
+        if let Some(journal) = &self.args.journal_log {
+            journal.record_failure(&item.path);
+        }
+        record_failure(self.args, failures, item);
+    }
+}
+
+fn record_failure(args: &Args, failures: &mut Vec<FailedItem>, item: FailedItem) {
+    if let Some(sink) = &args.failure_sink {
+        let _ = sink.send(item.clone());
+    }
+    failures.push(item);
+}
+
+fn declined(path: &Path) -> DeletionStats {
+    DeletionStats {
+        skipped: vec![(path.to_path_buf(), SkipReason::Declined)],
+        ..Default::default()
+    }
+}
+
+fn delete_directory(
+    path: &Path,
+    args: &Args,
+    cached_tree: Option<tree::DirectoryTree>,
+) -> Result<DeletionStats, Error> {
+    if args.recycle {
+        return recycle_entry(path, args, cached_tree);
+    }
 
-            if !confirm_yes()? {
-                return Ok(DeletionStats::default());
+    #[cfg(windows)]
+    let result = if args.gui {
+        delete_directory_with_gui(path, args, cached_tree)
+    } else {
+        api::delete_directory(path, &delete_options(args, path), cached_tree)
+    };
+    #[cfg(not(windows))]
+    let result = api::delete_directory(path, &delete_options(args, path), cached_tree);
+
+    print_deleted_directory(path, args, &result);
+    result
+}
+
+/// Report a directory deletion from what the library returned
+fn print_deleted_directory(path: &Path, args: &Args, result: &Result<DeletionStats, Error>) {
+    let stats = match result {
+        Ok(stats) => stats,
+        Err(Error::PartialFailure { errors, .. }) if args.verbose => {
+            for failure in errors.iter().take(5) {
+                eprintln!(
+                    "rmx: cannot remove '{}': {}",
+                    failure.path.display(),
+                    failure.error
+                );
             }
+            if errors.len() > 5 {
+                eprintln!("rmx: ... and {} more errors", errors.len() - 5);
+            }
+            return;
+        }
+        Err(_) => return,
+    };
+
+    if let Some(report) = &stats.timing_report {
+        print!("{}", report);
+    }
+    if !args.quiet {
+        for (original, moved) in &stats.deferred {
+            eprintln!(
+                "rmx: moved locked '{}' to '{}'; delete it once the lock is released",
+                original.display(),
+                moved.display()
+            );
         }
+    }
+    if !args.verbose {
+        return;
+    }
+
+    for (mount, _) in stats
+        .skipped
+        .iter()
+        .filter(|(_, reason)| *reason == SkipReason::OtherFileSystem)
+    {
+        println!(
+            "skipped mount point '{}' (other file system)",
+            mount.display()
+        );
+    }
+    if stats.streams > 0 {
+        println!("removed {} alternate data stream(s)", stats.streams);
+    }
+    let kept = if args.scan_filter.keep_root {
+        "kept directory; "
+    } else {
+        ""
+    };
+    println!(
+        "{} '{}' ({}{} files, {} dirs in {:.2?})",
+        if args.scan_filter.keep_root {
+            "emptied"
+        } else {
+            "removed"
+        },
+        path.display(),
+        kept,
+        stats.files_deleted,
+        stats.dirs_deleted,
+        stats.total_time
+    );
+    if stats.stream_bytes > 0 {
+        println!(
+            "freed {} held in alternate data streams",
+            format_bytes(stats.stream_bytes)
+        );
+    }
+    if args.scan_filter.on_disk_size {
+        println!("freed {} on disk", format_bytes(stats.on_disk_bytes));
+    }
+    if stats.placeholders > 0 {
+        println!(
+            "removed {} cloud placeholder(s) without downloading them",
+            stats.placeholders
+        );
+    }
+    let links = &stats.reparse_points;
+    if links.junctions + links.mount_points > 0 {
+        println!(
+            "removed {} junction(s) and {} mount point(s) without deleting their targets",
+            links.junctions, links.mount_points
+        );
+    }
+}
 
-        return delete_directory(path, args, Some(tree));
+/// Report what a dry run found, from what the library returned
+fn print_dry_run(path: &Path, args: &Args, stats: &DeletionStats, is_dir: bool) {
+    if args.verbose && !is_dir {
+        println!("would remove '{}'", path.display());
+    } else if args.verbose {
+        println!(
+            "{} '{}' ({} files, {} directories, {})",
+            if args.scan_filter.keep_root {
+                "would empty"
+            } else {
+                "would remove"
+            },
+            path.display(),
+            stats.files_deleted,
+            stats.dirs_deleted,
+            format_bytes(stats.total_bytes)
+        );
+        for (kept, reason) in &stats.skipped {
+            println!("would keep '{}' ({})", kept.display(), reason);
+        }
+        if stats.streams > 0 {
+            println!("would remove {} alternate data stream(s)", stats.streams);
+        }
+        if stats.stream_bytes > 0 {
+            println!(
+                "{} of that is in alternate data streams",
+                format_bytes(stats.stream_bytes)
+            );
+        }
+        if args.scan_filter.on_disk_size {
+            println!("{} allocated on disk", format_bytes(stats.on_disk_bytes));
+        }
+    }
+    if let Some(listing) = &stats.listing {
+        print!("{}", listing);
+    }
+    if args.verbose && is_dir && args.hard_link_aware {
+        println!(
+            "would free {} (hard-link aware)",
+            format_bytes(stats.freed_bytes)
+        );
+    }
+    for (blocked, reason) in &stats.blocked {
+        println!("would fail '{}': {}", blocked.display(), reason);
+    }
+    for group in &stats.locks {
+        println!(
+            "locked by {} (PID {}):",
+            group.process.name, group.process.pid
+        );
+        for path in &group.paths {
+            println!("  '{}'", path.display());
+        }
     }
-
-    delete_directory(path, args, None)
 }
 
-fn dry_run_directory(path: &Path, args: &Args) -> Result<DeletionStats, Error> {
-    let tree = tree::discover_tree(path).map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
+/// `-v` progress of large trees on stderr, when `--progress` draws no bar
+#[derive(Default)]
+struct VerbosePercent {
+    total: AtomicUsize,
+}
 
-    if args.verbose {
-        println!(
-            "would remove '{}' ({} files, {} directories, {})",
-            path.display(),
-            tree.file_count,
-            tree.dirs.len(),
-            format_bytes(tree.total_bytes)
-        );
+impl rmx::ProgressSink for VerbosePercent {
+    fn on_dir_complete(&self, completed: usize, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        if total > 10 {
+            eprint!("\rdeleting... {}%", completed * 100 / total);
+            std::io::stderr().flush().ok();
+        }
     }
 
-    Ok(DeletionStats {
-        dirs_deleted: tree.dirs.len(),
-        files_deleted: tree.file_count,
-        total_bytes: tree.total_bytes,
-        ..Default::default()
-    })
+    fn on_finish(&self) {
+        if self.total.load(Ordering::Relaxed) > 10 {
+            eprintln!("\rdeleting... done");
+        }
+    }
 }
 
-fn delete_directory(
+/// Move a file or a whole directory to the Recycle Bin. Bypasses the
+/// parallel broker: the shell moves the subtree in one operation.
+fn recycle_entry(
     path: &Path,
     args: &Args,
     cached_tree: Option<tree::DirectoryTree>,
 ) -> Result<DeletionStats, Error> {
-    #[cfg(windows)]
-    if args.gui {
-        return delete_directory_with_gui(path, args, cached_tree);
+    let is_dir = rmx::winapi::is_directory(path);
+    // The Recycle Bin takes the directory whole, so entries the .rmxignore
+    // rules protect would go with it
+    let ignored = is_dir && args.scan_filter.has_ignore_file(path);
+    let tree = match cached_tree {
+        Some(t) => Some(t),
+        None if ignored => Some(
+            tree::discover_tree_filtered(path, &args.scan_filter)
+                .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?,
+        ),
+        None if is_dir => Some(
+            tree::discover_tree(path).map_err(|e| Error::io_with_path(path.to_path_buf(), e))?,
+        ),
+        None => None,
+    };
+    if let Some((protected, _)) = tree
+        .as_ref()
+        .filter(|_| ignored)
+        .and_then(|t| t.skipped.first())
+    {
+        return Err(Error::InvalidPath {
+            path: path.to_path_buf(),
+            reason: format!(
+                "'{}' is protected by .rmxignore and --recycle would move it too (use --no-ignore to recycle everything)",
+                protected.display()
+            ),
+        });
+    }
+    // Never fall back to a permanent delete
+    if !rmx::winapi::has_recycle_bin(path) {
+        return Err(Error::NoRecycleBin {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let (dirs, files, bytes) = match &tree {
+        Some(t) => (t.dirs.len(), t.file_count, t.total_bytes),
+        None => (0, 1, rmx::winapi::stat_path(path).size),
+    };
+
+    let start = Instant::now();
+    rmx::winapi::move_to_recycle_bin(&[path.to_path_buf()])
+        .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
+
+    if args.verbose {
+        println!("recycled '{}'", path.display());
     }
 
-    delete_directory_internal(path, args, None, cached_tree)
+    Ok(DeletionStats {
+        dirs_deleted: dirs,
+        files_deleted: files,
+        total_bytes: bytes,
+        total_time: start.elapsed(),
+        recycled: dirs + files,
+        ..Default::default()
+    })
 }
 
 #[cfg(windows)]
@@ -596,43 +2187,23 @@ fn delete_directory_with_gui(
 ) -> Result<DeletionStats, Error> {
     let tree = match cached_tree {
         Some(t) => t,
-        None => {
-            tree::discover_tree(path).map_err(|e| Error::io_with_path(path.to_path_buf(), e))?
-        }
+        None => tree::discover_tree_filtered(path, &args.scan_filter)
+            .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?,
     };
 
     let total_items = tree.file_count + tree.dirs.len();
 
     if !progress_ui::should_show_progress_ui(total_items) {
-        return delete_directory_internal(path, args, None, Some(tree));
+        return api::delete_directory(path, &delete_options(args, path), Some(tree));
     }
 
     let progress = Arc::new(DeleteProgress::new(tree.file_count, tree.dirs.len()));
     let progress_clone = progress.clone();
     let path_buf = path.to_path_buf();
-    let args_clone = Args {
-        command: None,
-        paths: vec![],
-        force: args.force,
-        recursive: args.recursive,
-        threads: args.threads,
-        dry_run: args.dry_run,
-        verbose: args.verbose,
-        stats: args.stats,
-        no_preserve_root: args.no_preserve_root,
-        kill_processes: args.kill_processes,
-        gui: false,
-        unlock: false,
-        reset_confirm: false,
-    };
+    let options = delete_options(args, path).progress_sink(progress.clone());
 
     let delete_handle = thread::spawn(move || {
-        let result = delete_directory_internal(
-            &path_buf,
-            &args_clone,
-            Some(progress_clone.clone()),
-            Some(tree),
-        );
+        let result = api::delete_directory(&path_buf, &options, Some(tree));
 
         match &result {
             Ok(_) => {
@@ -669,177 +2240,6 @@ fn delete_directory_with_gui(
     }
 }
 
-fn delete_directory_internal(
-    path: &Path,
-    args: &Args,
-    #[allow(unused_variables)] progress: Option<Arc<DeleteProgress>>,
-    cached_tree: Option<tree::DirectoryTree>,
-) -> Result<DeletionStats, Error> {
-    let start = Instant::now();
-
-    let tree = match cached_tree {
-        Some(t) => {
-            if args.verbose {
-                println!("reusing cached tree for '{}'...", path.display());
-            }
-            t
-        }
-        None => {
-            if args.verbose {
-                println!("scanning '{}'...", path.display());
-            }
-            tree::discover_tree(path).map_err(|e| Error::io_with_path(path.to_path_buf(), e))?
-        }
-    };
-
-    let dir_count = tree.dirs.len();
-    let file_count = tree.file_count;
-    let total_bytes = tree.total_bytes;
-
-    let worker_count = if let Some(t) = args.threads {
-        t
-    } else {
-        let base = tree::cpu_count();
-        if rmx::winapi::is_ssd_drive(path) {
-            (base * 5 / 4).max(base + 1)
-        } else {
-            (base * 2).min(64)
-        }
-    };
-
-    let (broker, rx) = Broker::new(tree, worker_count);
-    let broker = Arc::new(broker);
-
-    let error_tracker = Arc::new(worker::ErrorTracker::new());
-    let worker_config = worker::WorkerConfig {
-        verbose: args.verbose,
-        ignore_errors: true,
-        kill_processes: args.kill_processes,
-    };
-
-    let handles = worker::spawn_workers(
-        worker_count,
-        rx,
-        broker.clone(),
-        worker_config,
-        error_tracker.clone(),
-    );
-    let progress_handle = if args.verbose && dir_count > 10 {
-        let total = broker.total_dirs();
-        let broker_clone = broker.clone();
-        Some(thread::spawn(move || loop {
-            thread::sleep(std::time::Duration::from_millis(200));
-            let completed = broker_clone.completed_count();
-            if completed >= total {
-                break;
-            }
-            let pct = (completed as f64 / total as f64 * 100.0) as u32;
-            eprint!("\rdeleting... {}%", pct);
-            std::io::stderr().flush().ok();
-        }))
-    } else {
-        None
-    };
-
-    #[cfg(windows)]
-    let gui_progress_handle = progress.as_ref().map(|p| {
-        let progress = p.clone();
-        let broker_clone = broker.clone();
-        let total = broker_clone.total_dirs();
-        thread::spawn(move || loop {
-            thread::sleep(std::time::Duration::from_millis(50));
-            let completed = broker_clone.completed_count();
-            progress
-                .deleted_dirs
-                .store(completed, std::sync::atomic::Ordering::Relaxed);
-
-            if completed >= total
-                || progress.is_cancelled()
-                || progress
-                    .is_complete
-                    .load(std::sync::atomic::Ordering::Acquire)
-            {
-                let final_completed = broker_clone.completed_count();
-                progress
-                    .deleted_dirs
-                    .store(final_completed, std::sync::atomic::Ordering::Relaxed);
-                break;
-            }
-        })
-    });
-
-    for handle in handles {
-        handle.join().expect("Worker thread panicked");
-    }
-
-    if let Some(handle) = progress_handle {
-        handle.join().ok();
-        eprintln!("\rdeleting... done");
-    }
-
-    let elapsed = start.elapsed();
-    let failures = error_tracker.get_failures();
-
-    #[cfg(windows)]
-    if let Some(ref p) = progress {
-        p.deleted_dirs.store(
-            broker.completed_count(),
-            std::sync::atomic::Ordering::Relaxed,
-        );
-        if !failures.is_empty() {
-            let error_messages: Vec<String> = failures
-                .iter()
-                .map(|e| format!("{}: {}", e.path.display(), e.error))
-                .collect();
-            p.set_errors(error_messages);
-        }
-        p.mark_complete();
-    }
-
-    #[cfg(windows)]
-    if let Some(handle) = gui_progress_handle {
-        handle.join().ok();
-    }
-
-    if args.verbose {
-        println!(
-            "removed '{}' ({} files, {} dirs in {:.2?})",
-            path.display(),
-            file_count,
-            dir_count,
-            elapsed
-        );
-    }
-
-    if !failures.is_empty() {
-        if args.verbose {
-            for failure in failures.iter().take(5) {
-                eprintln!(
-                    "rmx: cannot remove '{}': {}",
-                    failure.path.display(),
-                    failure.error
-                );
-            }
-            if failures.len() > 5 {
-                eprintln!("rmx: ... and {} more errors", failures.len() - 5);
-            }
-        }
-
-        return Err(Error::PartialFailure {
-            total: dir_count + file_count,
-            failed: failures.len(),
-            errors: failures,
-        });
-    }
-
-    Ok(DeletionStats {
-        dirs_deleted: dir_count,
-        files_deleted: file_count,
-        total_bytes,
-        total_time: elapsed,
-    })
-}
-
 #[cfg(windows)]
 fn read_skip_confirm() -> bool {
     use windows::core::PCWSTR;
@@ -931,45 +2331,170 @@ fn write_skip_confirm(skip: bool) {
     }
 }
 
-fn contains_glob_chars(s: &str) -> bool {
-    s.contains('*') || s.contains('?') || s.contains('[')
-}
-
-fn expand_globs(paths: &[PathBuf], force: bool) -> Vec<PathBuf> {
-    let mut expanded = Vec::new();
-
-    for path in paths {
-        let path_str = path.to_string_lossy();
-        if !contains_glob_chars(&path_str) {
-            expanded.push(path.clone());
-            continue;
-        }
-
-        match glob(&path_str) {
-            Ok(entries) => {
-                let mut matched = false;
-                for entry in entries {
-                    match entry {
-                        Ok(p) => {
-                            matched = true;
-                            expanded.push(p);
-                        }
-                        Err(e) => {
-                            eprintln!("rmx: glob error: {}", e);
-                        }
-                    }
+/// Resolve the filtering options into a [`tree::ScanFilter`].
+fn delete_options(args: &Args, path: &Path) -> DeleteOptions {
+    let mut options = DeleteOptions::new()
+        .force(args.force)
+        .kill_processes(args.kill_processes)
+        .graceful_kill(args.graceful_kill)
+        .verbose(args.verbose)
+        .dry_run(args.dry_run)
+        .check(args.check)
+        .check_locks(args.check_locks)
+        .hard_link_aware(args.hard_link_aware)
+        .cache(args.cache)
+        .progress_bar(args.progress)
+        .profile(args.profile || (args.stats.is_some() && args.verbose))
+        .worker_stats(args.worker_stats)
+        .retry_policy(retry_policy(args, path))
+        .fail_fast(args.fail_fast)
+        .rename_locked(args.rename_locked)
+        .verify(args.verify)
+        .list_tree(args.tree)
+        .backend(args.backend)
+        .scan_filter(args.scan_filter.clone());
+    if let Some(threads) = args.threads {
+        options = options.threads(threads);
+    }
+    if let Some(max) = args.max_list {
+        options = options.max_list(max);
+    }
+    if let Some(threshold) = args.batch_threshold {
+        options = options.batch_threshold(threshold);
+    }
+    let batch_size = args.batch_size.map(|size| size as usize).or_else(|| {
+        std::env::var(rmx::broker::BATCH_SIZE_ENV)
+            .ok()?
+            .parse()
+            .ok()
+            .filter(|&size| size > 0)
+    });
+    if let Some(size) = batch_size {
+        options = options.batch_size(size);
+    }
+    if let Some(threshold) = args.parallel_threshold {
+        options = options.parallel_threshold(threshold);
+    }
+    if let Some(size) = args.min_chunk_size {
+        options = options.min_chunk_size(size as usize);
+    }
+    if let Some(sink) = &args.failure_sink {
+        options = options.failure_sink(sink.clone());
+    }
+    if let Some(passes) = args.shred {
+        options = options.shred(passes);
+    }
+    if let Some(journal) = &args.journal_log {
+        options = options.journal(journal.clone());
+    }
+    if args.verbose && !(args.progress && std::io::stderr().is_terminal()) {
+        options = options.progress_sink(Arc::new(VerbosePercent::default()));
+    }
+    let interrupt = args.interrupt.clone();
+    let events = args.event_sink.clone();
+    let verbose = args.verbose;
+    options
+        .cancel_token(interrupt.flag.clone())
+        .progress_callback(move |event| {
+            match event {
+                ProgressEvent::FileDeleted { .. } => {
+                    interrupt.files.fetch_add(1, Ordering::Relaxed);
                 }
-                if !matched && !force {
-                    eprintln!("rmx: cannot remove '{}': No match", path_str);
+                ProgressEvent::DirCompleted { .. } => {
+                    interrupt.dirs.fetch_add(1, Ordering::Relaxed);
+                }
+                ProgressEvent::Failed { .. } => {}
+                ProgressEvent::Notice { message, .. } => {
+                    if verbose {
+                        println!("{}", message);
+                    }
                 }
             }
-            Err(e) => {
-                eprintln!("rmx: invalid pattern '{}': {}", path_str, e);
+            if let (Some(events), Some(event)) = (&events, ActionEvent::from_progress(event)) {
+                let _ = events.send(event);
             }
-        }
+        })
+}
+
+/// Defaults for `path` (network shares retry longer), then the user's overrides
+fn retry_policy(args: &Args, path: &Path) -> RetryPolicy {
+    let mut policy = RetryPolicy::for_path(path);
+    if let Some(max_retries) = args.max_retries {
+        policy.max_retries = max_retries;
+    }
+    if let Some(delay) = args.retry_delay {
+        policy.delays_ms = vec![delay];
+    } else if !args.retry_backoff.is_empty() {
+        policy.delays_ms = args.retry_backoff.clone();
+    }
+    policy
+}
+
+fn build_scan_filter(args: &Args) -> Result<tree::ScanFilter, Error> {
+    let mut filter = tree::ScanFilter {
+        no_recurse_hidden: args.no_recurse_hidden,
+        keep_top_level: args.keep.clone(),
+        one_file_system: args.one_file_system || args.preserve_root == Some(PreserveRoot::All),
+        follow_junctions: args.follow_junctions,
+        ignore_files: !args.no_ignore,
+        ignore_parents: args.ignore_parents,
+        max_depth: args.max_depth.map(|n| n as usize),
+        streams: args.streams,
+        stream_bytes: args.include_streams,
+        on_disk_size: args.on_disk_size,
+        quiet: args.quiet,
+        keep_root: args.contents_only,
+        empty_only: args.empty_only,
+        larger_than: args.larger_than,
+        smaller_than: args.smaller_than,
+        ..Default::default()
+    };
+
+    for pattern in &args.exclude {
+        let compiled = glob::Pattern::new(pattern).map_err(|e| Error::InvalidPattern {
+            pattern: pattern.clone(),
+            reason: e.msg.to_string(),
+        })?;
+        filter.exclude.push(compiled);
+    }
+
+    if let Some(ref reference) = args.older_than_file {
+        let modified = std::fs::metadata(reference)
+            .and_then(|m| m.modified())
+            .map_err(|e| Error::io_with_path(reference.clone(), e))?;
+        filter.older_than = Some(modified);
     }
 
-    expanded
+    if let Some(age) = args.older_than {
+        filter.older_than = Some(
+            std::time::SystemTime::now()
+                .checked_sub(age)
+                .unwrap_or(std::time::UNIX_EPOCH),
+        );
+    }
+
+    Ok(filter)
+}
+
+/// Collect paths from `--from-file` and stdin (`--from-stdin` or the `-`
+/// operand), removing `-` from `args.paths`.
+fn read_listed_paths(args: &mut Args) -> Result<Vec<PathBuf>, Error> {
+    let mut listed = Vec::new();
+
+    if let Some(ref list) = args.from_file {
+        let bytes = std::fs::read(list).map_err(|e| Error::io_with_path(list.clone(), e))?;
+        listed.extend(rmx::probe::parse_path_list(&bytes, args.null));
+    }
+
+    let stdin_marker = Path::new("-");
+    if args.from_stdin || args.paths.iter().any(|p| p == stdin_marker) {
+        args.paths.retain(|p| p != stdin_marker);
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin(), &mut bytes)?;
+        listed.extend(rmx::probe::parse_path_list(&bytes, args.null));
+    }
+
+    Ok(listed)
 }
 
 fn confirm_deletion(path: &Path, is_dir: bool) -> Result<bool, Error> {
@@ -979,6 +2504,25 @@ fn confirm_deletion(path: &Path, is_dir: bool) -> Result<bool, Error> {
     confirm_yes()
 }
 
+/// `-I`: a single prompt for the whole command line, like GNU `rm -I`.
+/// Item-level prompts are skipped once this has been answered.
+fn confirm_once(args: &Args) -> Result<bool, Error> {
+    let count = args.paths.len();
+    let recursive = args.recursive && args.paths.iter().any(|p| rmx::winapi::is_directory(p));
+    if count <= INTERACTIVE_ONCE_THRESHOLD && !recursive {
+        return Ok(true);
+    }
+
+    eprint!(
+        "rmx: remove {} argument{}{}? [y/N] ",
+        count,
+        if count == 1 { "" } else { "s" },
+        if recursive { " recursively" } else { "" }
+    );
+    std::io::stderr().flush().ok();
+    confirm_yes()
+}
+
 fn confirm_yes() -> Result<bool, Error> {
     let mut response = String::new();
     std::io::stdin()
@@ -996,6 +2540,7 @@ fn confirm_yes() -> Result<bool, Error> {
 
 fn run_unlock(args: &Args) -> Result<(), Error> {
     let verbose = args.verbose;
+    let grace = args.graceful_kill.then_some(rmx::winapi::KILL_GRACE_PERIOD);
 
     for path in &args.paths {
         let exists = rmx::winapi::path_exists(path);
@@ -1007,35 +2552,78 @@ fn run_unlock(args: &Args) -> Result<(), Error> {
             continue;
         }
 
+        if args.dry_run {
+            preview_unlock(path, grace.is_some())?;
+            continue;
+        }
+
         let is_dir = rmx::winapi::is_directory(path);
         if is_dir {
             #[cfg(windows)]
             if args.gui {
                 unlock_directory_gui(path)?;
             } else {
-                unlock_directory(path, verbose)?;
+                unlock_directory(path, verbose, grace)?;
             }
 
             #[cfg(not(windows))]
-            unlock_directory(path, verbose)?;
+            unlock_directory(path, verbose, grace)?;
         } else {
             #[cfg(windows)]
             if args.gui {
                 unlock_single_file_gui(path)?;
             } else {
-                unlock_single_file(path, verbose)?;
+                unlock_single_file(path, verbose, grace)?;
             }
 
             #[cfg(not(windows))]
-            unlock_single_file(path, verbose)?;
+            unlock_single_file(path, verbose, grace)?;
         }
     }
 
     Ok(())
 }
 
-#[cfg(windows)]
-fn unlock_directory_gui(path: &Path) -> Result<(), Error> {
+fn run_list_locks(args: &Args) -> Result<(), Error> {
+    for path in &args.paths {
+        if !rmx::winapi::path_exists(path) {
+            eprintln!(
+                "rmx: cannot access '{}': No such file or directory",
+                path.display()
+            );
+            continue;
+        }
+
+        let mut processes = if rmx::winapi::is_directory(path) {
+            find_directory_locks(path)?
+        } else {
+            rmx::winapi::find_locking_processes(path).unwrap_or_default()
+        };
+        sort_and_dedup(&mut processes);
+
+        println!("{}:", path.display());
+        if processes.is_empty() {
+            println!("  no locking processes");
+            continue;
+        }
+        println!("  {:>7}  {:<24} Path", "PID", "Name");
+        for p in &processes {
+            println!(
+                "  {:>7}  {:<24} {}",
+                p.pid,
+                p.name,
+                p.exe_path.as_deref().unwrap_or("-")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Every process holding a handle to `path` or anything below it, sorted by
+/// PID and deduplicated.
+/// Every file and every directory (`path` included) below `path`
+fn directory_unlock_paths(path: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>), Error> {
     let tree = tree::discover_tree(path).map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
 
     let mut all_files: Vec<PathBuf> = Vec::new();
@@ -1046,31 +2634,83 @@ fn unlock_directory_gui(path: &Path) -> Result<(), Error> {
     let mut all_dirs: Vec<PathBuf> = tree.dirs.clone();
     all_dirs.push(path.to_path_buf());
 
-    let total_items = all_files.len() + all_dirs.len();
-    if total_items == 0 {
-        return Ok(());
+    Ok((all_files, all_dirs))
+}
+
+/// `--unlock --dry-run`: report the processes that would be stopped and
+/// the handles that would then be force-closed, touching neither.
+fn preview_unlock(path: &Path, graceful: bool) -> Result<(), Error> {
+    println!("would unlock '{}'...", path.display());
+
+    let (mut processes, paths) = if rmx::winapi::is_directory(path) {
+        let (mut all_paths, all_dirs) = directory_unlock_paths(path)?;
+        all_paths.extend(all_dirs);
+        (find_directory_locks(path)?, all_paths)
+    } else {
+        let processes = rmx::winapi::find_locking_processes(path).unwrap_or_default();
+        (processes, vec![path.to_path_buf()])
+    };
+    // The System process is never killed
+    processes.retain(|p| p.pid != 0 && p.pid != 4);
+    sort_and_dedup(&mut processes);
+
+    let action = if graceful {
+        "would ask to close"
+    } else {
+        "would kill"
+    };
+    for p in &processes {
+        println!("  {} '{}' (PID {})", action, p.name, p.pid);
     }
 
-    let mut all_locking_procs: Vec<rmx::winapi::LockingProcess> = Vec::new();
+    // Handles of a process that is stopped first go away with it
+    let mut handles = rmx::winapi::find_file_handles(&paths, false)
+        .map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
+    handles.retain(|h| !processes.iter().any(|p| p.pid == h.pid));
+    for h in &handles {
+        println!(
+            "  would close handle 0x{:04X} in PID {} on '{}'",
+            h.handle,
+            h.pid,
+            h.path.display()
+        );
+    }
 
-    #[cfg(windows)]
-    {
-        if !all_files.is_empty() {
-            if let Ok(procs) = rmx::winapi::find_locking_processes_batch(&all_files) {
-                all_locking_procs.extend(procs);
-            }
-        }
+    println!(
+        "  done: {} {} process(es), would close {} handle(s)",
+        action,
+        processes.len(),
+        handles.len()
+    );
+    Ok(())
+}
 
-        if !all_dirs.is_empty() {
-            if let Ok(procs) = rmx::winapi::find_locking_processes_batch(&all_dirs) {
-                all_locking_procs.extend(procs);
-            }
-        }
+fn find_directory_locks(path: &Path) -> Result<Vec<rmx::winapi::LockingProcess>, Error> {
+    let (all_files, all_dirs) = directory_unlock_paths(path)?;
 
-        all_locking_procs.sort_by(|a, b| a.pid.cmp(&b.pid));
-        all_locking_procs.dedup_by(|a, b| a.pid == b.pid);
+    let mut all_locking_procs: Vec<rmx::winapi::LockingProcess> = Vec::new();
+    if !all_files.is_empty() {
+        if let Ok(procs) = rmx::winapi::find_locking_processes_batch(&all_files) {
+            all_locking_procs.extend(procs);
+        }
+    }
+    if let Ok(procs) = rmx::winapi::find_locking_processes_batch(&all_dirs) {
+        all_locking_procs.extend(procs);
     }
 
+    sort_and_dedup(&mut all_locking_procs);
+    Ok(all_locking_procs)
+}
+
+fn sort_and_dedup(processes: &mut Vec<rmx::winapi::LockingProcess>) {
+    processes.sort_by_key(|p| p.pid);
+    processes.dedup_by(|a, b| a.pid == b.pid);
+}
+
+#[cfg(windows)]
+fn unlock_directory_gui(path: &Path) -> Result<(), Error> {
+    let all_locking_procs = find_directory_locks(path)?;
+
     let file_infos = vec![progress_ui::UnlockFileInfo {
         file_name: path
             .file_name()
@@ -1101,15 +2741,15 @@ fn unlock_single_file_gui(path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-fn unlock_single_file(path: &Path, verbose: bool) -> Result<(), Error> {
+fn unlock_single_file(path: &Path, verbose: bool, grace: Option<Duration>) -> Result<(), Error> {
     if verbose {
         println!("unlocking '{}'...", path.display());
     }
 
-    match rmx::winapi::kill_locking_processes(path, verbose) {
+    match rmx::winapi::kill_locking_processes(path, verbose, grace) {
         Ok(killed) if !killed.is_empty() => {
-            for p in &killed {
-                println!("  killed '{}' (PID {})", p.name, p.pid);
+            for (p, outcome) in &killed {
+                println!("  {} '{}' (PID {})", outcome.as_str(), p.name, p.pid);
             }
         }
         _ => {}
@@ -1130,18 +2770,10 @@ fn unlock_single_file(path: &Path, verbose: bool) -> Result<(), Error> {
     Ok(())
 }
 
-fn unlock_directory(path: &Path, verbose: bool) -> Result<(), Error> {
+fn unlock_directory(path: &Path, verbose: bool, grace: Option<Duration>) -> Result<(), Error> {
     println!("unlocking directory '{}'...", path.display());
 
-    let tree = tree::discover_tree(path).map_err(|e| Error::io_with_path(path.to_path_buf(), e))?;
-
-    let mut all_files: Vec<PathBuf> = Vec::new();
-    for files in tree.dir_files.values() {
-        all_files.extend(files.iter().cloned());
-    }
-
-    let mut all_dirs: Vec<PathBuf> = tree.dirs.clone();
-    all_dirs.push(path.to_path_buf());
+    let (all_files, all_dirs) = directory_unlock_paths(path)?;
 
     let total_items = all_files.len() + all_dirs.len();
     println!(
@@ -1156,17 +2788,21 @@ fn unlock_directory(path: &Path, verbose: bool) -> Result<(), Error> {
     }
 
     let mut total_killed = 0usize;
+    let mut total_closed = 0usize;
     let mut total_handles_closed = 0usize;
 
     if !all_files.is_empty() {
-        match rmx::winapi::kill_locking_processes_batch(&all_files, verbose) {
+        match rmx::winapi::kill_locking_processes_batch(&all_files, verbose, grace) {
             Ok(killed) => {
-                for p in &killed {
+                for (p, outcome) in &killed {
                     if verbose {
-                        println!("  killed '{}' (PID {})", p.name, p.pid);
+                        println!("  {} '{}' (PID {})", outcome.as_str(), p.name, p.pid);
+                    }
+                    match outcome {
+                        KillOutcome::Closed => total_closed += 1,
+                        KillOutcome::Terminated => total_killed += 1,
                     }
                 }
-                total_killed += killed.len();
             }
             Err(e) => {
                 if verbose {
@@ -1177,14 +2813,17 @@ fn unlock_directory(path: &Path, verbose: bool) -> Result<(), Error> {
     }
 
     if !all_dirs.is_empty() {
-        match rmx::winapi::kill_locking_processes_batch(&all_dirs, verbose) {
+        match rmx::winapi::kill_locking_processes_batch(&all_dirs, verbose, grace) {
             Ok(killed) => {
-                for p in &killed {
+                for (p, outcome) in &killed {
                     if verbose {
-                        println!("  killed '{}' (PID {})", p.name, p.pid);
+                        println!("  {} '{}' (PID {})", outcome.as_str(), p.name, p.pid);
+                    }
+                    match outcome {
+                        KillOutcome::Closed => total_closed += 1,
+                        KillOutcome::Terminated => total_killed += 1,
                     }
                 }
-                total_killed += killed.len();
             }
             Err(e) => {
                 if verbose {
@@ -1209,10 +2848,17 @@ fn unlock_directory(path: &Path, verbose: bool) -> Result<(), Error> {
         }
     }
 
-    println!(
-        "  done: killed {} process(es), closed {} handle(s)",
-        total_killed, total_handles_closed
-    );
+    if grace.is_some() {
+        println!(
+            "  done: {} process(es) closed gracefully, {} killed, closed {} handle(s)",
+            total_closed, total_killed, total_handles_closed
+        );
+    } else {
+        println!(
+            "  done: killed {} process(es), closed {} handle(s)",
+            total_killed, total_handles_closed
+        );
+    }
 
     Ok(())
 }
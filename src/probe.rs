@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::safety::{self, SafetyCheck};
+use crate::tree::DirectoryTree;
+use crate::winapi::{self, LockingProcess};
+
+/// Output format for `rmx probe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ProbeFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Classification result for a single probed path
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeRecord {
+    pub path: PathBuf,
+    pub exists: bool,
+    /// `"file"`, `"dir"`, or `None` when the path does not exist
+    pub kind: Option<&'static str>,
+    pub is_symlink: bool,
+    pub size: u64,
+}
+
+/// Classify every path in parallel. Never modifies the filesystem.
+pub fn probe_paths(paths: &[PathBuf]) -> Vec<ProbeRecord> {
+    paths
+        .par_iter()
+        .map(|path| {
+            let stat = winapi::stat_path(path);
+            ProbeRecord {
+                path: path.clone(),
+                exists: stat.exists,
+                kind: match (stat.exists, stat.is_dir) {
+                    (false, _) => None,
+                    (true, true) => Some("dir"),
+                    (true, false) => Some("file"),
+                },
+                is_symlink: stat.is_symlink,
+                size: stat.size,
+            }
+        })
+        .collect()
+}
+
+/// Pre-flight for `--dry-run --check`: probe every entry the tree would
+/// delete and return the ones that would likely fail, with the reason.
+pub fn preflight(tree: &DirectoryTree) -> Vec<(PathBuf, String)> {
+    let files = tree
+        .dir_files
+        .par_iter()
+        .flat_map_iter(|(_, files)| files.iter().map(|f| (f, false)));
+    let dirs = tree
+        .dirs
+        .par_iter()
+        .filter(|d| !tree.kept_dirs.contains(*d))
+        .map(|d| (d, true));
+
+    let mut blocked: Vec<(PathBuf, String)> = files
+        .chain(dirs)
+        .filter_map(|(path, is_dir)| {
+            let err = winapi::probe_deletable(path, is_dir).err()?;
+            Some((path.clone(), describe_block(path, &err)))
+        })
+        .collect();
+    blocked.sort();
+    blocked
+}
+
+/// Files registered per Restart Manager session by [`find_locks`]
+const LOCK_CHUNK: usize = 1000;
+
+/// A process and the scanned files it holds open
+#[derive(Debug, Clone)]
+pub struct LockGroup {
+    pub process: LockingProcess,
+    pub paths: Vec<PathBuf>,
+}
+
+/// For `--dry-run --check-locks`: ask the Restart Manager which processes
+/// hold the files of `tree` open, grouped by process. Nothing is killed.
+pub fn find_locks(tree: &DirectoryTree) -> Vec<LockGroup> {
+    let files: Vec<PathBuf> = tree.dir_files.values().flatten().cloned().collect();
+    let found: Vec<(PathBuf, LockingProcess)> = files
+        .par_chunks(LOCK_CHUNK)
+        .flat_map_iter(|chunk| {
+            let mut found = Vec::new();
+            locate_locks(chunk, &winapi::find_locking_processes_batch, &mut found);
+            found
+        })
+        .collect();
+    group_by_process(found)
+}
+
+/// Holders of a single file, in the same shape as [`find_locks`]
+pub fn find_file_locks(path: &Path) -> Vec<LockGroup> {
+    let holders = winapi::find_locking_processes(path).unwrap_or_default();
+    group_by_process(
+        holders
+            .into_iter()
+            .map(|process| (path.to_path_buf(), process))
+            .collect(),
+    )
+}
+
+/// One session answers "is anything in `paths` locked"; only locked halves
+/// are split further, so a mostly unlocked tree costs one query per chunk.
+fn locate_locks<F>(paths: &[PathBuf], lookup: &F, found: &mut Vec<(PathBuf, LockingProcess)>)
+where
+    F: Fn(&[PathBuf]) -> io::Result<Vec<LockingProcess>>,
+{
+    let holders = lookup(paths).unwrap_or_default();
+    if holders.is_empty() {
+        return;
+    }
+    if let [path] = paths {
+        found.extend(holders.into_iter().map(|process| (path.clone(), process)));
+        return;
+    }
+    let (left, right) = paths.split_at(paths.len() / 2);
+    locate_locks(left, lookup, found);
+    locate_locks(right, lookup, found);
+}
+
+fn group_by_process(found: Vec<(PathBuf, LockingProcess)>) -> Vec<LockGroup> {
+    let mut groups: BTreeMap<u32, LockGroup> = BTreeMap::new();
+    for (path, process) in found {
+        groups
+            .entry(process.pid)
+            .or_insert_with(|| LockGroup {
+                process,
+                paths: Vec::new(),
+            })
+            .paths
+            .push(path);
+    }
+    let mut groups: Vec<LockGroup> = groups.into_values().collect();
+    for group in &mut groups {
+        group.paths.sort();
+        group.paths.dedup();
+    }
+    groups
+}
+
+/// Explain why `path` can't be deleted, naming lock holders when known.
+pub fn describe_block(path: &Path, err: &io::Error) -> String {
+    if winapi::is_file_in_use_error(err) {
+        let holders = winapi::find_locking_processes(path).unwrap_or_default();
+        if !holders.is_empty() {
+            let names: Vec<String> = holders
+                .iter()
+                .map(|p| format!("{} (PID {})", p.name, p.pid))
+                .collect();
+            return format!("locked by {}", names.join(", "));
+        }
+    }
+    err.to_string()
+}
+
+/// Read one path per line from `list`, skipping blank lines.
+pub fn read_path_list(list: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(parse_path_list(&fs::read(list)?, false))
+}
+
+/// Split a path list into paths. Handles UTF-8 and UTF-16 (LE/BE) BOMs,
+/// since Windows tools often emit UTF-16LE.
+///
+/// With `null_separated`, entries are split on NUL and taken verbatim, so
+/// paths may contain newlines. Otherwise there is one path per line: blank
+/// lines are skipped, trailing whitespace is dropped and a pair of enclosing
+/// quotes (as from Explorer's "Copy as path") is removed.
+pub fn parse_path_list(bytes: &[u8], null_separated: bool) -> Vec<PathBuf> {
+    let text = decode_text(bytes);
+
+    if null_separated {
+        text.split('\0')
+            .filter(|entry| !entry.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    } else {
+        text.lines()
+            .map(|line| unquote(line.trim_end()))
+            .filter(|line| !line.trim().is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+fn unquote(line: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = line
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    line
+}
+
+fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// 执行 `rmx probe`
+pub fn run_probe(files_from: &Path, format: ProbeFormat) -> io::Result<()> {
+    let paths = read_path_list(files_from).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("cannot read '{}': {}", files_from.display(), e),
+        )
+    })?;
+
+    let records = probe_paths(&paths);
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    match format {
+        ProbeFormat::Json => {
+            serde_json::to_writer(&mut out, &records).map_err(io::Error::other)?;
+            writeln!(out)?;
+        }
+        ProbeFormat::Text => {
+            for record in &records {
+                let kind = match (record.kind, record.is_symlink) {
+                    (None, _) => "missing".to_string(),
+                    (Some(k), true) => format!("{}+link", k),
+                    (Some(k), false) => k.to_string(),
+                };
+                writeln!(
+                    out,
+                    "{:<9} {:>14} {}",
+                    kind,
+                    record.size,
+                    record.path.display()
+                )?;
+            }
+        }
+    }
+
+    out.flush()
+}
+
+/// `rmx check`: the safety verdict for a single path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    NotFound,
+    Safe,
+    Dangerous { reason: String, can_override: bool },
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::NotFound => f.write_str("not found"),
+            Verdict::Safe => f.write_str("safe"),
+            Verdict::Dangerous {
+                reason,
+                can_override: true,
+            } => write!(f, "dangerous: {} (override with -f)", reason),
+            Verdict::Dangerous {
+                reason,
+                can_override: false,
+            } => write!(f, "dangerous: {} (cannot override)", reason),
+        }
+    }
+}
+
+/// Run the same safety checks as a deletion would. Never modifies the
+/// filesystem.
+pub fn check_path(path: &Path) -> Verdict {
+    let path = safety::absolute_operand(path);
+    if !winapi::path_exists(&path) {
+        return Verdict::NotFound;
+    }
+    match safety::check_path_safety(&path) {
+        SafetyCheck::Safe => Verdict::Safe,
+        SafetyCheck::Dangerous {
+            reason,
+            can_override,
+        } => Verdict::Dangerous {
+            reason,
+            can_override,
+        },
+    }
+}
+
+/// Print a verdict per path; fails if any path can never be deleted.
+pub fn run_check(paths: &[PathBuf]) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut blocked = 0;
+
+    for path in paths {
+        let verdict = check_path(path);
+        if matches!(
+            verdict,
+            Verdict::Dangerous {
+                can_override: false,
+                ..
+            }
+        ) {
+            blocked += 1;
+        }
+        let stat = winapi::stat_path(path);
+        let kind = match (stat.exists, stat.is_dir, stat.is_symlink) {
+            (false, _, _) => String::new(),
+            (true, is_dir, link) => format!(
+                " ({}{})",
+                if is_dir { "dir" } else { "file" },
+                if link { "+link" } else { "" }
+            ),
+        };
+        writeln!(out, "{}{}: {}", path.display(), kind, verdict)?;
+    }
+    out.flush()?;
+
+    if blocked > 0 {
+        return Err(io::Error::other(format!(
+            "{} path(s) cannot be deleted",
+            blocked
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_locks_narrows_to_locked_files() {
+        let paths: Vec<PathBuf> = (0..10).map(|i| PathBuf::from(format!("f{}", i))).collect();
+        let holder = |pid| LockingProcess {
+            pid,
+            name: format!("p{}", pid),
+            exe_path: None,
+        };
+        let queries = std::cell::Cell::new(0);
+        let lookup = |chunk: &[PathBuf]| {
+            queries.set(queries.get() + 1);
+            let mut holders = Vec::new();
+            if chunk.contains(&PathBuf::from("f3")) {
+                holders.push(holder(7));
+            }
+            if chunk.contains(&PathBuf::from("f8")) {
+                holders.push(holder(7));
+            }
+            Ok(holders)
+        };
+
+        let mut found = Vec::new();
+        locate_locks(&paths, &lookup, &mut found);
+        let groups = group_by_process(found);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].process.pid, 7);
+        assert_eq!(groups[0].paths, [PathBuf::from("f3"), PathBuf::from("f8")]);
+        assert!(queries.get() < 2 * paths.len());
+
+        let mut found = Vec::new();
+        locate_locks(&paths, &|_: &[PathBuf]| Ok(Vec::new()), &mut found);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_check_path_verdicts() {
+        let missing = std::env::temp_dir().join("rmx_check_missing");
+        assert_eq!(check_path(&missing), Verdict::NotFound);
+
+        let exe_dir = std::env::current_exe().unwrap();
+        let exe_dir = exe_dir.parent().unwrap();
+        let verdict = check_path(exe_dir);
+        assert!(
+            verdict.to_string().ends_with("(override with -f)"),
+            "{}",
+            verdict
+        );
+
+        #[cfg(unix)]
+        assert!(check_path(Path::new("/usr"))
+            .to_string()
+            .ends_with("(cannot override)"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Per-directory timing for `--profile` and per-worker counters for
+//! `--worker-stats`. Only allocated when enabled, so workers pay a single
+//! `None` check otherwise.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crossbeam_queue::SegQueue;
+
+/// Bucket `i` counts `remove_dir` calls that took less than `2^i` µs
+const BUCKETS: usize = 32;
+
+const PERCENTILES: [u32; 4] = [50, 90, 99, 100];
+
+/// How many of the slowest directories a report lists
+const SLOWEST: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct DirTiming {
+    pub path: PathBuf,
+    /// Deleting the files directly inside the directory
+    pub files: Duration,
+    /// The `remove_dir` call itself (zero for kept directories)
+    pub remove: Duration,
+}
+
+impl DirTiming {
+    pub fn total(&self) -> Duration {
+        self.files + self.remove
+    }
+}
+
+pub struct Profiler {
+    remove_histogram: [AtomicU64; BUCKETS],
+    timings: SegQueue<DirTiming>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            remove_histogram: std::array::from_fn(|_| AtomicU64::new(0)),
+            timings: SegQueue::new(),
+        }
+    }
+
+    /// Record a processed directory; `remove` is `None` if it was kept.
+    pub fn record_dir(&self, path: &Path, files: Duration, remove: Option<Duration>) {
+        if let Some(remove) = remove {
+            let micros = remove.as_micros() as u64;
+            let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+            self.remove_histogram[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        }
+        self.timings.push(DirTiming {
+            path: path.to_path_buf(),
+            files,
+            remove: remove.unwrap_or_default(),
+        });
+    }
+
+    /// Record one batch of a large directory's files, deleted separately
+    /// from the directory itself.
+    pub fn record_files(&self, path: &Path, files: Duration) {
+        self.timings.push(DirTiming {
+            path: path.to_path_buf(),
+            files,
+            remove: Duration::ZERO,
+        });
+    }
+
+    /// Drain the recorded timings into a report.
+    pub fn report(&self, scan: Duration) -> ProfileReport {
+        let mut by_dir: HashMap<PathBuf, DirTiming> = HashMap::new();
+        while let Some(timing) = self.timings.pop() {
+            by_dir
+                .entry(timing.path.clone())
+                .and_modify(|t| {
+                    t.files += timing.files;
+                    t.remove += timing.remove;
+                })
+                .or_insert(timing);
+        }
+        let mut slowest: Vec<DirTiming> = by_dir.into_values().collect();
+        slowest.sort_by_key(|t| Reverse(t.total()));
+        slowest.truncate(SLOWEST);
+
+        let counts: Vec<u64> = self
+            .remove_histogram
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .collect();
+        let removed: u64 = counts.iter().sum();
+        let remove_percentiles = PERCENTILES
+            .iter()
+            .map(|&p| (p, percentile_bound(&counts, removed, p)))
+            .collect();
+
+        ProfileReport {
+            scan,
+            removed,
+            remove_percentiles,
+            slowest,
+        }
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Upper bound of the bucket holding the `p`th percentile
+fn percentile_bound(counts: &[u64], total: u64, p: u32) -> Duration {
+    if total == 0 {
+        return Duration::ZERO;
+    }
+    let target = (total * p as u64).div_ceil(100).max(1);
+    let mut seen = 0;
+    for (bucket, &count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return Duration::from_micros(1u64 << bucket);
+        }
+    }
+    Duration::from_micros(1u64 << (counts.len() - 1))
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileReport {
+    pub scan: Duration,
+    /// Number of `remove_dir` calls in the histogram
+    pub removed: u64,
+    /// `(percentile, upper bound)` of `remove_dir` latency
+    pub remove_percentiles: Vec<(u32, Duration)>,
+    pub slowest: Vec<DirTiming>,
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "\nProfile:")?;
+        writeln!(f, "  Scan:        {:.2?}", self.scan)?;
+        writeln!(f, "  remove_dir latency ({} calls):", self.removed)?;
+        for (p, bound) in &self.remove_percentiles {
+            writeln!(f, "    p{:<3} <= {:?}", p, bound)?;
+        }
+        writeln!(f, "  Slowest directories (files + remove_dir):")?;
+        for timing in &self.slowest {
+            writeln!(
+                f,
+                "    {:>10.2?} {:>10.2?}  {}",
+                timing.files,
+                timing.remove,
+                timing.path.display()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct WorkerCounters {
+    items: AtomicUsize,
+    busy_nanos: AtomicU64,
+    idle_nanos: AtomicU64,
+}
+
+/// How many work items each worker thread processed and how long it waited
+/// for work, to tell a broker bottleneck from a thread shortage.
+pub struct WorkerStats {
+    workers: Vec<WorkerCounters>,
+}
+
+impl WorkerStats {
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: (0..workers).map(|_| WorkerCounters::default()).collect(),
+        }
+    }
+
+    /// Worker `index` processed one item in `busy` after waiting `idle` for it.
+    pub fn record_item(&self, index: usize, idle: Duration, busy: Duration) {
+        let counters = &self.workers[index];
+        counters.items.fetch_add(1, Ordering::Relaxed);
+        counters
+            .idle_nanos
+            .fetch_add(idle.as_nanos() as u64, Ordering::Relaxed);
+        counters
+            .busy_nanos
+            .fetch_add(busy.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Worker `index` waited `idle` without getting an item.
+    pub fn record_idle(&self, index: usize, idle: Duration) {
+        self.workers[index]
+            .idle_nanos
+            .fetch_add(idle.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn report(&self) -> WorkerReport {
+        WorkerReport {
+            workers: self
+                .workers
+                .iter()
+                .map(|c| WorkerLoad {
+                    items: c.items.load(Ordering::Relaxed),
+                    busy: Duration::from_nanos(c.busy_nanos.load(Ordering::Relaxed)),
+                    idle: Duration::from_nanos(c.idle_nanos.load(Ordering::Relaxed)),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerLoad {
+    pub items: usize,
+    pub busy: Duration,
+    pub idle: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerReport {
+    /// Indexed by worker number
+    pub workers: Vec<WorkerLoad>,
+}
+
+impl WorkerReport {
+    /// Items of the busiest worker over the mean; 1.0 is perfectly balanced
+    pub fn imbalance(&self) -> f64 {
+        let total: usize = self.workers.iter().map(|w| w.items).sum();
+        let max = self.workers.iter().map(|w| w.items).max().unwrap_or(0);
+        if total == 0 {
+            return 1.0;
+        }
+        max as f64 * self.workers.len() as f64 / total as f64
+    }
+}
+
+impl fmt::Display for WorkerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "\nWorkers ({}):", self.workers.len())?;
+        writeln!(
+            f,
+            "  {:>6} {:>8} {:>10} {:>10}",
+            "worker", "items", "busy", "idle"
+        )?;
+        for (i, load) in self.workers.iter().enumerate() {
+            writeln!(
+                f,
+                "  {:>6} {:>8} {:>10.2?} {:>10.2?}",
+                i, load.items, load.busy, load.idle
+            )?;
+        }
+        writeln!(f, "  Imbalance (max / mean items): {:.2}", self.imbalance())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_merges_batches_and_ranks() {
+        let profiler = Profiler::new();
+        let ms = Duration::from_millis;
+        profiler.record_dir(Path::new("a"), ms(1), Some(ms(1)));
+        profiler.record_dir(Path::new("b"), ms(2), Some(Duration::from_micros(3)));
+        profiler.record_files(Path::new("a"), ms(5));
+        profiler.record_dir(Path::new("kept"), ms(1), None);
+
+        let report = profiler.report(ms(4));
+        assert_eq!(report.removed, 2);
+        assert_eq!(report.slowest[0].path, Path::new("a"));
+        assert_eq!(report.slowest[0].files, ms(6));
+        assert_eq!(report.slowest.len(), 3);
+        // 3µs lands in the [2, 4) bucket, 1ms in [512, 1024)
+        assert_eq!(report.remove_percentiles[0], (50, Duration::from_micros(4)));
+        assert_eq!(
+            report.remove_percentiles[3],
+            (100, Duration::from_micros(1024))
+        );
+    }
+
+    #[test]
+    fn test_worker_report_imbalance() {
+        let stats = WorkerStats::new(4);
+        let ms = Duration::from_millis;
+        for _ in 0..6 {
+            stats.record_item(0, ms(1), ms(2));
+        }
+        stats.record_item(1, ms(3), ms(1));
+        stats.record_idle(3, ms(5));
+
+        let report = stats.report();
+        assert_eq!(report.workers[0].items, 6);
+        assert_eq!(report.workers[0].busy, ms(12));
+        assert_eq!(report.workers[1].idle, ms(3));
+        assert_eq!(report.workers[3].items, 0);
+        assert_eq!(report.workers[3].idle, ms(5));
+        // 6 items on one worker against a mean of 7 / 4
+        assert!((report.imbalance() - 24.0 / 7.0).abs() < 1e-9);
+        assert_eq!(WorkerStats::new(2).report().imbalance(), 1.0);
+    }
+}
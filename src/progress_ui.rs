@@ -13,9 +13,18 @@ use gpui_component::progress::Progress;
 use gpui_component::{ActiveTheme, IconName, Root, Sizable};
 use gpui_component_assets::Assets;
 
+use crate::error::FailedItem;
+use crate::ui_strings::strings;
+use crate::worker::ProgressSink;
+
 const MIN_DISPLAY_DURATION: Duration = Duration::from_millis(800);
 const FAST_DELETE_THRESHOLD: usize = 50;
 
+/// Height of one row (path + reason) in the error list
+const ERROR_ROW_HEIGHT: f32 = 38.0;
+/// Rows visible before the error list scrolls
+const ERROR_ROWS_VISIBLE: usize = 4;
+
 pub struct DeleteProgress {
     pub total_files: usize,
     pub total_dirs: usize,
@@ -96,6 +105,33 @@ impl DeleteProgress {
     }
 }
 
+/// Split a `"path: reason"` message. Drive letters (`C:\`) have no space
+/// after the colon, so the first `": "` ends the path.
+fn split_error(message: &str) -> (&str, &str) {
+    message.split_once(": ").unwrap_or(("", message))
+}
+
+impl ProgressSink for DeleteProgress {
+    fn on_dir_complete(&self, completed: usize, _total: usize) {
+        self.deleted_dirs.store(completed, Ordering::Relaxed);
+    }
+
+    fn on_error(&self, failure: &FailedItem) {
+        self.errors
+            .lock()
+            .push(format!("{}: {}", failure.path.display(), failure.error));
+        self.error_count.fetch_add(1, Ordering::Release);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        DeleteProgress::is_cancelled(self)
+    }
+
+    fn on_finish(&self) {
+        self.mark_complete();
+    }
+}
+
 pub struct DeleteProgressWindow {
     progress: Arc<DeleteProgress>,
     path: PathBuf,
@@ -140,7 +176,8 @@ impl Render for DeleteProgressWindow {
 
         if is_complete && has_errors && !self.resized_for_errors {
             self.resized_for_errors = true;
-            window.resize(size(px(420.0), px(270.0)));
+            let list_height = ERROR_ROW_HEIGHT * error_count.min(ERROR_ROWS_VISIBLE) as f32;
+            window.resize(size(px(480.0), px(250.0 + list_height)));
         }
 
         if self.should_auto_close() && !has_errors {
@@ -167,19 +204,23 @@ impl Render for DeleteProgressWindow {
         let success_color = theme.success;
 
         let (icon_name, icon_color, title) = if is_complete && has_errors {
-            (IconName::TriangleAlert, danger_color, "删除完成（有错误）")
+            (
+                IconName::TriangleAlert,
+                danger_color,
+                strings().done_with_errors,
+            )
         } else if is_complete {
-            (IconName::CircleCheck, success_color, "删除完成")
+            (IconName::CircleCheck, success_color, strings().done)
         } else {
-            (IconName::LoaderCircle, muted_fg, "正在删除...")
+            (IconName::LoaderCircle, muted_fg, strings().deleting)
         };
 
         let status_text = if is_complete && has_errors {
-            format!("完成，{} 个错误", error_count)
+            (strings().completed_errors)(error_count)
         } else if is_complete {
-            "已完成".to_string()
+            strings().completed.to_string()
         } else {
-            format!("已删除 {} / {} 个目录", deleted_dirs, total_dirs)
+            (strings().deleted_dirs)(deleted_dirs, total_dirs)
         };
 
         let status_color = if is_complete && has_errors {
@@ -290,27 +331,50 @@ impl Render for DeleteProgressWindow {
             );
 
         if is_complete && has_errors {
-            if let Some(error_msg) = self.progress.get_first_error() {
-                let display_error = if error_msg.len() > 70 {
-                    format!("{}...", &error_msg[..67])
-                } else {
-                    error_msg
-                };
-                content = content.child(
+            let mut error_list = div()
+                .id("error-list")
+                .flex()
+                .flex_col()
+                .mx_4()
+                .mb_2()
+                .rounded_md()
+                .border_1()
+                .border_color(danger_color.opacity(0.3))
+                .bg(danger_color.opacity(0.05))
+                .max_h(px(ERROR_ROW_HEIGHT * ERROR_ROWS_VISIBLE as f32))
+                .overflow_y_scroll();
+
+            for message in &errors_for_copy {
+                let (path, reason) = split_error(message);
+                error_list = error_list.child(
                     div()
-                        .mx_4()
-                        .mb_2()
+                        .flex()
+                        .flex_col()
+                        .flex_shrink_0()
+                        .h(px(ERROR_ROW_HEIGHT))
+                        .justify_center()
                         .px_3()
-                        .py_2()
-                        .rounded_md()
-                        .bg(danger_color.opacity(0.08))
-                        .text_xs()
-                        .text_color(danger_color)
-                        .overflow_hidden()
-                        .whitespace_nowrap()
-                        .child(display_error),
+                        .border_b_1()
+                        .border_color(danger_color.opacity(0.15))
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(fg)
+                                .overflow_hidden()
+                                .whitespace_nowrap()
+                                .child(path.to_string()),
+                        )
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(danger_color)
+                                .overflow_hidden()
+                                .whitespace_nowrap()
+                                .child(reason.to_string()),
+                        ),
                 );
             }
+            content = content.child(error_list);
         }
 
         content.child(
@@ -329,7 +393,7 @@ impl Render for DeleteProgressWindow {
                     this.child(
                         Button::new("copy-errors")
                             .ghost()
-                            .label("复制错误")
+                            .label(strings().copy_errors)
                             .on_click(move |_, _, cx| {
                                 let text = errors_for_copy.join("\n");
                                 cx.write_to_clipboard(ClipboardItem::new_string(text));
@@ -339,14 +403,14 @@ impl Render for DeleteProgressWindow {
                 .child(if is_complete {
                     Button::new("close")
                         .primary()
-                        .label("关闭")
+                        .label(strings().close)
                         .on_click(|_, _, cx| {
                             cx.quit();
                         })
                 } else {
                     Button::new("cancel")
                         .ghost()
-                        .label("取消")
+                        .label(strings().cancel)
                         .on_click(move |_, _, cx| {
                             progress_clone.cancel();
                             cx.quit();
@@ -434,16 +498,16 @@ impl ConfirmDeleteWindow {
 
     fn format_item_summary(&self) -> String {
         if self.total_dirs == 0 && self.total_files <= 1 {
-            return "1 个文件".to_string();
+            return strings().one_file.to_string();
         }
         let mut parts = Vec::new();
         if self.total_files > 0 {
-            parts.push(format!("{} 个文件", self.total_files));
+            parts.push((strings().files)(self.total_files));
         }
         if self.total_dirs > 0 {
-            parts.push(format!("{} 个目录", self.total_dirs));
+            parts.push((strings().dirs)(self.total_dirs));
         }
-        parts.join("，")
+        parts.join(strings().list_separator)
     }
 }
 
@@ -504,13 +568,13 @@ impl Render for ConfirmDeleteWindow {
                                             .text_base()
                                             .font_weight(FontWeight::SEMIBOLD)
                                             .text_color(fg)
-                                            .child("确认删除"),
+                                            .child(strings().confirm_title),
                                     )
                                     .child(
                                         div()
                                             .text_xs()
                                             .text_color(muted_fg)
-                                            .child("此操作不可撤销，文件不会进入回收站"),
+                                            .child(strings().confirm_warning),
                                     ),
                             ),
                     )
@@ -556,7 +620,7 @@ impl Render for ConfirmDeleteWindow {
                     .child(
                         gpui_component::checkbox::Checkbox::new("skip-confirm-checkbox")
                             .checked(skip_checked)
-                            .label("下次不再确认")
+                            .label(strings().skip_next_confirm)
                             .with_size(gpui_component::Size::Small)
                             .on_click(move |checked, _, _| {
                                 state_checkbox.set_skip_next_confirm(*checked);
@@ -571,7 +635,7 @@ impl Render for ConfirmDeleteWindow {
                             .child(
                                 Button::new("cancel-btn")
                                     .ghost()
-                                    .label("取消")
+                                    .label(strings().cancel)
                                     .on_click(move |_, _, cx| {
                                         state_cancel.cancel();
                                         cx.quit();
@@ -580,7 +644,7 @@ impl Render for ConfirmDeleteWindow {
                             .child(
                                 Button::new("confirm-btn")
                                     .danger()
-                                    .label("删除")
+                                    .label(strings().delete)
                                     .icon(IconName::Delete)
                                     .on_click(move |_, _, cx| {
                                         state.confirm();
@@ -625,7 +689,7 @@ pub fn run_confirmation_dialog(
         cx.spawn(async move |cx| {
             let window_options = WindowOptions {
                 titlebar: Some(TitlebarOptions {
-                    title: Some("确认删除".into()),
+                    title: Some(strings().confirm_title.into()),
                     ..Default::default()
                 }),
                 window_bounds: Some(WindowBounds::Windowed(window_bounds)),
@@ -666,7 +730,7 @@ pub fn run_progress_window(progress: Arc<DeleteProgress>, path: PathBuf) -> anyh
         cx.spawn(async move |cx| {
             let window_options = WindowOptions {
                 titlebar: Some(TitlebarOptions {
-                    title: Some("删除进度".into()),
+                    title: Some(strings().progress_title.into()),
                     ..Default::default()
                 }),
                 window_bounds: Some(WindowBounds::Windowed(window_bounds)),
@@ -803,7 +867,7 @@ impl UnlockProgressWindow {
                         .text_xs()
                         .font_weight(FontWeight::MEDIUM)
                         .text_color(muted_fg)
-                        .child("名称"),
+                        .child(strings().name),
                 )
                 .child(
                     div()
@@ -811,7 +875,7 @@ impl UnlockProgressWindow {
                         .text_xs()
                         .font_weight(FontWeight::MEDIUM)
                         .text_color(muted_fg)
-                        .child("路径"),
+                        .child(strings().path),
                 ),
         );
 
@@ -922,14 +986,22 @@ impl Render for UnlockProgressWindow {
         // ── Header ──
         match &self.phase {
             UnlockPhase::Confirm => {
-                content = content.child(
-                    self.render_header(fg, muted_fg, "文件解锁", "帮助你解锁被其他进程占用的文件或文件夹", None),
-                );
+                content = content.child(self.render_header(
+                    fg,
+                    muted_fg,
+                    strings().unlock_title,
+                    strings().unlock_subtitle,
+                    None,
+                ));
             }
             UnlockPhase::Working => {
-                content = content.child(
-                    self.render_header(fg, muted_fg, "正在解锁...", "正在终止占用进程", Some(muted_fg)),
-                );
+                content = content.child(self.render_header(
+                    fg,
+                    muted_fg,
+                    strings().unlocking,
+                    strings().unlocking_subtitle,
+                    Some(muted_fg),
+                ));
             }
             UnlockPhase::Success { killed } => {
                 content = content.child(
@@ -945,13 +1017,18 @@ impl Render for UnlockProgressWindow {
                                                 .text_color(success_color),
                                         )
                                         .child(
-                                            div().text_base().font_weight(FontWeight::BOLD)
-                                                .text_color(fg).child("解锁成功"),
+                                            div()
+                                                .text_base()
+                                                .font_weight(FontWeight::BOLD)
+                                                .text_color(fg)
+                                                .child(strings().unlock_success),
                                         ),
                                 )
                                 .child(
-                                    div().text_xs().text_color(muted_fg)
-                                        .child(format!("已终止 {} 个占用进程", killed)),
+                                    div()
+                                        .text_xs()
+                                        .text_color(muted_fg)
+                                        .child((strings().killed_processes)(*killed)),
                                 ),
                         ),
                 );
@@ -970,16 +1047,19 @@ impl Render for UnlockProgressWindow {
                                                 .text_color(danger_color),
                                         )
                                         .child(
-                                            div().text_base().font_weight(FontWeight::BOLD)
-                                                .text_color(fg).child("部分解锁失败"),
+                                            div()
+                                                .text_base()
+                                                .font_weight(FontWeight::BOLD)
+                                                .text_color(fg)
+                                                .child(strings().unlock_partial),
                                         ),
                                 )
                                 .child(
-                                    div().text_xs().text_color(muted_fg)
-                                        .child(format!(
-                                            "成功 {} 个，失败 {} 个",
-                                            killed, failures.len()
-                                        )),
+                                    div().text_xs().text_color(muted_fg).child((strings()
+                                        .unlock_counts)(
+                                        *killed,
+                                        failures.len(),
+                                    )),
                                 ),
                         ),
                 );
@@ -989,8 +1069,12 @@ impl Render for UnlockProgressWindow {
         // ── 文件列表 (Confirm / Working) ──
         if matches!(self.phase, UnlockPhase::Confirm | UnlockPhase::Working) {
             content = content.child(
-                div().px_4().py_1().text_xs().text_color(muted_fg)
-                    .child(format!("将对以下 {} 个文件/文件夹进行解锁", file_count)),
+                div()
+                    .px_4()
+                    .py_1()
+                    .text_xs()
+                    .text_color(muted_fg)
+                    .child((strings().unlock_targets)(file_count)),
             );
 
             let mut file_list = div()
@@ -999,13 +1083,39 @@ impl Render for UnlockProgressWindow {
                 .max_h(px(120.0)).overflow_hidden();
 
             file_list = file_list.child(
-                div().flex().flex_row().items_center().px_3().py_1p5()
-                    .bg(theme.secondary.opacity(0.3)).border_b_1().border_color(border)
-                    .child(div().flex_1().text_xs().font_weight(FontWeight::MEDIUM).text_color(muted_fg).child("文件/文件夹名称"))
-                    .child(div().w(px(60.0)).text_xs().font_weight(FontWeight::MEDIUM).text_color(muted_fg).text_right().child("状态")),
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .px_3()
+                    .py_1p5()
+                    .bg(theme.secondary.opacity(0.3))
+                    .border_b_1()
+                    .border_color(border)
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_xs()
+                            .font_weight(FontWeight::MEDIUM)
+                            .text_color(muted_fg)
+                            .child(strings().item_name),
+                    )
+                    .child(
+                        div()
+                            .w(px(60.0))
+                            .text_xs()
+                            .font_weight(FontWeight::MEDIUM)
+                            .text_color(muted_fg)
+                            .text_right()
+                            .child(strings().status),
+                    ),
             );
 
-            let status_text = if matches!(self.phase, UnlockPhase::Working) { "解锁中" } else { "待解锁" };
+            let status_text = if matches!(self.phase, UnlockPhase::Working) {
+                strings().status_unlocking
+            } else {
+                strings().status_pending
+            };
             for file in files {
                 file_list = file_list.child(
                     div().flex().flex_row().items_center().px_3().py_1p5()
@@ -1022,9 +1132,19 @@ impl Render for UnlockProgressWindow {
         if matches!(self.phase, UnlockPhase::Confirm | UnlockPhase::Working) && !processes.is_empty() {
             let first_file_name = files.first().map(|f| f.file_name.clone()).unwrap_or_default();
             content = content.child(
-                div().flex().flex_col().gap_1().px_4().pt_3()
-                    .child(div().text_xs().font_weight(FontWeight::MEDIUM).text_color(fg)
-                        .child(format!("{} 被以下程序锁定", first_file_name)))
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .px_4()
+                    .pt_3()
+                    .child(
+                        div()
+                            .text_xs()
+                            .font_weight(FontWeight::MEDIUM)
+                            .text_color(fg)
+                            .child((strings().locked_by)(&first_file_name)),
+                    )
                     .child(self.render_process_table(&processes, theme)),
             );
         }
@@ -1037,10 +1157,31 @@ impl Render for UnlockProgressWindow {
                 .max_h(px(150.0)).overflow_hidden();
 
             fail_list = fail_list.child(
-                div().flex().flex_row().items_center().px_3().py_1p5()
-                    .bg(danger_color.opacity(0.08)).border_b_1().border_color(danger_color.opacity(0.2))
-                    .child(div().w(px(120.0)).text_xs().font_weight(FontWeight::MEDIUM).text_color(danger_color).child("进程"))
-                    .child(div().flex_1().text_xs().font_weight(FontWeight::MEDIUM).text_color(danger_color).child("失败原因")),
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .px_3()
+                    .py_1p5()
+                    .bg(danger_color.opacity(0.08))
+                    .border_b_1()
+                    .border_color(danger_color.opacity(0.2))
+                    .child(
+                        div()
+                            .w(px(120.0))
+                            .text_xs()
+                            .font_weight(FontWeight::MEDIUM)
+                            .text_color(danger_color)
+                            .child(strings().process),
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_xs()
+                            .font_weight(FontWeight::MEDIUM)
+                            .text_color(danger_color)
+                            .child(strings().failure_reason),
+                    ),
             );
 
             for f in failures {
@@ -1062,30 +1203,49 @@ impl Render for UnlockProgressWindow {
                 .when(matches!(self.phase, UnlockPhase::Confirm), |this| {
                     let signal = self.confirm_signal.clone();
                     this.child(
-                        Button::new("unlock-btn").primary().label("全部解锁")
+                        Button::new("unlock-btn")
+                            .primary()
+                            .label(strings().unlock_all)
                             .on_click(move |_, _, cx| {
                                 signal.store(true, Ordering::Release);
                                 cx.refresh_windows();
                             }),
                     )
                     .child(
-                        Button::new("cancel-btn").ghost().label("取消")
-                            .on_click(|_, _, cx| { cx.quit(); }),
+                        Button::new("cancel-btn")
+                            .ghost()
+                            .label(strings().cancel)
+                            .on_click(|_, _, cx| {
+                                cx.quit();
+                            }),
                     )
                 })
                 .when(matches!(self.phase, UnlockPhase::Working), |this| {
-                    this.child(div().text_xs().text_color(muted_fg).child("正在处理，请稍候..."))
+                    this.child(
+                        div()
+                            .text_xs()
+                            .text_color(muted_fg)
+                            .child(strings().please_wait),
+                    )
                 })
                 .when(matches!(self.phase, UnlockPhase::Success { .. }), |this| {
                     this.child(
-                        Button::new("close-btn-ok").primary().label("好的")
-                            .on_click(|_, _, cx| { cx.quit(); }),
+                        Button::new("close-btn-ok")
+                            .primary()
+                            .label(strings().ok)
+                            .on_click(|_, _, cx| {
+                                cx.quit();
+                            }),
                     )
                 })
                 .when(matches!(self.phase, UnlockPhase::Failed { .. }), |this| {
                     this.child(
-                        Button::new("close-btn").primary().label("关闭")
-                            .on_click(|_, _, cx| { cx.quit(); }),
+                        Button::new("close-btn")
+                            .primary()
+                            .label(strings().close)
+                            .on_click(|_, _, cx| {
+                                cx.quit();
+                            }),
                     )
                 }),
         );
@@ -1184,12 +1344,7 @@ impl Render for NoLockWindow {
                                     .child("!"),
                             ),
                     )
-                    .child(
-                        div()
-                            .text_sm()
-                            .text_color(fg)
-                            .child("未检测到文件被占用，无需解锁"),
-                    ),
+                    .child(div().text_sm().text_color(fg).child(strings().no_locks)),
             )
             .child(
                 div()
@@ -1205,7 +1360,7 @@ impl Render for NoLockWindow {
                     .child(
                         Button::new("ok-btn")
                             .primary()
-                            .label("好的")
+                            .label(strings().ok)
                             .on_click(|_, _, cx| {
                                 cx.quit();
                             }),
@@ -1232,7 +1387,7 @@ pub fn run_unlock_dialog(
             cx.spawn(async move |cx| {
                 let window_options = WindowOptions {
                     titlebar: Some(TitlebarOptions {
-                        title: Some("文件解锁".into()),
+                        title: Some(strings().unlock_title.into()),
                         ..Default::default()
                     }),
                     window_bounds: Some(WindowBounds::Windowed(window_bounds)),
@@ -1266,7 +1421,7 @@ pub fn run_unlock_dialog(
             cx.spawn(async move |cx| {
                 let window_options = WindowOptions {
                     titlebar: Some(TitlebarOptions {
-                        title: Some("文件解锁".into()),
+                        title: Some(strings().unlock_title.into()),
                         ..Default::default()
                     }),
                     window_bounds: Some(WindowBounds::Windowed(window_bounds)),
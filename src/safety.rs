@@ -1,7 +1,17 @@
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::winapi;
+
+/// Extra protected paths, separated like `PATH`
+pub const PROTECT_ENV: &str = "RMX_PROTECT";
+
 pub fn is_system_directory(path: &Path) -> bool {
+    if is_user_protected(path) {
+        return true;
+    }
+
     let canonical = path.canonicalize().ok();
     let path_str = path.to_string_lossy();
     let canonical_str = canonical.as_ref().map(|p| p.to_string_lossy());
@@ -18,18 +28,26 @@ pub fn is_system_directory(path: &Path) -> bool {
             "C:\\Users",
         ];
 
+        // Compare in the form the Win32 API sees, so `\\?\C:\Windows` and
+        // `C:/Windows` match too
+        let normalize = |s: &str| {
+            let normalized = winapi::normalize_path_str(s);
+            normalized.trim_end_matches('\\').to_string()
+        };
+        let forms: Vec<String> = [Some(&*path_str), canonical_str.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(normalize)
+            .collect();
+
         for protected in &protected_windows {
-            if path_str.eq_ignore_ascii_case(protected) {
+            let protected = normalize(protected);
+            if forms.iter().any(|f| f.eq_ignore_ascii_case(&protected)) {
                 return true;
             }
-            if let Some(ref canonical) = canonical_str {
-                if canonical.eq_ignore_ascii_case(protected) {
-                    return true;
-                }
-            }
         }
 
-        if path_str.len() <= 3 && path_str.ends_with(":\\") {
+        if is_volume_root(&path_str) || canonical_str.as_deref().is_some_and(is_volume_root) {
             return true;
         }
     }
@@ -77,6 +95,164 @@ pub fn is_system_directory(path: &Path) -> bool {
     false
 }
 
+/// `%APPDATA%\rmx\protected.txt` (`~/.config/rmx/protected.txt` elsewhere):
+/// extra protected paths, one per line, `#` starts a comment.
+pub fn protected_list_file() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let base = env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    base.map(|dir| dir.join("rmx").join("protected.txt"))
+}
+
+/// A user-protected path; `subtree` (a trailing `\*`) also covers
+/// everything below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProtectedPath {
+    path: String,
+    subtree: bool,
+}
+
+impl ProtectedPath {
+    fn parse(entry: &str) -> Option<Self> {
+        let entry = entry.trim();
+        if entry.is_empty() || entry.starts_with('#') {
+            return None;
+        }
+        let (entry, subtree) = match entry
+            .strip_suffix('*')
+            .filter(|dir| dir.ends_with(['\\', '/']))
+        {
+            Some(dir) => (dir, true),
+            None => (entry, false),
+        };
+        Some(Self {
+            path: comparable(entry),
+            subtree,
+        })
+    }
+
+    fn matches(&self, form: &str) -> bool {
+        if form == self.path {
+            return true;
+        }
+        // `C:\*` keeps no separator after trimming, `/*` keeps its only one
+        self.subtree
+            && form.strip_prefix(&self.path).is_some_and(|rest| {
+                rest.starts_with(std::path::MAIN_SEPARATOR)
+                    || self.path.ends_with(std::path::MAIN_SEPARATOR)
+            })
+    }
+}
+
+/// `path` as protected entries are compared: case-folded, in the form the
+/// Win32 API sees on Windows, without a trailing separator.
+fn comparable(path: &str) -> String {
+    #[cfg(windows)]
+    {
+        let normalized = winapi::normalize_path_str(path);
+        normalized.trim_end_matches('\\').to_lowercase()
+    }
+    #[cfg(not(windows))]
+    {
+        let trimmed = path.trim_end_matches('/');
+        if trimmed.is_empty() && path.starts_with('/') {
+            "/".to_string()
+        } else {
+            trimmed.to_string()
+        }
+    }
+}
+
+/// Entries from [`PROTECT_ENV`] and [`protected_list_file`]. Read on every
+/// check, so edits take effect immediately.
+fn user_protected_paths() -> Vec<ProtectedPath> {
+    let mut entries: Vec<ProtectedPath> = env::var_os(PROTECT_ENV)
+        .map(|value| {
+            env::split_paths(&value)
+                .filter_map(|p| ProtectedPath::parse(&p.to_string_lossy()))
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Some(text) = protected_list_file().and_then(|file| fs::read_to_string(file).ok()) {
+        entries.extend(text.lines().filter_map(ProtectedPath::parse));
+    }
+    entries
+}
+
+fn matches_protected(path: &Path, entries: &[ProtectedPath]) -> bool {
+    if entries.is_empty() {
+        return false;
+    }
+    let mut forms = vec![comparable(&path.to_string_lossy())];
+    if let Ok(canonical) = path.canonicalize() {
+        forms.push(comparable(&canonical.to_string_lossy()));
+    }
+    entries.iter().any(|entry| {
+        // Also match through links in the listed path itself
+        let resolved = Path::new(&entry.path)
+            .canonicalize()
+            .ok()
+            .map(|p| ProtectedPath {
+                path: comparable(&p.to_string_lossy()),
+                subtree: entry.subtree,
+            });
+        forms
+            .iter()
+            .any(|form| entry.matches(form) || resolved.as_ref().is_some_and(|r| r.matches(form)))
+    })
+}
+
+/// Returns `true` if `path` is listed in [`PROTECT_ENV`] or
+/// [`protected_list_file`], or lies below an entry ending in `\*`.
+pub fn is_user_protected(path: &Path) -> bool {
+    matches_protected(path, &user_protected_paths())
+}
+
+/// Returns `true` for a drive or share root in any spelling: `C:\`, `C:/`,
+/// `\\?\C:\`, `\\server\share`, `\\?\UNC\server\share` or
+/// `\\?\Volume{...}\`.
+pub fn is_volume_root(path_str: &str) -> bool {
+    let normalized = winapi::normalize_path_str(path_str);
+    let Some(rest) = normalized
+        .strip_prefix(r"\\?\")
+        .or_else(|| normalized.strip_prefix(r"\\.\"))
+    else {
+        return false;
+    };
+    let (rest, root_parts) = match rest.strip_prefix(r"UNC\") {
+        Some(unc) => (unc, 2),
+        None => (rest, 1),
+    };
+    rest.split('\\').filter(|part| !part.is_empty()).count() <= root_parts
+}
+
+/// Resolve a relative operand to an absolute path; others are returned as is.
+///
+/// Relative paths don't get the `\\?\` prefix in `path_to_wide()`, hitting the
+/// 260-char MAX_PATH limit on deeply nested trees (e.g. pnpm node_modules).
+pub fn absolute_operand(path: &Path) -> PathBuf {
+    if !path.is_relative() {
+        return path.to_path_buf();
+    }
+    let Ok(abs) = fs::canonicalize(path) else {
+        return path.to_path_buf();
+    };
+    let s = abs.to_string_lossy();
+    // canonicalize returns \\?\C:\... on Windows; strip it so path_to_wide() can re-add it
+    // and the checks here can match against plain paths like "C:\Windows".
+    // \\?\UNC\server\share maps back to \\server\share.
+    if let Some(unc) = s.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(stripped) = s.strip_prefix(r"\\?\") {
+        PathBuf::from(stripped)
+    } else {
+        abs
+    }
+}
+
 pub fn is_in_current_directory(path: &Path) -> bool {
     if let Ok(cwd) = env::current_dir() {
         if let (Ok(p1), Ok(p2)) = (path.canonicalize(), cwd.canonicalize()) {
@@ -86,7 +262,64 @@ pub fn is_in_current_directory(path: &Path) -> bool {
     false
 }
 
+/// Returns `true` if `path` is on a different volume than its parent
+/// directory, i.e. it is a mount point (`--preserve-root=all`).
+pub fn is_on_other_volume_than_parent(path: &Path) -> bool {
+    let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) else {
+        return false;
+    };
+    match (winapi::volume_serial(path), winapi::volume_serial(parent)) {
+        (Ok(volume), Ok(parent_volume)) => volume != parent_volume,
+        _ => false,
+    }
+}
+
+/// Returns `true` if `path` is or contains the running rmx executable.
+pub fn is_self_location(path: &Path) -> bool {
+    let Ok(exe) = env::current_exe().and_then(|exe| exe.canonicalize()) else {
+        return false;
+    };
+    path.canonicalize()
+        .map(|target| exe.starts_with(&target))
+        .unwrap_or(false)
+}
+
+/// Returns `true` for `$RECYCLE.BIN` / `System Volume Information` directly
+/// under a drive root. These are owned by the OS and are always skipped.
+pub fn is_protected_system_folder(path: &Path) -> bool {
+    const SYSTEM_FOLDERS: [&str; 2] = ["$RECYCLE.BIN", "System Volume Information"];
+
+    let at_root = path
+        .parent()
+        .is_some_and(|parent| parent.parent().is_none());
+    if !at_root {
+        return false;
+    }
+
+    path.file_name()
+        .map(|name| {
+            let name = name.to_string_lossy();
+            SYSTEM_FOLDERS
+                .iter()
+                .any(|folder| name.eq_ignore_ascii_case(folder))
+        })
+        .unwrap_or(false)
+}
+
+/// Returns `true` if `path` directly contains a `.git` directory.
+pub fn is_git_repository_root(path: &Path) -> bool {
+    path.join(".git").is_dir()
+}
+
 fn get_danger_reason(path: &Path) -> Option<String> {
+    if is_user_protected(path) {
+        return Some(format!(
+            "'{}' is protected by {} or protected.txt",
+            path.display(),
+            PROTECT_ENV
+        ));
+    }
+
     if is_system_directory(path) {
         return Some(format!(
             "'{}' is a system directory - deleting it could break your system",
@@ -101,6 +334,17 @@ fn get_danger_reason(path: &Path) -> Option<String> {
         ));
     }
 
+    if is_self_location(path) {
+        return Some(format!(
+            "'{}' contains the running rmx executable",
+            path.display()
+        ));
+    }
+
+    if is_git_repository_root(path) {
+        return Some(format!("'{}' is a Git repository root", path.display()));
+    }
+
     None
 }
 
@@ -120,3 +364,124 @@ pub fn check_path_safety(path: &Path) -> SafetyCheck {
         SafetyCheck::Safe
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_folder_only_at_drive_root() {
+        #[cfg(windows)]
+        let root = Path::new("D:\\");
+        #[cfg(not(windows))]
+        let root = Path::new("/");
+
+        assert!(is_protected_system_folder(&root.join("$RECYCLE.BIN")));
+        assert!(is_protected_system_folder(
+            &root.join("system volume information")
+        ));
+        assert!(!is_protected_system_folder(
+            &root.join("data").join("$RECYCLE.BIN")
+        ));
+        assert!(!is_protected_system_folder(&root.join("node_modules")));
+    }
+
+    #[test]
+    fn test_self_location() {
+        let exe = env::current_exe().unwrap();
+        let exe_dir = exe.parent().unwrap();
+        assert!(is_self_location(exe_dir));
+        assert!(is_self_location(&exe));
+        assert!(!is_self_location(&exe_dir.join("missing")));
+        assert!(matches!(
+            check_path_safety(exe_dir),
+            SafetyCheck::Dangerous {
+                can_override: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_git_repository_root() {
+        let temp = env::temp_dir().join("rmx_git_root_test");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("src")).unwrap();
+        assert!(matches!(check_path_safety(&temp), SafetyCheck::Safe));
+
+        fs::create_dir(temp.join(".git")).unwrap();
+        match check_path_safety(&temp) {
+            SafetyCheck::Dangerous {
+                reason,
+                can_override,
+            } => {
+                assert!(reason.ends_with("is a Git repository root"));
+                assert!(can_override);
+            }
+            SafetyCheck::Safe => panic!("repository root reported safe"),
+        }
+        assert!(matches!(
+            check_path_safety(&temp.join("src")),
+            SafetyCheck::Safe
+        ));
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_volume_root_spellings() {
+        assert!(is_volume_root(r"C:\"));
+        assert!(is_volume_root("C:/"));
+        assert!(is_volume_root(r"\\?\C:\"));
+        assert!(is_volume_root(r"\\?\C:"));
+        assert!(is_volume_root(r"\\server\share"));
+        assert!(is_volume_root("//server/share/"));
+        assert!(is_volume_root(r"\\?\UNC\server\share\"));
+        assert!(is_volume_root(
+            r"\\?\Volume{01234567-89ab-cdef-0123-456789abcdef}\"
+        ));
+        assert!(!is_volume_root(r"C:\Users"));
+        assert!(!is_volume_root(r"\\?\C:\Users"));
+        assert!(!is_volume_root(r"\\server\share\dir"));
+        assert!(!is_volume_root(r"\\?\UNC\server\share\dir"));
+        assert!(!is_volume_root("target"));
+    }
+
+    #[test]
+    fn test_same_volume_as_parent() {
+        let exe = env::current_exe().unwrap();
+        assert!(!is_on_other_volume_than_parent(exe.parent().unwrap()));
+        assert!(!is_on_other_volume_than_parent(Path::new("relative")));
+    }
+
+    #[test]
+    fn test_user_protected_entries() {
+        let temp = env::temp_dir().join("rmx_user_protected_test");
+        fs::create_dir_all(temp.join("shared").join("sub")).unwrap();
+        let shared = temp.join("shared");
+        let list = format!(
+            "# team shares\n\n{}\n{}{}*\n",
+            shared.display(),
+            temp.join("repo").display(),
+            std::path::MAIN_SEPARATOR
+        );
+        let entries: Vec<ProtectedPath> = list.lines().filter_map(ProtectedPath::parse).collect();
+        assert_eq!(entries.len(), 2);
+        assert!(!entries[0].subtree && entries[1].subtree);
+
+        assert!(matches_protected(&shared, &entries));
+        assert!(!matches_protected(&shared.join("sub"), &entries));
+        assert!(matches_protected(&temp.join("repo"), &entries));
+        assert!(matches_protected(&temp.join("repo").join("src"), &entries));
+        assert!(!matches_protected(&temp.join("repository"), &entries));
+        assert!(!matches_protected(&temp, &entries));
+
+        #[cfg(windows)]
+        assert!(matches_protected(
+            Path::new(&shared.to_string_lossy().to_uppercase()),
+            &entries
+        ));
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::tree::{self, DirectoryTree};
+use crate::winapi;
+
+/// Bumped whenever the cache layout changes so old entries are ignored.
+const CACHE_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize)]
+struct CachedScan<T> {
+    version: u32,
+    root: PathBuf,
+    /// Last write time of every scanned directory at the time of caching.
+    /// A directory's mtime changes whenever an entry is added, removed or
+    /// renamed inside it, so any mismatch invalidates the whole entry.
+    dir_mtimes: HashMap<PathBuf, Option<SystemTime>>,
+    tree: T,
+}
+
+/// `%LOCALAPPDATA%\rmx\scan-cache` (falls back to the temp directory).
+pub fn cache_dir() -> PathBuf {
+    env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("rmx")
+        .join("scan-cache")
+}
+
+/// Scan `root`, reusing a cached result when nothing has changed since it
+/// was written. Fresh scans are written back to the cache.
+///
+/// Only for read-only queries (dry-run, size): deletion must always rescan
+/// so it never acts on stale data.
+pub fn discover_tree_cached(root: &Path) -> io::Result<DirectoryTree> {
+    let file = cache_file(&cache_dir(), root);
+
+    if let Some(tree) = load_at(&file, root) {
+        return Ok(tree);
+    }
+
+    let tree = tree::discover_tree(root)?;
+    // Cache failures only cost speed next time; never fail the query
+    let _ = store_at(&file, root, &tree);
+    Ok(tree)
+}
+
+fn cache_file(dir: &Path, root: &Path) -> PathBuf {
+    let key = root.to_string_lossy().to_lowercase();
+    dir.join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+}
+
+/// Stable across builds, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn load_at(file: &Path, root: &Path) -> Option<DirectoryTree> {
+    let data = fs::read(file).ok()?;
+    let cached: CachedScan<DirectoryTree> = serde_json::from_slice(&data).ok()?;
+
+    if cached.version != CACHE_VERSION || cached.root != root {
+        return None;
+    }
+
+    let unchanged = cached.dir_mtimes.par_iter().all(|(dir, mtime)| {
+        let stat = winapi::stat_path(dir);
+        stat.exists && stat.modified == *mtime
+    });
+
+    if unchanged {
+        Some(cached.tree)
+    } else {
+        let _ = fs::remove_file(file);
+        None
+    }
+}
+
+fn store_at(file: &Path, root: &Path, tree: &DirectoryTree) -> io::Result<()> {
+    let dir_mtimes = tree
+        .dirs
+        .par_iter()
+        .map(|dir| (dir.clone(), winapi::stat_path(dir).modified))
+        .collect();
+
+    let cached = CachedScan {
+        version: CACHE_VERSION,
+        root: root.to_path_buf(),
+        dir_mtimes,
+        tree,
+    };
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let data = serde_json::to_vec(&cached).map_err(io::Error::other)?;
+    fs::write(file, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_cache_invalidated_on_change() {
+        let temp = env::temp_dir().join("rmx_scan_cache_test");
+        let _ = fs::remove_dir_all(&temp);
+        let root = temp.join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/a.txt"), "aaaa").unwrap();
+        let file = cache_file(&temp.join("cache"), &root);
+
+        let tree = tree::discover_tree(&root).unwrap();
+        store_at(&file, &root, &tree).unwrap();
+
+        let cached = load_at(&file, &root).expect("unchanged tree should hit the cache");
+        assert_eq!(cached.file_count, 1);
+        assert_eq!(cached.total_bytes, 4);
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(root.join("sub/b.txt"), "b").unwrap();
+        assert!(load_at(&file, &root).is_none());
+        assert!(!file.exists(), "stale cache entry should be dropped");
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+}
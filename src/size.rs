@@ -0,0 +1,207 @@
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::scan_cache;
+use crate::tree::DirectoryTree;
+use crate::winapi;
+
+/// Size of one immediate child directory, for `rmx size --by-dir`
+#[derive(Debug, Clone)]
+pub struct ChildSize {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub on_disk: Option<u64>,
+}
+
+/// Result of measuring a path without deleting anything
+#[derive(Debug, Clone, Default)]
+pub struct SizeReport {
+    pub total_bytes: u64,
+    /// Only filled in when the on-disk size was requested
+    pub on_disk_bytes: Option<u64>,
+    pub file_count: usize,
+    pub dir_count: usize,
+    /// Immediate child directories, largest first (only with `by_dir`)
+    pub children: Vec<ChildSize>,
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+    const TB: u64 = GB * 1024;
+
+    if bytes >= TB {
+        format!("{:.2} TB", bytes as f64 / TB as f64)
+    } else if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// `n` with thousands separators: `5,231`
+pub fn format_count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Allocated size of every file in the tree. Unreadable files count as 0.
+fn tree_on_disk_bytes(tree: &DirectoryTree) -> u64 {
+    tree.dir_files
+        .par_iter()
+        .map(|(_, files)| {
+            files
+                .iter()
+                .map(|f| winapi::allocated_size(f).unwrap_or(0))
+                .sum::<u64>()
+        })
+        .sum()
+}
+
+/// Measure `root` with the parallel scanner, reusing the scan cache for
+/// trees unchanged since the last query. Never modifies the filesystem.
+pub fn measure(root: &Path, on_disk: bool, by_dir: bool) -> io::Result<SizeReport> {
+    let stat = winapi::stat_path(root);
+    if !stat.exists {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No such file or directory",
+        ));
+    }
+
+    if !stat.is_dir || stat.is_symlink {
+        return Ok(SizeReport {
+            total_bytes: stat.size,
+            on_disk_bytes: on_disk.then(|| winapi::allocated_size(root).unwrap_or(0)),
+            file_count: 1,
+            ..Default::default()
+        });
+    }
+
+    if !by_dir {
+        let tree = scan_cache::discover_tree_cached(root)?;
+        return Ok(SizeReport {
+            total_bytes: tree.total_bytes,
+            on_disk_bytes: on_disk.then(|| tree_on_disk_bytes(&tree)),
+            file_count: tree.file_count,
+            dir_count: tree.dirs.len(),
+            children: Vec::new(),
+        });
+    }
+
+    // Scan each immediate child separately so every subtree gets its own
+    // total; files directly under root only contribute to the grand total.
+    let mut child_dirs = Vec::new();
+    let mut root_files = Vec::new();
+    let mut report = SizeReport {
+        dir_count: 1,
+        ..Default::default()
+    };
+    winapi::enumerate_files(root, |entry| {
+        if entry.is_dir && !entry.is_symlink {
+            child_dirs.push(entry.path);
+        } else {
+            if !entry.is_symlink {
+                report.total_bytes += entry.size;
+            }
+            report.file_count += 1;
+            root_files.push(entry.path);
+        }
+        Ok(())
+    })?;
+
+    let scanned: Vec<(ChildSize, usize, usize)> = child_dirs
+        .into_par_iter()
+        .map(|dir| {
+            let tree = scan_cache::discover_tree_cached(&dir)?;
+            let child = ChildSize {
+                bytes: tree.total_bytes,
+                on_disk: on_disk.then(|| tree_on_disk_bytes(&tree)),
+                path: dir,
+            };
+            Ok((child, tree.file_count, tree.dirs.len()))
+        })
+        .collect::<io::Result<_>>()?;
+
+    let mut root_on_disk = on_disk.then(|| {
+        root_files
+            .par_iter()
+            .map(|f| winapi::allocated_size(f).unwrap_or(0))
+            .sum::<u64>()
+    });
+
+    for (child, files, dirs) in scanned {
+        report.total_bytes += child.bytes;
+        report.file_count += files;
+        report.dir_count += dirs;
+        if let (Some(total), Some(child_on_disk)) = (root_on_disk.as_mut(), child.on_disk) {
+            *total += child_on_disk;
+        }
+        report.children.push(child);
+    }
+    report.on_disk_bytes = root_on_disk;
+
+    report
+        .children
+        .sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+
+    Ok(report)
+}
+
+/// 执行 `rmx size`
+pub fn run_size(root: &Path, on_disk: bool, by_dir: bool) -> io::Result<()> {
+    let report = measure(root, on_disk, by_dir).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("cannot measure '{}': {}", root.display(), e),
+        )
+    })?;
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    for child in &report.children {
+        match child.on_disk {
+            Some(on_disk) => writeln!(
+                out,
+                "{:>12} {:>12}  {}",
+                format_bytes(child.bytes),
+                format_bytes(on_disk),
+                child.path.display()
+            )?,
+            None => writeln!(
+                out,
+                "{:>12}  {}",
+                format_bytes(child.bytes),
+                child.path.display()
+            )?,
+        }
+    }
+
+    writeln!(
+        out,
+        "Total:   {} ({} files, {} directories)",
+        format_bytes(report.total_bytes),
+        report.file_count,
+        report.dir_count
+    )?;
+    if let Some(on_disk) = report.on_disk_bytes {
+        writeln!(out, "On disk: {}", format_bytes(on_disk))?;
+    }
+
+    out.flush()
+}
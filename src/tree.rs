@@ -1,12 +1,21 @@
 use dashmap::{DashMap, DashSet};
+use parking_lot::Mutex;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fmt;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use std::sync::OnceLock;
 
+use crate::ignore::{IgnoreRules, IGNORE_FILE};
+use crate::winapi::{FileEntry, ReparseKind};
+
 pub fn cpu_count() -> usize {
     static CPU_COUNT: OnceLock<usize> = OnceLock::new();
     *CPU_COUNT.get_or_init(|| {
@@ -27,7 +36,7 @@ fn scan_parallel_threshold() -> usize {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DirectoryTree {
     pub dirs: Vec<PathBuf>,
     pub children: HashMap<PathBuf, Vec<PathBuf>>,
@@ -36,6 +45,71 @@ pub struct DirectoryTree {
     pub total_bytes: u64,
     /// Files in each directory - collected during scan to avoid re-enumeration during deletion
     pub dir_files: HashMap<PathBuf, Vec<PathBuf>>,
+    /// Bytes of the files in each directory of `dir_files`, so the broker
+    /// can size file batches by work rather than by count alone
+    #[serde(default)]
+    pub dir_bytes: HashMap<PathBuf, u64>,
+    /// Directories that must survive because they (transitively) contain kept entries
+    pub kept_dirs: HashSet<PathBuf>,
+    /// Entries deliberately left in place, with the reason why
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+    /// Cloud placeholder files among `file_count`; their sizes are not in
+    /// `total_bytes` since the data was never local
+    #[serde(default)]
+    pub placeholders: Vec<PathBuf>,
+    /// The scan stopped at the item limit of [`discover_tree_with_limit`];
+    /// only the counts are meaningful and the tree must not be deleted from
+    #[serde(default)]
+    pub truncated: bool,
+    /// Alternate data streams of the files, as `file:name` paths; only
+    /// collected with [`ScanFilter::streams`]
+    #[serde(default)]
+    pub streams: Vec<PathBuf>,
+    /// Bytes in the alternate data streams of the files, already included in
+    /// `total_bytes`; only measured with [`ScanFilter::streams`] or
+    /// [`ScanFilter::stream_bytes`]
+    #[serde(default)]
+    pub stream_bytes: u64,
+    /// Bytes allocated on disk for the files (cluster slack, compression,
+    /// sparse ranges); only measured with [`ScanFilter::on_disk_size`]
+    #[serde(default)]
+    pub on_disk_bytes: u64,
+    /// Links that will be removed as links, leaving their targets alone, by
+    /// kind. Junctions entered with [`ScanFilter::follow_junctions`] are not
+    /// counted.
+    #[serde(default)]
+    pub reparse_points: ReparseCounts,
+}
+
+/// Number of links of each [`ReparseKind`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReparseCounts {
+    pub symlinks: usize,
+    pub junctions: usize,
+    pub mount_points: usize,
+    pub other: usize,
+}
+
+impl ReparseCounts {
+    pub fn add(&mut self, kind: ReparseKind) {
+        match kind {
+            ReparseKind::Symlink => self.symlinks += 1,
+            ReparseKind::Junction => self.junctions += 1,
+            ReparseKind::MountPoint => self.mount_points += 1,
+            ReparseKind::Other => self.other += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &ReparseCounts) {
+        self.symlinks += other.symlinks;
+        self.junctions += other.junctions;
+        self.mount_points += other.mount_points;
+        self.other += other.other;
+    }
+
+    pub fn total(&self) -> usize {
+        self.symlinks + self.junctions + self.mount_points + self.other
+    }
 }
 
 impl DirectoryTree {
@@ -47,8 +121,78 @@ impl DirectoryTree {
             file_count: 0,
             total_bytes: 0,
             dir_files: HashMap::new(),
+            dir_bytes: HashMap::new(),
+            kept_dirs: HashSet::new(),
+            skipped: Vec::new(),
+            placeholders: Vec::new(),
+            truncated: false,
+            streams: Vec::new(),
+            stream_bytes: 0,
+            on_disk_bytes: 0,
+            reparse_points: ReparseCounts::default(),
         }
     }
+
+    /// Number of directories that will actually be removed.
+    pub fn removable_dir_count(&self) -> usize {
+        self.dirs.len() - self.kept_dirs.len()
+    }
+
+    /// Every directory, children before their parent (the order they can be
+    /// removed in); `.rev()` gives parents first. Siblings are sorted by
+    /// name, so the order is the same for the same tree on every run.
+    pub fn iter_dirs_topological(&self) -> impl DoubleEndedIterator<Item = &Path> {
+        let sorted_children = |dir: &Path| {
+            let mut children: Vec<&Path> = self
+                .children
+                .get(dir)
+                .map(|c| c.iter().map(PathBuf::as_path).collect())
+                .unwrap_or_default();
+            children.sort_unstable_by(|a, b| a.file_name().cmp(&b.file_name()));
+            children
+        };
+
+        let nested: HashSet<&Path> = self
+            .children
+            .values()
+            .flatten()
+            .map(PathBuf::as_path)
+            .collect();
+        let mut roots: Vec<&Path> = self
+            .dirs
+            .iter()
+            .map(PathBuf::as_path)
+            .filter(|d| !nested.contains(d))
+            .collect();
+        roots.sort_unstable();
+
+        // Iterative post-order: a directory is emitted once its children are
+        let mut order = Vec::with_capacity(self.dirs.len());
+        let mut stack: Vec<(&Path, bool)> = roots.into_iter().rev().map(|r| (r, false)).collect();
+        while let Some((dir, expanded)) = stack.pop() {
+            if expanded {
+                order.push(dir);
+                continue;
+            }
+            stack.push((dir, true));
+            stack.extend(sorted_children(dir).into_iter().rev().map(|c| (c, false)));
+        }
+        order.into_iter()
+    }
+
+    /// Every file, grouped by directory in [`Self::iter_dirs_topological`]
+    /// order and sorted by name within each directory.
+    pub fn iter_files(&self) -> impl Iterator<Item = &Path> {
+        self.iter_dirs_topological().flat_map(|dir| {
+            let mut files: Vec<&Path> = self
+                .dir_files
+                .get(dir)
+                .map(|f| f.iter().map(PathBuf::as_path).collect())
+                .unwrap_or_default();
+            files.sort_unstable_by(|a, b| a.file_name().cmp(&b.file_name()));
+            files
+        })
+    }
 }
 
 impl Default for DirectoryTree {
@@ -57,28 +201,447 @@ impl Default for DirectoryTree {
     }
 }
 
+/// Why an entry was intentionally not deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// `$RECYCLE.BIN` / `System Volume Information` at a drive root
+    SystemFolder,
+    /// Matched an exclusion pattern
+    Excluded,
+    /// Rejected by a scan filter (age, hidden, ...)
+    Filtered,
+    /// Locked by another process and left alone: a `rename_locked`
+    /// directory still locked when the run ended
+    LockedSkipped,
+    /// The user answered "no" to an interactive prompt
+    Declined,
+    /// A mount point or link to another volume (`--one-file-system`)
+    OtherFileSystem,
+    /// A directory below the `--max-depth` limit, left unscanned
+    DepthLimit,
+    /// Finished by an earlier run according to the `--journal`
+    Journaled,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SkipReason::SystemFolder => "system folder",
+            SkipReason::Excluded => "excluded",
+            SkipReason::Filtered => "filtered",
+            SkipReason::LockedSkipped => "locked",
+            SkipReason::Declined => "declined",
+            SkipReason::OtherFileSystem => "other file system",
+            SkipReason::DepthLimit => "beyond max depth",
+            SkipReason::Journaled => "already done (journal)",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Decides which entries the scanner selects for deletion. Entries that are
+/// not selected stay on disk together with every directory above them.
+#[derive(Debug, Clone, Default)]
+pub struct ScanFilter {
+    /// Only entries last modified strictly before this instant are deleted
+    pub older_than: Option<SystemTime>,
+    /// Only files strictly larger than this many bytes are deleted
+    pub larger_than: Option<u64>,
+    /// Only files strictly smaller than this many bytes are deleted
+    pub smaller_than: Option<u64>,
+    /// Leave hidden directories (and everything inside them) untouched while
+    /// still deleting hidden files
+    pub no_recurse_hidden: bool,
+    /// Names of immediate children of the scan root to leave untouched
+    pub keep_top_level: Vec<String>,
+    /// Glob patterns for entries to leave untouched. Patterns containing `/`
+    /// match the path relative to the scan root, others match the name at
+    /// any depth (`*.log`)
+    pub exclude: Vec<glob::Pattern>,
+    /// Leave directories that resolve to a different volume than the scan
+    /// root (mount points, junctions) in place
+    pub one_file_system: bool,
+    /// Recurse into directory junctions and delete what they point to
+    /// (`--follow-junctions`). Targets inside the scan root, above it, or
+    /// failing the operand safety checks are still removed as links.
+    pub follow_junctions: bool,
+    /// Honor the root's `.rmxignore` (see [`crate::ignore`]) and keep the
+    /// file itself
+    pub ignore_files: bool,
+    /// With `ignore_files`, also read `.rmxignore` from every ancestor
+    pub ignore_parents: bool,
+    /// Only enumerate directories fewer than this many levels below the root
+    /// (the root is level 0); deeper directories are kept whole
+    pub max_depth: Option<usize>,
+    /// Also list the alternate data streams of every selected file in
+    /// [`DirectoryTree::streams`]. Selects nothing by itself.
+    pub streams: bool,
+    /// Count the bytes in alternate data streams toward `total_bytes`
+    /// without listing the streams (`--include-streams`)
+    pub stream_bytes: bool,
+    /// Also measure the allocated size of every selected file into
+    /// [`DirectoryTree::on_disk_bytes`] (`--on-disk-size`); one extra open
+    /// per file
+    pub on_disk_size: bool,
+    /// Don't warn about directories that can't be enumerated
+    pub quiet: bool,
+    /// Directories an earlier run already finished (see [`crate::journal`]);
+    /// they are left in place unscanned
+    pub journaled: Option<Arc<HashSet<PathBuf>>>,
+    /// Empty the scan root but leave the directory itself (`--contents-only`)
+    pub keep_root: bool,
+    /// Select no files at all, so only directories with nothing but empty
+    /// directories below them are deleted (`--empty-only`)
+    pub empty_only: bool,
+}
+
+impl ScanFilter {
+    pub fn is_active(&self) -> bool {
+        self.older_than.is_some()
+            || self.larger_than.is_some()
+            || self.smaller_than.is_some()
+            || self.no_recurse_hidden
+            || !self.keep_top_level.is_empty()
+            || !self.exclude.is_empty()
+            || self.one_file_system
+            || self.max_depth.is_some()
+            || self.journaled.is_some()
+            || self.keep_root
+            || self.empty_only
+    }
+
+    /// Returns `true` if scanning `root` would read a `.rmxignore`.
+    pub fn has_ignore_file(&self, root: &Path) -> bool {
+        if !self.ignore_files {
+            return false;
+        }
+        let depth = if self.ignore_parents { usize::MAX } else { 1 };
+        root.ancestors()
+            .take(depth)
+            .any(|dir| dir.join(IGNORE_FILE).exists())
+    }
+
+    /// Returns `true` if `path` (below `root`) matches an exclude pattern.
+    pub fn excludes(&self, root: &Path, path: &Path) -> bool {
+        if self.exclude.is_empty() {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name = relative.rsplit('/').next().unwrap_or(&relative);
+
+        let options = glob::MatchOptions {
+            // NTFS names are case-insensitive
+            case_sensitive: !cfg!(windows),
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        self.exclude.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_with(&relative, options)
+            } else {
+                pattern.matches_with(name, options)
+            }
+        })
+    }
+
+    /// Returns `true` if an immediate child of the root named `name` is kept.
+    pub fn keeps_top_level(&self, name: &OsStr) -> bool {
+        let name = name.to_string_lossy();
+        // NTFS names are case-insensitive
+        self.keep_top_level
+            .iter()
+            .any(|keep| keep.eq_ignore_ascii_case(&name))
+    }
+
+    /// Returns `true` if a non-directory entry must be left in place.
+    pub fn keeps(&self, modified: Option<SystemTime>, size: u64) -> bool {
+        if self.empty_only {
+            return true;
+        }
+        if self.larger_than.is_some_and(|min| size <= min)
+            || self.smaller_than.is_some_and(|max| size >= max)
+        {
+            return true;
+        }
+        if let Some(cutoff) = self.older_than {
+            // Unknown timestamps are kept: never delete what we can't classify
+            match modified {
+                Some(m) if m < cutoff => {}
+                _ => return true,
+            }
+        }
+        false
+    }
+}
+
+/// Render the entries selected below `root` as an indented listing for
+/// `--dry-run --tree`: directories before files, each level sorted by name,
+/// so the output is the same on every run. After `max_entries` lines the rest
+/// is summarized as `... and N more`.
+pub fn render_tree(tree: &DirectoryTree, root: &Path, max_entries: Option<usize>) -> String {
+    use std::fmt::Write;
+
+    let kept = |dir: &Path| {
+        if tree.kept_dirs.contains(dir) {
+            " (kept)"
+        } else {
+            ""
+        }
+    };
+    // Popped from the back, so each level is pushed in reverse
+    fn push_children<'a>(
+        stack: &mut Vec<(&'a Path, bool, usize)>,
+        tree: &'a DirectoryTree,
+        dir: &Path,
+        depth: usize,
+    ) {
+        let by_name_desc = |a: &&Path, b: &&Path| b.file_name().cmp(&a.file_name());
+        let mut files: Vec<&Path> = tree
+            .dir_files
+            .get(dir)
+            .map(|files| files.iter().map(PathBuf::as_path).collect())
+            .unwrap_or_default();
+        let mut dirs: Vec<&Path> = tree
+            .children
+            .get(dir)
+            .map(|dirs| dirs.iter().map(PathBuf::as_path).collect())
+            .unwrap_or_default();
+        files.sort_unstable_by(by_name_desc);
+        dirs.sort_unstable_by(by_name_desc);
+        stack.extend(files.into_iter().map(|f| (f, false, depth)));
+        stack.extend(dirs.into_iter().map(|d| (d, true, depth)));
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}{}", root.display(), kept(root));
+
+    let total = tree.dirs.len().saturating_sub(1) + tree.file_count;
+    let mut listed = 0;
+    let mut stack = Vec::new();
+    push_children(&mut stack, tree, root, 1);
+    while let Some((path, is_dir, depth)) = stack.pop() {
+        if max_entries.is_some_and(|max| listed >= max) {
+            break;
+        }
+        listed += 1;
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        if is_dir {
+            let _ = writeln!(
+                out,
+                "{:indent$}{}/{}",
+                "",
+                name,
+                kept(path),
+                indent = depth * 2
+            );
+            push_children(&mut stack, tree, path, depth + 1);
+        } else {
+            let _ = writeln!(out, "{:indent$}{}", "", name, indent = depth * 2);
+        }
+    }
+    if listed < total {
+        let _ = writeln!(out, "... and {} more", total - listed);
+    }
+    out
+}
+
+/// Parse an age such as `90s`, `30m`, `24h`, `7d` or `2w` for `--older-than`.
+pub fn parse_age(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid age '{}': expected a number and a unit, e.g. 7d", s))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid age unit in '{}': use s, m, h, d or w", s)),
+    };
+    value
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{}' is too large", s))
+}
+
+/// Parse a size such as `512`, `64K`, `10M` or `1G` (binary units, an
+/// optional trailing `B`/`iB`) for `--larger-than` and `--smaller-than`.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let value: u64 = digits.parse().map_err(|_| {
+        format!(
+            "invalid size '{}': expected a number and a unit, e.g. 10M",
+            s
+        )
+    })?;
+    let unit = unit.to_ascii_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let shift = match unit {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("invalid size unit in '{}': use K, M, G or T", s)),
+    };
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+impl ScanState<'_> {
+    /// Whether to recurse into `entry`, a junction, rather than remove it as
+    /// a link.
+    fn follows_junction(&self, entry: &FileEntry) -> bool {
+        let Some(root) = &self.canonical_root else {
+            return false;
+        };
+        if entry.reparse_kind != Some(ReparseKind::Junction) {
+            return false;
+        }
+        let Ok(target) = std::fs::canonicalize(&entry.path) else {
+            return false;
+        };
+        if target.starts_with(root) || root.starts_with(&target) {
+            return false;
+        }
+        if !matches!(
+            crate::safety::check_path_safety(&target),
+            crate::safety::SafetyCheck::Safe
+        ) {
+            return false;
+        }
+        crate::winapi::file_link_info(&target)
+            .is_ok_and(|info| self.followed.insert((info.volume, info.file_id)))
+    }
+}
+
+/// Shared accumulators for a single parallel scan.
+struct ScanState<'a> {
+    root: &'a Path,
+    filter: &'a ScanFilter,
+    /// Volume of the root, only queried with `one_file_system`
+    root_volume: Option<u64>,
+    ignore: IgnoreRules,
+    all_dirs: DashSet<PathBuf>,
+    children_map: DashMap<PathBuf, Vec<PathBuf>>,
+    dir_files_map: DashMap<PathBuf, Vec<PathBuf>>,
+    dir_bytes_map: DashMap<PathBuf, u64>,
+    kept_dirs: DashSet<PathBuf>,
+    file_count: AtomicUsize,
+    skipped: Mutex<Vec<(PathBuf, SkipReason)>>,
+    streams: Mutex<Vec<PathBuf>>,
+    total_bytes: AtomicU64,
+    stream_bytes: AtomicU64,
+    on_disk_bytes: AtomicU64,
+    placeholders: Mutex<Vec<PathBuf>>,
+    reparse_points: Mutex<ReparseCounts>,
+    /// Canonical root, only resolved with `follow_junctions`
+    canonical_root: Option<PathBuf>,
+    /// Volume and file ID of the root and of every junction target entered,
+    /// so a junction back into an entered folder is removed as a link
+    /// instead of being scanned again (or forever, for a cycle)
+    followed: DashSet<(u64, u64)>,
+    /// Stop scanning once this many files and directories were found
+    max_items: Option<usize>,
+    items: AtomicUsize,
+    stop: AtomicBool,
+}
+
 pub fn discover_tree(root: &Path) -> io::Result<DirectoryTree> {
-    let all_dirs: DashSet<PathBuf> = DashSet::new();
-    let children_map: DashMap<PathBuf, Vec<PathBuf>> = DashMap::new();
-    let dir_files_map: DashMap<PathBuf, Vec<PathBuf>> = DashMap::new();
-    let file_count = AtomicUsize::new(0);
-    let total_bytes = AtomicU64::new(0);
+    discover_tree_filtered(root, &ScanFilter::default())
+}
 
-    scan_parallel(
+/// Like [`discover_tree`], but leaves entries rejected by `filter` (and their
+/// ancestor directories) out of the deletion plan.
+pub fn discover_tree_filtered(root: &Path, filter: &ScanFilter) -> io::Result<DirectoryTree> {
+    discover(root, filter, None)
+}
+
+/// Like [`discover_tree_filtered`], but stops once more than `max_items`
+/// files and directories were found and returns the partial tree with
+/// `truncated` set. For quick "100,000+ items" estimates only.
+pub fn discover_tree_with_limit(
+    root: &Path,
+    filter: &ScanFilter,
+    max_items: usize,
+) -> io::Result<DirectoryTree> {
+    discover(root, filter, Some(max_items))
+}
+
+fn discover(
+    root: &Path,
+    filter: &ScanFilter,
+    max_items: Option<usize>,
+) -> io::Result<DirectoryTree> {
+    let root_volume = if filter.one_file_system {
+        Some(crate::winapi::volume_serial(root)?)
+    } else {
+        None
+    };
+    let followed = DashSet::new();
+    let canonical_root = if filter.follow_junctions {
+        let info = crate::winapi::file_link_info(root)?;
+        followed.insert((info.volume, info.file_id));
+        Some(std::fs::canonicalize(root)?)
+    } else {
+        None
+    };
+    let ignore = if filter.ignore_files {
+        IgnoreRules::load(root, filter.ignore_parents)?
+    } else {
+        IgnoreRules::default()
+    };
+    let state = ScanState {
         root,
-        &all_dirs,
-        &children_map,
-        &dir_files_map,
-        &file_count,
-        &total_bytes,
-    );
+        filter,
+        root_volume,
+        ignore,
+        all_dirs: DashSet::new(),
+        children_map: DashMap::new(),
+        dir_files_map: DashMap::new(),
+        dir_bytes_map: DashMap::new(),
+        kept_dirs: DashSet::new(),
+        file_count: AtomicUsize::new(0),
+        skipped: Mutex::new(Vec::new()),
+        streams: Mutex::new(Vec::new()),
+        total_bytes: AtomicU64::new(0),
+        stream_bytes: AtomicU64::new(0),
+        on_disk_bytes: AtomicU64::new(0),
+        placeholders: Mutex::new(Vec::new()),
+        reparse_points: Mutex::new(ReparseCounts::default()),
+        canonical_root,
+        followed,
+        max_items,
+        items: AtomicUsize::new(0),
+        stop: AtomicBool::new(false),
+    };
+
+    scan_parallel(root, 0, &state);
 
     let mut tree = DirectoryTree::new();
 
-    tree.dirs = all_dirs.into_iter().collect();
+    tree.dirs = state.all_dirs.into_iter().collect();
 
-    tree.children = children_map.into_iter().collect();
-    tree.dir_files = dir_files_map.into_iter().collect();
+    tree.children = state.children_map.into_iter().collect();
+    tree.dir_files = state.dir_files_map.into_iter().collect();
+    tree.dir_bytes = state.dir_bytes_map.into_iter().collect();
+    tree.kept_dirs = state.kept_dirs.into_iter().collect();
 
     for dir in &tree.dirs {
         if !tree.children.contains_key(dir) {
@@ -86,64 +649,252 @@ pub fn discover_tree(root: &Path) -> io::Result<DirectoryTree> {
         }
     }
 
-    tree.file_count = file_count.load(Ordering::Relaxed);
-    tree.total_bytes = total_bytes.load(Ordering::Relaxed);
+    tree.file_count = state.file_count.load(Ordering::Relaxed);
+    tree.skipped = state.skipped.into_inner();
+    tree.streams = state.streams.into_inner();
+    tree.total_bytes = state.total_bytes.load(Ordering::Relaxed);
+    tree.stream_bytes = state.stream_bytes.load(Ordering::Relaxed);
+    tree.on_disk_bytes = state.on_disk_bytes.load(Ordering::Relaxed);
+    tree.placeholders = state.placeholders.into_inner();
+    tree.reparse_points = state.reparse_points.into_inner();
+    tree.truncated = state.stop.load(Ordering::Relaxed);
 
     Ok(tree)
 }
 
-fn scan_parallel(
-    dir: &Path,
-    all_dirs: &DashSet<PathBuf>,
-    children_map: &DashMap<PathBuf, Vec<PathBuf>>,
-    dir_files_map: &DashMap<PathBuf, Vec<PathBuf>>,
-    file_count: &AtomicUsize,
-    total_bytes: &AtomicU64,
-) {
-    all_dirs.insert(dir.to_path_buf());
+/// Bytes actually released by deleting every file in `tree`.
+///
+/// Hard-linked files (pnpm/npm stores) share one copy of their data, so a
+/// file's size only counts once, and only when all of its links are inside
+/// the tree. Files whose link info can't be read are not counted.
+pub fn unique_freed_bytes(tree: &DirectoryTree) -> u64 {
+    let unique = AtomicU64::new(0);
+    // (volume, file id) -> (link count, size, links seen in tree)
+    let linked: DashMap<(u64, u64), (u32, u64, u32)> = DashMap::new();
+
+    tree.dir_files.par_iter().for_each(|(_, files)| {
+        for file in files {
+            match crate::winapi::file_link_info(file) {
+                Ok(info) if info.links > 1 => {
+                    linked
+                        .entry((info.volume, info.file_id))
+                        .or_insert((info.links, info.size, 0))
+                        .2 += 1;
+                }
+                Ok(info) => {
+                    unique.fetch_add(info.size, Ordering::Relaxed);
+                }
+                Err(_) => {}
+            }
+        }
+    });
+
+    let shared: u64 = linked
+        .iter()
+        .filter(|entry| entry.2 >= entry.0)
+        .map(|entry| entry.1)
+        .sum();
+
+    unique.load(Ordering::Relaxed) + shared
+}
+
+/// The alternate data streams of `file` as `file:name` paths, or none if
+/// they can't be listed.
+pub fn file_streams(file: &Path) -> Vec<PathBuf> {
+    crate::winapi::list_streams(file)
+        .unwrap_or_default()
+        .iter()
+        .map(|stream| crate::winapi::stream_path(file, &stream.name))
+        .collect()
+}
+
+/// Scans `dir` recursively. Returns `true` if the directory has to be kept
+/// because something inside it was rejected by the filter.
+fn scan_parallel(dir: &Path, depth: usize, state: &ScanState) -> bool {
+    if state.stop.load(Ordering::Relaxed) {
+        return false;
+    }
+    state.all_dirs.insert(dir.to_path_buf());
 
     let mut child_dirs = Vec::with_capacity(16);
     let mut files = Vec::with_capacity(64);
     let mut local_bytes = 0u64;
+    let mut local_stream_bytes = 0u64;
+    let mut local_on_disk = 0u64;
+    let mut local_placeholders = Vec::new();
+    let mut local_links = ReparseCounts::default();
+    let mut local_skipped = Vec::new();
+    let mut local_streams = Vec::new();
+    let mut has_files = false;
 
     let mut symlink_dirs = Vec::new();
+    let at_root = dir == state.root;
 
     if let Err(e) = crate::winapi::enumerate_files(dir, |entry| {
+        if at_root
+            && !state.filter.keep_top_level.is_empty()
+            && entry
+                .path
+                .file_name()
+                .is_some_and(|name| state.filter.keeps_top_level(name))
+        {
+            local_skipped.push((entry.path, SkipReason::Excluded));
+            return Ok(());
+        }
+
+        if state.filter.excludes(state.root, &entry.path) || state.ignore.ignores(&entry.path) {
+            local_skipped.push((entry.path, SkipReason::Excluded));
+            return Ok(());
+        }
+
+        if at_root
+            && state.filter.ignore_files
+            && entry.path.file_name() == Some(OsStr::new(IGNORE_FILE))
+        {
+            local_skipped.push((entry.path, SkipReason::Excluded));
+            return Ok(());
+        }
+
+        if entry.is_dir && crate::safety::is_protected_system_folder(&entry.path) {
+            local_skipped.push((entry.path, SkipReason::SystemFolder));
+            return Ok(());
+        }
+
+        if state.root_volume.is_some() && entry.may_cross_volume() {
+            // Unresolvable links (dangling junctions) can only be removed as links
+            if let Ok(volume) = crate::winapi::volume_serial(&entry.path) {
+                if Some(volume) != state.root_volume {
+                    local_skipped.push((entry.path, SkipReason::OtherFileSystem));
+                    return Ok(());
+                }
+            }
+        }
+
+        if entry.is_dir
+            && state
+                .filter
+                .journaled
+                .as_ref()
+                .is_some_and(|done| done.contains(&entry.path))
+        {
+            local_skipped.push((entry.path, SkipReason::Journaled));
+            return Ok(());
+        }
+
+        if entry.is_dir && (!entry.is_symlink || state.follows_junction(&entry)) {
+            if state.filter.max_depth.is_some_and(|max| depth + 1 >= max) {
+                local_skipped.push((entry.path, SkipReason::DepthLimit));
+            } else if state.filter.no_recurse_hidden && entry.is_hidden {
+                local_skipped.push((entry.path, SkipReason::Filtered));
+            } else {
+                child_dirs.push(entry.path);
+            }
+            return Ok(());
+        }
+
+        if state.filter.empty_only {
+            // Not listed in `skipped`: that would be every file in the tree
+            has_files = true;
+            return Ok(());
+        }
+
+        if state.filter.keeps(entry.modified, entry.size) {
+            local_skipped.push((entry.path, SkipReason::Filtered));
+            return Ok(());
+        }
+
         if entry.is_symlink {
+            if let Some(kind) = entry.reparse_kind {
+                local_links.add(kind);
+            }
             if entry.is_dir {
                 symlink_dirs.push(entry.path);
             } else {
                 files.push(entry.path);
             }
-        } else if entry.is_dir {
-            child_dirs.push(entry.path);
+        } else if entry.is_placeholder {
+            local_placeholders.push(entry.path.clone());
+            files.push(entry.path);
         } else {
+            if state.filter.streams || state.filter.stream_bytes {
+                // Unreadable streams still go away with their file
+                let streams = crate::winapi::list_streams(&entry.path).unwrap_or_default();
+                local_stream_bytes += streams.iter().map(|s| s.size).sum::<u64>();
+                if state.filter.streams {
+                    local_streams.extend(
+                        streams
+                            .iter()
+                            .map(|s| crate::winapi::stream_path(&entry.path, &s.name)),
+                    );
+                }
+            }
+            if state.filter.on_disk_size {
+                local_on_disk += crate::winapi::allocated_size(&entry.path).unwrap_or(0);
+            }
             files.push(entry.path);
             local_bytes += entry.size;
         }
         Ok(())
     }) {
-        eprintln!(
-            "Warning: Skipping directory due to enumeration error {}: {}",
-            dir.display(),
-            e
-        );
-        return;
+        if !state.filter.quiet {
+            eprintln!(
+                "Warning: Skipping directory due to enumeration error {}: {}",
+                dir.display(),
+                e
+            );
+        }
+        return false;
     }
 
     // Register symlink directories as leaf directories (no recursion into them)
     for symlink_dir in &symlink_dirs {
-        all_dirs.insert(symlink_dir.clone());
+        state.all_dirs.insert(symlink_dir.clone());
     }
 
     let local_file_count = files.len();
     if !files.is_empty() {
-        dir_files_map.insert(dir.to_path_buf(), files);
-        file_count.fetch_add(local_file_count, Ordering::Relaxed);
+        state.dir_files_map.insert(dir.to_path_buf(), files);
+        state.dir_bytes_map.insert(dir.to_path_buf(), local_bytes);
+        state
+            .file_count
+            .fetch_add(local_file_count, Ordering::Relaxed);
     }
 
+    if local_stream_bytes > 0 {
+        state
+            .stream_bytes
+            .fetch_add(local_stream_bytes, Ordering::Relaxed);
+        local_bytes += local_stream_bytes;
+    }
+    if local_on_disk > 0 {
+        state
+            .on_disk_bytes
+            .fetch_add(local_on_disk, Ordering::Relaxed);
+    }
     if local_bytes > 0 {
-        total_bytes.fetch_add(local_bytes, Ordering::Relaxed);
+        state.total_bytes.fetch_add(local_bytes, Ordering::Relaxed);
+    }
+    if !local_placeholders.is_empty() {
+        state.placeholders.lock().extend(local_placeholders);
+    }
+    if local_links.total() > 0 {
+        state.reparse_points.lock().merge(&local_links);
+    }
+
+    if let Some(max_items) = state.max_items {
+        let found = local_file_count + 1;
+        if state.items.fetch_add(found, Ordering::Relaxed) + found > max_items {
+            state.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    if !local_streams.is_empty() {
+        state.streams.lock().extend(local_streams);
+    }
+
+    let has_skipped = !local_skipped.is_empty();
+    if has_skipped {
+        state.skipped.lock().extend(local_skipped);
     }
 
     // Include symlink dirs in children so parent waits for them before removal
@@ -154,35 +905,29 @@ fn scan_parallel(
         .collect();
 
     if !all_children.is_empty() {
-        children_map.insert(dir.to_path_buf(), all_children);
-    }
-
-    // Only recurse into non-symlink child directories
-    if !child_dirs.is_empty() {
-        if child_dirs.len() >= scan_parallel_threshold() {
-            child_dirs.par_iter().for_each(|child| {
-                scan_parallel(
-                    child,
-                    all_dirs,
-                    children_map,
-                    dir_files_map,
-                    file_count,
-                    total_bytes,
-                );
-            });
-        } else {
-            for child in &child_dirs {
-                scan_parallel(
-                    child,
-                    all_dirs,
-                    children_map,
-                    dir_files_map,
-                    file_count,
-                    total_bytes,
-                );
-            }
+        state.children_map.insert(dir.to_path_buf(), all_children);
+    }
+
+    // Only recurse into non-symlink child directories.
+    // reduce() instead of any(): every child must be scanned, no short-circuit.
+    let child_kept = if child_dirs.len() >= scan_parallel_threshold() {
+        child_dirs
+            .par_iter()
+            .map(|child| scan_parallel(child, depth + 1, state))
+            .reduce(|| false, |a, b| a || b)
+    } else {
+        let mut kept = false;
+        for child in &child_dirs {
+            kept |= scan_parallel(child, depth + 1, state);
         }
+        kept
+    };
+
+    let kept = has_skipped || has_files || child_kept || (depth == 0 && state.filter.keep_root);
+    if kept {
+        state.kept_dirs.insert(dir.to_path_buf());
     }
+    kept
 }
 
 #[cfg(test)]
@@ -210,4 +955,286 @@ mod tests {
 
         let _ = fs::remove_dir_all(&temp);
     }
+
+    #[test]
+    fn test_discover_tree_with_limit_stops_early() {
+        let temp = std::env::temp_dir().join("rmx_scan_limit");
+        let _ = fs::remove_dir_all(&temp);
+        // A single chain is scanned sequentially, so the stop is exact
+        let mut dir = temp.clone();
+        for i in 0..20 {
+            dir = dir.join(format!("d{}", i));
+            fs::create_dir_all(&dir).unwrap();
+            for j in 0..10 {
+                fs::write(dir.join(format!("f{}", j)), "x").unwrap();
+            }
+        }
+
+        let filter = ScanFilter::default();
+        let limited = discover_tree_with_limit(&temp, &filter, 50).unwrap();
+        assert!(limited.truncated);
+        assert_eq!(limited.file_count, 50);
+
+        let full = discover_tree_with_limit(&temp, &filter, 1000).unwrap();
+        assert!(!full.truncated);
+        assert_eq!(full.file_count, 200);
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("24h"), Ok(Duration::from_secs(86_400)));
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 86_400)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 86_400)));
+        assert!(parse_age("7").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("7y").is_err());
+        assert!(parse_age("-1d").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("64K"), Ok(64 * 1024));
+        assert_eq!(parse_size("10m"), Ok(10 << 20));
+        assert_eq!(parse_size("1GB"), Ok(1 << 30));
+        assert_eq!(parse_size("2TiB"), Ok(2 << 40));
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("1.5G").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn test_iter_dirs_topological_puts_children_first() {
+        let root = PathBuf::from("root");
+        let (a, b, deep) = (root.join("a"), root.join("b"), root.join("b/deep"));
+        let mut tree = DirectoryTree::new();
+        tree.dirs = vec![root.clone(), b.clone(), deep.clone(), a.clone()];
+        tree.children
+            .insert(root.clone(), vec![b.clone(), a.clone()]);
+        tree.children.insert(b.clone(), vec![deep.clone()]);
+        tree.dir_files
+            .insert(root.clone(), vec![root.join("z"), root.join("y")]);
+        tree.dir_files.insert(deep.clone(), vec![deep.join("x")]);
+
+        let dirs: Vec<&Path> = tree.iter_dirs_topological().collect();
+        assert_eq!(dirs, [a.as_path(), &deep, &b, &root]);
+        let parents_first: Vec<&Path> = tree.iter_dirs_topological().rev().collect();
+        assert_eq!(parents_first[0], root);
+
+        let files: Vec<&Path> = tree.iter_files().collect();
+        assert_eq!(files, [deep.join("x"), root.join("y"), root.join("z")]);
+    }
+
+    #[test]
+    fn test_render_tree_is_sorted_and_capped() {
+        let root = PathBuf::from("root");
+        let mut tree = DirectoryTree::new();
+        tree.dirs = vec![root.clone(), root.join("b"), root.join("a")];
+        tree.children
+            .insert(root.clone(), vec![root.join("b"), root.join("a")]);
+        tree.dir_files
+            .insert(root.clone(), vec![root.join("z.txt"), root.join("c.txt")]);
+        tree.dir_files
+            .insert(root.join("b"), vec![root.join("b/x")]);
+        tree.file_count = 3;
+        tree.kept_dirs.insert(root.join("a"));
+
+        assert_eq!(
+            render_tree(&tree, &root, None),
+            "root\n  a/ (kept)\n  b/\n    x\n  c.txt\n  z.txt\n"
+        );
+        assert_eq!(
+            render_tree(&tree, &root, Some(2)),
+            "root\n  a/ (kept)\n  b/\n... and 3 more\n"
+        );
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let filter = ScanFilter {
+            exclude: vec![
+                glob::Pattern::new("*.log").unwrap(),
+                glob::Pattern::new("**/node_modules/.cache/**").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let root = Path::new("root");
+
+        assert!(filter.excludes(root, &root.join("a.log")));
+        assert!(filter.excludes(root, &root.join("deep/dir/b.log")));
+        assert!(filter.excludes(root, &root.join("pkg/node_modules/.cache/x/y.bin")));
+        assert!(filter.excludes(root, &root.join("node_modules/.cache/z")));
+        assert!(!filter.excludes(root, &root.join("node_modules/lodash/index.js")));
+        assert!(!filter.excludes(root, &root.join("log.txt")));
+    }
+
+    #[test]
+    fn test_max_depth_keeps_deeper_directories() {
+        let temp = std::env::temp_dir().join("rmx_max_depth");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("a/b/c")).unwrap();
+        fs::write(temp.join("top.txt"), "").unwrap();
+        fs::write(temp.join("a/mid.txt"), "").unwrap();
+        fs::write(temp.join("a/b/deep.txt"), "").unwrap();
+
+        let filter = ScanFilter {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let tree = discover_tree_filtered(&temp, &filter).unwrap();
+
+        assert_eq!(tree.file_count, 2);
+        assert_eq!(
+            tree.skipped,
+            vec![(temp.join("a/b"), SkipReason::DepthLimit)]
+        );
+        assert!(!tree.dirs.contains(&temp.join("a/b")));
+        assert_eq!(tree.removable_dir_count(), 0);
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_excluded_file_keeps_parent_chain() {
+        let temp = std::env::temp_dir().join("rmx_exclude_parent_chain");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("a/b")).unwrap();
+        fs::create_dir_all(temp.join("c")).unwrap();
+        fs::write(temp.join("a/b/deps.lock"), "").unwrap();
+        fs::write(temp.join("a/b/x.txt"), "").unwrap();
+        fs::write(temp.join("c/y.txt"), "").unwrap();
+
+        let filter = ScanFilter {
+            exclude: vec![glob::Pattern::new("*.lock").unwrap()],
+            ..Default::default()
+        };
+        let tree = discover_tree_filtered(&temp, &filter).unwrap();
+
+        let lock = temp.join("a/b/deps.lock");
+        assert_eq!(tree.skipped, vec![(lock.clone(), SkipReason::Excluded)]);
+        assert!(!tree.dir_files.values().flatten().any(|f| *f == lock));
+        assert_eq!(tree.file_count, 2);
+        for dir in [temp.join("a/b"), temp.join("a"), temp.clone()] {
+            assert!(tree.kept_dirs.contains(&dir), "{}", dir.display());
+        }
+        assert!(!tree.kept_dirs.contains(&temp.join("c")));
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_one_file_system_skips_other_volumes() {
+        use std::os::unix::fs::MetadataExt;
+
+        // Scanning never modifies anything. Look for a directory under /dev
+        // on another device (/dev/pts, /dev/shm, ...) rather than assuming one
+        let root = Path::new("/dev");
+        let Ok(root_dev) = fs::metadata(root).map(|m| m.dev()) else {
+            eprintln!("skipping test_one_file_system_skips_other_volumes: no /dev");
+            return;
+        };
+        let mount = fs::read_dir(root).ok().and_then(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .find(|p| fs::symlink_metadata(p).is_ok_and(|m| m.is_dir() && m.dev() != root_dev))
+        });
+        let Some(mount) = mount else {
+            eprintln!(
+                "skipping test_one_file_system_skips_other_volumes: \
+                 nothing under /dev is on another device"
+            );
+            return;
+        };
+        let mount = mount.as_path();
+
+        let filter = ScanFilter {
+            one_file_system: true,
+            ..Default::default()
+        };
+        let tree = discover_tree_filtered(root, &filter).unwrap();
+        assert!(tree
+            .skipped
+            .contains(&(mount.to_path_buf(), SkipReason::OtherFileSystem)));
+        assert!(!tree.dirs.iter().any(|d| d.starts_with(mount)));
+        assert!(tree.kept_dirs.contains(root));
+    }
+
+    #[test]
+    fn test_empty_only_selects_empty_directories() {
+        let temp = std::env::temp_dir().join("rmx_empty_only_test");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("empty/nested")).unwrap();
+        fs::create_dir_all(temp.join("full/sub")).unwrap();
+        fs::write(temp.join("full/sub/file.txt"), "x").unwrap();
+        fs::create_dir_all(temp.join("full/hollow")).unwrap();
+
+        let filter = ScanFilter {
+            empty_only: true,
+            ..Default::default()
+        };
+        let tree = discover_tree_filtered(&temp, &filter).unwrap();
+        assert_eq!(tree.file_count, 0);
+        assert!(tree.skipped.is_empty());
+        assert_eq!(
+            tree.kept_dirs,
+            HashSet::from([temp.clone(), temp.join("full"), temp.join("full/sub")])
+        );
+        // empty, empty/nested and full/hollow
+        assert_eq!(tree.removable_dir_count(), 3);
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_keep_root_only_keeps_the_root() {
+        let temp = std::env::temp_dir().join("rmx_keep_root_test");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(temp.join("a/b")).unwrap();
+        fs::write(temp.join("a/b/file.txt"), "x").unwrap();
+
+        let filter = ScanFilter {
+            keep_root: true,
+            ..Default::default()
+        };
+        let tree = discover_tree_filtered(&temp, &filter).unwrap();
+        assert_eq!(tree.kept_dirs, HashSet::from([temp.clone()]));
+        assert_eq!(tree.removable_dir_count(), 2);
+        assert_eq!(tree.file_count, 1);
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_links_are_counted_and_not_followed() {
+        let temp = std::env::temp_dir().join("rmx_reparse_count_test");
+        let outside = std::env::temp_dir().join("rmx_reparse_count_target");
+        let _ = fs::remove_dir_all(&temp);
+        let _ = fs::remove_dir_all(&outside);
+        fs::create_dir_all(&temp).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("keep.txt"), "x").unwrap();
+        std::os::unix::fs::symlink(&outside, temp.join("dir_link")).unwrap();
+        std::os::unix::fs::symlink(outside.join("keep.txt"), temp.join("file_link")).unwrap();
+
+        // Symlinks are never followed, junctions or not
+        let filter = ScanFilter {
+            follow_junctions: true,
+            ..Default::default()
+        };
+        let tree = discover_tree_filtered(&temp, &filter).unwrap();
+        assert_eq!(tree.reparse_points.symlinks, 2);
+        assert_eq!(tree.reparse_points.total(), 2);
+        assert_eq!(tree.dirs, vec![temp.clone()]);
+        assert_eq!(tree.file_count, 2);
+
+        let _ = fs::remove_dir_all(&temp);
+        let _ = fs::remove_dir_all(&outside);
+    }
 }
@@ -0,0 +1,238 @@
+//! User-facing text of the GUI windows. Every language is one [`Strings`]
+//! table; the language comes from `--lang`, then `RMX_LANG`, then the
+//! Windows UI language.
+
+use std::sync::OnceLock;
+
+/// Overrides the detected language, like `--lang`
+pub const LANG_ENV: &str = "RMX_LANG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Lang {
+    En,
+    Zh,
+}
+
+impl Lang {
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Lang::En => &EN,
+            Lang::Zh => &ZH,
+        }
+    }
+
+    /// Accepts `en`, `zh` and locale names such as `zh_CN.UTF-8` or `en-US`.
+    pub fn parse(s: &str) -> Option<Lang> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.starts_with("zh") {
+            Some(Lang::Zh)
+        } else if s.starts_with("en") {
+            Some(Lang::En)
+        } else {
+            None
+        }
+    }
+
+    fn detect() -> Lang {
+        std::env::var(LANG_ENV)
+            .ok()
+            .and_then(|s| Lang::parse(&s))
+            .unwrap_or_else(system_lang)
+    }
+}
+
+#[cfg(windows)]
+fn system_lang() -> Lang {
+    const LANG_CHINESE: u16 = 0x04;
+    let lang_id = unsafe { windows::Win32::Globalization::GetUserDefaultUILanguage() };
+    // PRIMARYLANGID
+    if lang_id & 0x3ff == LANG_CHINESE {
+        Lang::Zh
+    } else {
+        Lang::En
+    }
+}
+
+#[cfg(not(windows))]
+fn system_lang() -> Lang {
+    std::env::var("LANG")
+        .ok()
+        .and_then(|s| Lang::parse(&s))
+        .unwrap_or(Lang::En)
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Use `lang` for all windows. Only the first call (before any window is
+/// shown) has an effect.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+pub fn lang() -> Lang {
+    *LANG.get_or_init(Lang::detect)
+}
+
+/// The table for the current language
+pub fn strings() -> &'static Strings {
+    lang().strings()
+}
+
+pub struct Strings {
+    // Delete progress
+    pub progress_title: &'static str,
+    pub deleting: &'static str,
+    pub done: &'static str,
+    pub done_with_errors: &'static str,
+    pub completed: &'static str,
+    pub completed_errors: fn(usize) -> String,
+    pub deleted_dirs: fn(usize, usize) -> String,
+    pub copy_errors: &'static str,
+
+    // Confirm delete
+    pub confirm_title: &'static str,
+    pub confirm_warning: &'static str,
+    pub skip_next_confirm: &'static str,
+    pub delete: &'static str,
+    pub one_file: &'static str,
+    pub files: fn(usize) -> String,
+    pub dirs: fn(usize) -> String,
+    pub list_separator: &'static str,
+
+    // Unlock
+    pub unlock_title: &'static str,
+    pub unlock_subtitle: &'static str,
+    pub unlocking: &'static str,
+    pub unlocking_subtitle: &'static str,
+    pub unlock_success: &'static str,
+    pub killed_processes: fn(usize) -> String,
+    pub unlock_partial: &'static str,
+    pub unlock_counts: fn(usize, usize) -> String,
+    pub unlock_targets: fn(usize) -> String,
+    pub item_name: &'static str,
+    pub status: &'static str,
+    pub status_unlocking: &'static str,
+    pub status_pending: &'static str,
+    pub locked_by: fn(&str) -> String,
+    pub process: &'static str,
+    pub name: &'static str,
+    pub path: &'static str,
+    pub failure_reason: &'static str,
+    pub unlock_all: &'static str,
+    pub please_wait: &'static str,
+    pub no_locks: &'static str,
+
+    // Shared buttons
+    pub cancel: &'static str,
+    pub close: &'static str,
+    pub ok: &'static str,
+}
+
+pub const EN: Strings = Strings {
+    progress_title: "Delete Progress",
+    deleting: "Deleting...",
+    done: "Deletion complete",
+    done_with_errors: "Deletion complete (with errors)",
+    completed: "Completed",
+    completed_errors: |errors| format!("Completed, {} error(s)", errors),
+    deleted_dirs: |deleted, total| format!("Deleted {} / {} directories", deleted, total),
+    copy_errors: "Copy errors",
+
+    confirm_title: "Confirm Delete",
+    confirm_warning: "This cannot be undone; files will not go to the Recycle Bin",
+    skip_next_confirm: "Don't ask again",
+    delete: "Delete",
+    one_file: "1 file",
+    files: |n| format!("{} files", n),
+    dirs: |n| format!("{} directories", n),
+    list_separator: ", ",
+
+    unlock_title: "Unlock Files",
+    unlock_subtitle: "Unlock files or folders held open by other processes",
+    unlocking: "Unlocking...",
+    unlocking_subtitle: "Terminating locking processes",
+    unlock_success: "Unlocked",
+    killed_processes: |n| format!("Terminated {} locking process(es)", n),
+    unlock_partial: "Some items could not be unlocked",
+    unlock_counts: |ok, failed| format!("{} succeeded, {} failed", ok, failed),
+    unlock_targets: |n| format!("The following {} file(s)/folder(s) will be unlocked", n),
+    item_name: "File/folder name",
+    status: "Status",
+    status_unlocking: "Unlocking",
+    status_pending: "Pending",
+    locked_by: |name| format!("{} is locked by", name),
+    process: "Process",
+    name: "Name",
+    path: "Path",
+    failure_reason: "Reason",
+    unlock_all: "Unlock all",
+    please_wait: "Working, please wait...",
+    no_locks: "No locks found, nothing to unlock",
+
+    cancel: "Cancel",
+    close: "Close",
+    ok: "OK",
+};
+
+pub const ZH: Strings = Strings {
+    progress_title: "删除进度",
+    deleting: "正在删除...",
+    done: "删除完成",
+    done_with_errors: "删除完成（有错误）",
+    completed: "已完成",
+    completed_errors: |errors| format!("完成，{} 个错误", errors),
+    deleted_dirs: |deleted, total| format!("已删除 {} / {} 个目录", deleted, total),
+    copy_errors: "复制错误",
+
+    confirm_title: "确认删除",
+    confirm_warning: "此操作不可撤销，文件不会进入回收站",
+    skip_next_confirm: "下次不再确认",
+    delete: "删除",
+    one_file: "1 个文件",
+    files: |n| format!("{} 个文件", n),
+    dirs: |n| format!("{} 个目录", n),
+    list_separator: "，",
+
+    unlock_title: "文件解锁",
+    unlock_subtitle: "帮助你解锁被其他进程占用的文件或文件夹",
+    unlocking: "正在解锁...",
+    unlocking_subtitle: "正在终止占用进程",
+    unlock_success: "解锁成功",
+    killed_processes: |n| format!("已终止 {} 个占用进程", n),
+    unlock_partial: "部分解锁失败",
+    unlock_counts: |ok, failed| format!("成功 {} 个，失败 {} 个", ok, failed),
+    unlock_targets: |n| format!("将对以下 {} 个文件/文件夹进行解锁", n),
+    item_name: "文件/文件夹名称",
+    status: "状态",
+    status_unlocking: "解锁中",
+    status_pending: "待解锁",
+    locked_by: |name| format!("{} 被以下程序锁定", name),
+    process: "进程",
+    name: "名称",
+    path: "路径",
+    failure_reason: "失败原因",
+    unlock_all: "全部解锁",
+    please_wait: "正在处理，请稍候...",
+    no_locks: "未检测到文件被占用，无需解锁",
+
+    cancel: "取消",
+    close: "关闭",
+    ok: "好的",
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lang() {
+        assert_eq!(Lang::parse("zh_CN.UTF-8"), Some(Lang::Zh));
+        assert_eq!(Lang::parse("EN-us"), Some(Lang::En));
+        assert_eq!(Lang::parse("de_DE"), None);
+        assert_eq!((Lang::En.strings().files)(3), "3 files");
+        assert_eq!(
+            (Lang::Zh.strings().deleted_dirs)(1, 2),
+            "已删除 1 / 2 个目录"
+        );
+    }
+}
@@ -1,18 +1,26 @@
 use std::env;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
 const GITHUB_API_URL: &str = "https://api.github.com/repos/zerx-lab/rmx/releases/latest";
+const GITHUB_RELEASES_URL: &str = "https://api.github.com/repos/zerx-lab/rmx/releases";
 const ASSET_SUFFIX: &str = "x86_64-pc-windows-msvc.zip";
+const DOWNLOAD_BAR_WIDTH: usize = 30;
+const DOWNLOAD_REDRAW_INTERVAL: Duration = Duration::from_millis(100);
 
 // ── GitHub API types ─────────────────────────────────────────────────────
 
 #[derive(Debug, Deserialize)]
 struct GitHubRelease {
     tag_name: String,
+    #[serde(default)]
+    draft: bool,
     assets: Vec<GitHubAsset>,
 }
 
@@ -21,6 +29,21 @@ struct GitHubAsset {
     name: String,
     browser_download_url: String,
     size: u64,
+    /// `sha256:<hex>`, reported by the API for assets uploaded since mid-2025
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// Which release `rmx upgrade` installs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UpgradeTarget {
+    /// The newest stable release
+    #[default]
+    Latest,
+    /// The newest release, prereleases included (`--pre`)
+    Prerelease,
+    /// A specific release tag (`--version`), which may be a downgrade
+    Tag(String),
 }
 
 // ── Installation method detection ────────────────────────────────────────
@@ -34,11 +57,8 @@ enum InstallMethod {
 }
 
 impl InstallMethod {
-    fn detect() -> Self {
-        let path_str = env::current_exe()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_lowercase();
+    fn detect(exe: &Path) -> Self {
+        let path_str = exe.to_string_lossy().to_lowercase();
 
         if path_str.contains("scoop") && path_str.contains("apps") {
             InstallMethod::Scoop
@@ -68,22 +88,127 @@ impl InstallMethod {
 
 // ── Public API ───────────────────────────────────────────────────────────
 
+/// Resolve the path of the running rmx executable.
+///
+/// Fails with a descriptive error instead of falling back to an empty path,
+/// which would otherwise make install detection and `.old` handling misbehave.
+pub fn current_exe_path() -> io::Result<PathBuf> {
+    resolve_exe_path(env::current_exe())
+}
+
+fn resolve_exe_path(exe: io::Result<PathBuf>) -> io::Result<PathBuf> {
+    match exe {
+        Ok(path) if path.file_name().is_some() => Ok(path),
+        Ok(path) => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "cannot determine rmx executable path (got '{}')",
+                path.display()
+            ),
+        )),
+        Err(e) => Err(io::Error::new(
+            e.kind(),
+            format!("cannot determine rmx executable path: {}", e),
+        )),
+    }
+}
+
 /// 清理上次升级残留的 .old 文件（在 main 启动时调用）
+///
+/// 无法确定 exe 路径时直接跳过。
 pub fn cleanup_old_binary() {
-    if let Ok(exe) = env::current_exe() {
-        let old = old_path(&exe);
-        if old.exists() {
-            let _ = fs::remove_file(&old);
-        }
+    cleanup_old_binary_for(current_exe_path().ok().as_deref());
+}
+
+fn cleanup_old_binary_for(exe: Option<&Path>) {
+    let Some(exe) = exe else {
+        return;
+    };
+    // `rmx rollback` is swapping the binaries right now
+    if rollback_marker_path(exe).exists() {
+        return;
+    }
+    let old = old_path(exe);
+    if old.exists() {
+        let _ = fs::remove_file(&old);
+    }
+}
+
+/// 回滚到上次升级前的版本：与 `.old` 互换
+///
+/// Only possible until the next rmx start cleans `.old` up. The replaced
+/// binary becomes the new `.old`, so a second rollback undoes the first.
+pub fn rollback() -> anyhow::Result<()> {
+    let current_exe = current_exe_path()?;
+    let restored = rollback_for(&current_exe)?;
+    println!(
+        "rmx: restored the previous binary\n  -> {}",
+        restored.display()
+    );
+    Ok(())
+}
+
+fn rollback_for(current_exe: &Path) -> anyhow::Result<PathBuf> {
+    let old_exe = old_path(current_exe);
+    if !old_exe.exists() {
+        return Err(anyhow::anyhow!(
+            "no previous binary at '{}'; it is removed the next time rmx starts after an upgrade",
+            old_exe.display()
+        ));
     }
+
+    // Keeps other rmx processes from cleaning up `.old` mid-swap
+    let marker = rollback_marker_path(current_exe);
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&marker)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => anyhow::anyhow!(
+                "another rollback is in progress (remove '{}' if it is stale)",
+                marker.display()
+            ),
+            _ => anyhow::anyhow!("failed to create '{}': {}", marker.display(), e),
+        })?;
+    let result = swap_binaries(current_exe, &old_exe);
+    let _ = fs::remove_file(&marker);
+    result.map(|()| current_exe.to_path_buf())
+}
+
+/// Windows 允许重命名正在运行的 exe，三次重命名完成互换
+fn swap_binaries(current_exe: &Path, old_exe: &Path) -> anyhow::Result<()> {
+    let aside = with_suffix(current_exe, ".rollback-tmp");
+    if aside.exists() {
+        fs::remove_file(&aside)
+            .map_err(|e| anyhow::anyhow!("failed to remove stale '{}': {}", aside.display(), e))?;
+    }
+
+    fs::rename(current_exe, &aside)
+        .map_err(|e| anyhow::anyhow!("failed to rename current binary: {}", e))?;
+    if let Err(e) = fs::rename(old_exe, current_exe) {
+        let _ = fs::rename(&aside, current_exe);
+        return Err(anyhow::anyhow!("failed to restore previous binary: {}", e));
+    }
+    // The previous binary is in place; a leftover aside file is harmless
+    let _ = fs::rename(&aside, old_exe);
+    Ok(())
 }
 
 /// 执行升级流程
-pub fn run_upgrade(check_only: bool, force: bool) -> anyhow::Result<()> {
-    cleanup_old_binary();
+///
+/// `proxy` overrides the `HTTPS_PROXY` / `HTTP_PROXY` / `NO_PROXY` environment.
+pub fn run_upgrade(
+    check_only: bool,
+    force: bool,
+    target: &UpgradeTarget,
+    proxy: Option<&str>,
+) -> anyhow::Result<()> {
+    let agent = build_agent(proxy)?;
+    let current_exe = current_exe_path()?;
+    cleanup_old_binary_for(Some(&current_exe));
 
     if !force {
-        let method = InstallMethod::detect();
+        let method = InstallMethod::detect(&current_exe);
         if let Some(hint) = method.upgrade_hint() {
             println!("rmx: detected {:?} installation", method);
             println!("  recommended: {}", hint);
@@ -99,23 +224,42 @@ pub fn run_upgrade(check_only: bool, force: bool) -> anyhow::Result<()> {
     );
     io::stdout().flush().ok();
 
-    let release = fetch_latest_release()?;
+    let release = fetch_release(&agent, target)?;
     let latest_version = release.tag_name.trim_start_matches('v');
     println!("v{}", latest_version);
 
-    if !force {
-        if is_up_to_date(current_version, latest_version) {
+    if let UpgradeTarget::Tag(_) = target {
+        // An explicit version is installed even if it isn't newer
+        if check_only {
+            println!(
+                "rmx: would install v{} (current: v{})",
+                latest_version, current_version
+            );
+            return Ok(());
+        }
+        if !force && is_older(latest_version, current_version) {
+            let prompt = format!(
+                "rmx: v{} is older than the installed v{}; downgrade? [y/N] ",
+                latest_version, current_version
+            );
+            if !confirm(&prompt)? {
+                println!("rmx: upgrade cancelled");
+                return Ok(());
+            }
+        }
+    } else {
+        if !force && is_up_to_date(current_version, latest_version) {
             println!("rmx: already up to date");
             return Ok(());
         }
-    }
 
-    if check_only {
-        println!(
-            "rmx: update available: v{} -> v{}",
-            current_version, latest_version
-        );
-        return Ok(());
+        if check_only {
+            println!(
+                "rmx: update available: v{} -> v{}",
+                current_version, latest_version
+            );
+            return Ok(());
+        }
     }
 
     let asset = release
@@ -124,6 +268,8 @@ pub fn run_upgrade(check_only: bool, force: bool) -> anyhow::Result<()> {
         .find(|a| a.name.ends_with(ASSET_SUFFIX))
         .ok_or_else(|| anyhow::anyhow!("no matching release asset for this platform"))?;
 
+    let expected_sha256 = expected_sha256(&agent, &release, asset)?;
+
     println!(
         "rmx: downloading {} ({})...",
         asset.name,
@@ -133,14 +279,26 @@ pub fn run_upgrade(check_only: bool, force: bool) -> anyhow::Result<()> {
     let temp_dir = env::temp_dir().join("rmx-upgrade");
     fs::create_dir_all(&temp_dir)?;
     let zip_path = temp_dir.join(&asset.name);
-    download_file(&asset.browser_download_url, &zip_path)?;
+    download_file(&agent, &asset.browser_download_url, &zip_path)?;
+
+    println!("rmx: verifying checksum...");
+    let actual_sha256 = sha256_file(&zip_path)?;
+    if actual_sha256 != expected_sha256 {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return Err(anyhow::anyhow!(
+            "checksum mismatch for {}: expected {}, got {}; upgrade aborted",
+            asset.name,
+            expected_sha256,
+            actual_sha256
+        ));
+    }
 
     println!("rmx: extracting...");
     let new_exe = temp_dir.join("rmx.exe");
     extract_exe_from_zip(&zip_path, &new_exe)?;
 
     println!("rmx: installing...");
-    let installed_path = replace_self(&new_exe)?;
+    let installed_path = replace_self(&new_exe, &current_exe)?;
 
     let _ = fs::remove_dir_all(&temp_dir);
 
@@ -156,41 +314,246 @@ pub fn run_upgrade(check_only: bool, force: bool) -> anyhow::Result<()> {
 // ── Internal helpers ─────────────────────────────────────────────────────
 
 fn old_path(exe: &Path) -> PathBuf {
+    with_suffix(exe, ".old")
+}
+
+fn rollback_marker_path(exe: &Path) -> PathBuf {
+    with_suffix(exe, ".rollback")
+}
+
+fn with_suffix(exe: &Path, suffix: &str) -> PathBuf {
     let mut name = exe.file_name().unwrap_or_default().to_os_string();
-    name.push(".old");
+    name.push(suffix);
     exe.with_file_name(name)
 }
 
-fn fetch_latest_release() -> anyhow::Result<GitHubRelease> {
-    let body: String = ureq::get(GITHUB_API_URL)
+/// HTTP agent for every request of one upgrade. Without `proxy`, ureq picks
+/// up `HTTPS_PROXY` / `HTTP_PROXY` (and `NO_PROXY`) from the environment.
+fn build_agent(proxy: Option<&str>) -> anyhow::Result<ureq::Agent> {
+    let proxy = match proxy {
+        Some(url) => Some(
+            ureq::Proxy::new(url).map_err(|e| anyhow::anyhow!("invalid proxy '{}': {}", url, e))?,
+        ),
+        None => ureq::Proxy::try_from_env(),
+    };
+    Ok(ureq::Agent::config_builder()
+        .proxy(proxy)
+        .build()
+        .new_agent())
+}
+
+/// The proxy `url` is fetched through, unless `NO_PROXY` exempts it
+fn proxy_for<'a>(agent: &'a ureq::Agent, url: &str) -> Option<&'a ureq::Proxy> {
+    let uri: ureq::http::Uri = url.parse().ok()?;
+    agent
+        .config()
+        .proxy()
+        .filter(|proxy| !proxy.is_no_proxy(&uri))
+}
+
+/// Blame the proxy for connection failures when one is in use, so a dead
+/// proxy isn't mistaken for GitHub being unreachable.
+fn request_error(agent: &ureq::Agent, url: &str, context: &str, e: ureq::Error) -> anyhow::Error {
+    let Some(proxy) = proxy_for(agent, url) else {
+        return anyhow::anyhow!("{}: {}", context, e);
+    };
+    match e {
+        ureq::Error::ConnectProxyFailed(_)
+        | ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::Io(_)
+        | ureq::Error::Timeout(_) => anyhow::anyhow!(
+            "{}: cannot connect through proxy {}:{}: {}",
+            context,
+            proxy.host(),
+            proxy.port(),
+            e
+        ),
+        e => anyhow::anyhow!(
+            "{} (via proxy {}:{}): {}",
+            context,
+            proxy.host(),
+            proxy.port(),
+            e
+        ),
+    }
+}
+
+fn fetch_release(agent: &ureq::Agent, target: &UpgradeTarget) -> anyhow::Result<GitHubRelease> {
+    match target {
+        UpgradeTarget::Latest => fetch_json(agent, GITHUB_API_URL),
+        UpgradeTarget::Tag(tag) => {
+            // Release tags are `v`-prefixed; accept `0.3.0` as well
+            let tag = format!("v{}", tag.trim_start_matches('v'));
+            fetch_json(agent, &format!("{}/tags/{}", GITHUB_RELEASES_URL, tag))
+                .map_err(|e| anyhow::anyhow!("release {} not found: {}", tag, e))
+        }
+        UpgradeTarget::Prerelease => {
+            let releases: Vec<GitHubRelease> =
+                fetch_json(agent, &format!("{}?per_page=30", GITHUB_RELEASES_URL))?;
+            releases
+                .into_iter()
+                .filter(|r| !r.draft)
+                .filter_map(|r| {
+                    let version =
+                        semver::Version::parse(r.tag_name.trim_start_matches('v')).ok()?;
+                    Some((version, r))
+                })
+                .max_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, r)| r)
+                .ok_or_else(|| anyhow::anyhow!("no releases found"))
+        }
+    }
+}
+
+fn fetch_json<T: DeserializeOwned>(agent: &ureq::Agent, url: &str) -> anyhow::Result<T> {
+    let body: String = agent
+        .get(url)
         .header("Accept", "application/vnd.github.v3+json")
         .header("User-Agent", "rmx-self-updater")
         .call()
-        .map_err(|e| anyhow::anyhow!("failed to query GitHub API: {}", e))?
+        .map_err(|e| request_error(agent, url, "failed to query GitHub API", e))?
         .body_mut()
         .read_to_string()
         .map_err(|e| anyhow::anyhow!("failed to read response body: {}", e))?;
 
-    let release: GitHubRelease = serde_json::from_str(&body)
-        .map_err(|e| anyhow::anyhow!("failed to parse GitHub response: {}", e))?;
+    serde_json::from_str(&body)
+        .map_err(|e| anyhow::anyhow!("failed to parse GitHub response: {}", e))
+}
 
-    Ok(release)
+fn confirm(prompt: &str) -> io::Result<bool> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    Ok(matches!(
+        response.trim().to_lowercase().as_str(),
+        "y" | "yes"
+    ))
 }
 
-fn download_file(url: &str, dest: &Path) -> anyhow::Result<()> {
-    let mut reader = ureq::get(url)
+fn download_file(agent: &ureq::Agent, url: &str, dest: &Path) -> anyhow::Result<()> {
+    let response = agent
+        .get(url)
         .header("User-Agent", "rmx-self-updater")
         .call()
-        .map_err(|e| anyhow::anyhow!("download failed: {}", e))?
-        .into_body()
-        .into_reader();
+        .map_err(|e| request_error(agent, url, "download failed", e))?;
+    let total = response
+        .headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let mut reader = response.into_body().into_reader();
 
     let mut file = fs::File::create(dest)?;
-    io::copy(&mut reader, &mut file)?;
+    let show_bar = io::stderr().is_terminal();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut downloaded = 0u64;
+    let mut last_draw = Instant::now();
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        file.write_all(&buf[..n])?;
+        downloaded += n as u64;
+        if show_bar && last_draw.elapsed() >= DOWNLOAD_REDRAW_INTERVAL {
+            eprint!("\r{}", download_progress_line(downloaded, total));
+            io::stderr().flush().ok();
+            last_draw = Instant::now();
+        }
+    }
+    if show_bar {
+        eprintln!("\r{}", download_progress_line(downloaded, total));
+    }
     file.flush()?;
     Ok(())
 }
 
+/// `[#####-----]  48%   3.5 MB / 7.2 MB`, or just the byte count when the
+/// server sent no `Content-Length`.
+fn download_progress_line(downloaded: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) if total > 0 => {
+            let fraction = (downloaded as f64 / total as f64).min(1.0);
+            let filled = (fraction * DOWNLOAD_BAR_WIDTH as f64) as usize;
+            format!(
+                "[{}{}] {:>3}% {:>9} / {}",
+                "#".repeat(filled),
+                "-".repeat(DOWNLOAD_BAR_WIDTH - filled),
+                (fraction * 100.0) as u32,
+                format_size(downloaded),
+                format_size(total)
+            )
+        }
+        _ => format!("{:>9} downloaded", format_size(downloaded)),
+    }
+}
+
+/// SHA-256 the downloaded `asset` must have, from the API's per-asset digest
+/// or else the release's `<asset>.sha256` sidecar. Unverifiable assets are
+/// an error rather than installed blindly.
+fn expected_sha256(
+    agent: &ureq::Agent,
+    release: &GitHubRelease,
+    asset: &GitHubAsset,
+) -> anyhow::Result<String> {
+    if let Some(hex) = asset
+        .digest
+        .as_deref()
+        .and_then(|d| d.strip_prefix("sha256:"))
+    {
+        return parse_sha256(hex)
+            .ok_or_else(|| anyhow::anyhow!("malformed digest for {}: {}", asset.name, hex));
+    }
+
+    let sidecar_name = format!("{}.sha256", asset.name);
+    let sidecar = release
+        .assets
+        .iter()
+        .find(|a| a.name == sidecar_name)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "release has no SHA-256 checksum for {}; refusing to install an unverified binary",
+                asset.name
+            )
+        })?;
+
+    let url = &sidecar.browser_download_url;
+    let text = agent
+        .get(url)
+        .header("User-Agent", "rmx-self-updater")
+        .call()
+        .map_err(|e| {
+            request_error(
+                agent,
+                url,
+                &format!("failed to download {}", sidecar.name),
+                e,
+            )
+        })?
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| anyhow::anyhow!("failed to read {}: {}", sidecar.name, e))?;
+    parse_sha256(&text).ok_or_else(|| anyhow::anyhow!("malformed checksum file {}", sidecar.name))
+}
+
+/// First token of a `sha256sum`-style line, lowercased, if it is 64 hex digits
+fn parse_sha256(text: &str) -> Option<String> {
+    let hex = text.split_whitespace().next()?;
+    (hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| hex.to_ascii_lowercase())
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 fn extract_exe_from_zip(zip_path: &Path, dest: &Path) -> anyhow::Result<()> {
     let file = fs::File::open(zip_path)?;
     let mut archive = zip::ZipArchive::new(file)?;
@@ -212,9 +575,8 @@ fn extract_exe_from_zip(zip_path: &Path, dest: &Path) -> anyhow::Result<()> {
 }
 
 /// Rename-and-Replace: Windows 允许重命名正在运行的 exe
-fn replace_self(new_exe: &Path) -> anyhow::Result<PathBuf> {
-    let current_exe = env::current_exe()?;
-    let old_exe = old_path(&current_exe);
+fn replace_self(new_exe: &Path, current_exe: &Path) -> anyhow::Result<PathBuf> {
+    let old_exe = old_path(current_exe);
 
     if old_exe.exists() {
         fs::remove_file(&old_exe).map_err(|e| {
@@ -222,36 +584,32 @@ fn replace_self(new_exe: &Path) -> anyhow::Result<PathBuf> {
         })?;
     }
 
-    fs::rename(&current_exe, &old_exe)
+    fs::rename(current_exe, &old_exe)
         .map_err(|e| anyhow::anyhow!("failed to rename current binary: {}", e))?;
 
-    if let Err(e) = fs::copy(new_exe, &current_exe) {
+    if let Err(e) = fs::copy(new_exe, current_exe) {
         // rollback
         eprintln!("rmx: install failed, rolling back...");
-        let _ = fs::rename(&old_exe, &current_exe);
+        let _ = fs::rename(&old_exe, current_exe);
         return Err(anyhow::anyhow!("failed to install new binary: {}", e));
     }
 
-    Ok(current_exe)
+    Ok(current_exe.to_path_buf())
 }
 
 /// Parse a git-describe version like "0.2.8-3-g20679e1" into (base_version, commits_ahead).
 /// Pure semver like "0.2.8" returns (version, 0). Returns None if unparseable.
 fn parse_git_describe_version(version: &str) -> Option<(semver::Version, u64)> {
-    if let Ok(v) = semver::Version::parse(version) {
-        return Some((v, 0));
-    }
-
-    // git describe format: "0.2.8-3-g20679e1" → base="0.2.8", ahead=3
+    // git describe format: "0.2.8-3-g20679e1" → base="0.2.8", ahead=3.
+    // Checked first because it is also valid semver (with a prerelease).
     let parts: Vec<&str> = version.rsplitn(3, '-').collect();
-    if parts.len() == 3 {
-        let base = parts[2];
-        let ahead: u64 = parts[1].parse().ok()?;
-        let v = semver::Version::parse(base).ok()?;
-        return Some((v, ahead));
+    if parts.len() == 3 && parts[0].starts_with('g') {
+        if let (Ok(ahead), Ok(v)) = (parts[1].parse(), semver::Version::parse(parts[2])) {
+            return Some((v, ahead));
+        }
     }
 
-    None
+    semver::Version::parse(version).ok().map(|v| (v, 0))
 }
 
 fn is_up_to_date(current: &str, latest: &str) -> bool {
@@ -275,6 +633,18 @@ fn is_up_to_date(current: &str, latest: &str) -> bool {
     }
 }
 
+/// Whether installing `target` would go back from `current`. A git-describe
+/// build ahead of its tag is newer than that tag.
+fn is_older(target: &str, current: &str) -> bool {
+    let Ok(target_ver) = semver::Version::parse(target) else {
+        return false;
+    };
+    match parse_git_describe_version(current) {
+        Some((base, ahead)) => (target_ver, 0) < (base, ahead),
+        None => false,
+    }
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -290,3 +660,124 @@ fn format_size(bytes: u64) -> String {
         format!("{} B", bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_exe_path_reports_failure() {
+        let err =
+            resolve_exe_path(Err(io::Error::new(io::ErrorKind::NotFound, "gone"))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err
+            .to_string()
+            .contains("cannot determine rmx executable path"));
+
+        assert!(resolve_exe_path(Ok(PathBuf::new())).is_err());
+        assert!(resolve_exe_path(Ok(PathBuf::from("rmx.exe"))).is_ok());
+    }
+
+    #[test]
+    fn test_is_older() {
+        assert!(is_older("0.2.8", "0.2.9"));
+        assert!(is_older("0.2.9", "0.2.9-3-g20679e1"));
+        assert!(!is_older("0.2.9", "0.2.9"));
+        assert!(!is_older("0.3.0-beta.1", "0.2.9"));
+        assert!(is_older("0.3.0-beta.1", "0.3.0"));
+        // Unknown versions never count as downgrades
+        assert!(!is_older("0.2.8", "20679e1"));
+        assert!(!is_older("latest", "0.2.9"));
+    }
+
+    #[test]
+    fn test_sha256_file_and_sidecar_parsing() {
+        let temp = env::temp_dir().join("rmx_sha256_test");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(&temp).unwrap();
+        let file = temp.join("abc.zip");
+        fs::write(&file, "abc").unwrap();
+
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(sha256_file(&file).unwrap(), expected);
+
+        let sidecar = format!("{}  abc.zip\n", expected.to_uppercase());
+        assert_eq!(parse_sha256(&sidecar).as_deref(), Some(expected));
+        assert_eq!(parse_sha256("deadbeef  abc.zip"), None);
+        assert_eq!(parse_sha256(""), None);
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_download_progress_line() {
+        let line = download_progress_line(512 * 1024, Some(1024 * 1024));
+        assert!(line.starts_with(&format!("[{}{}]", "#".repeat(15), "-".repeat(15))));
+        assert!(line.contains(" 50%"));
+        assert!(line.ends_with("512.0 KB / 1.0 MB"));
+        // Overshooting a wrong Content-Length never overflows the bar
+        assert!(download_progress_line(2048, Some(1024)).contains("100%"));
+        assert_eq!(download_progress_line(100, None).trim(), "100 B downloaded");
+    }
+
+    #[test]
+    fn test_proxy_override_and_no_proxy() {
+        assert!(build_agent(Some("not a proxy url")).is_err());
+
+        let agent = build_agent(Some("http://proxy.example:3128")).unwrap();
+        let proxy = proxy_for(&agent, GITHUB_API_URL).unwrap();
+        assert_eq!((proxy.host(), proxy.port()), ("proxy.example", 3128));
+
+        let err = request_error(
+            &agent,
+            GITHUB_API_URL,
+            "failed",
+            ureq::Error::ConnectionFailed,
+        );
+        assert!(err.to_string().contains("through proxy proxy.example:3128"));
+    }
+
+    #[test]
+    fn test_cleanup_old_binary_skips_unknown_exe() {
+        let temp = env::temp_dir().join("rmx_cleanup_old_test");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(&temp).unwrap();
+        let exe = temp.join("rmx.exe");
+        let old = old_path(&exe);
+        fs::write(&old, "old").unwrap();
+
+        cleanup_old_binary_for(None);
+        assert!(old.exists());
+
+        cleanup_old_binary_for(Some(&exe));
+        assert!(!old.exists());
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_rollback_swaps_with_old_binary() {
+        let temp = env::temp_dir().join("rmx_rollback_test");
+        let _ = fs::remove_dir_all(&temp);
+        fs::create_dir_all(&temp).unwrap();
+        let exe = temp.join("rmx.exe");
+        let old = old_path(&exe);
+        fs::write(&exe, "new").unwrap();
+        assert!(rollback_for(&exe).is_err());
+
+        fs::write(&old, "old").unwrap();
+        // A rollback in progress keeps cleanup away from `.old`
+        fs::write(rollback_marker_path(&exe), "").unwrap();
+        cleanup_old_binary_for(Some(&exe));
+        assert!(old.exists());
+        assert!(rollback_for(&exe).is_err());
+        fs::remove_file(rollback_marker_path(&exe)).unwrap();
+
+        rollback_for(&exe).unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&old).unwrap(), "new");
+        assert!(!rollback_marker_path(&exe).exists());
+
+        let _ = fs::remove_dir_all(&temp);
+    }
+}
@@ -2,7 +2,7 @@ use std::ffi::c_void;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 #[cfg(windows)]
 use windows::core::PCWSTR;
@@ -22,15 +22,22 @@ use windows::Win32::Foundation::{
     STATUS_INFO_LENGTH_MISMATCH,
 };
 #[cfg(windows)]
+use windows::Win32::Foundation::{BOOL, BOOLEAN, HWND, LPARAM, WAIT_OBJECT_0, WPARAM};
+#[cfg(windows)]
 use windows::Win32::Foundation::{ERROR_MORE_DATA, WIN32_ERROR};
 #[cfg(windows)]
 use windows::Win32::Storage::FileSystem::{
-    CreateFileW, FileDispositionInfoEx, FindClose, FindFirstFileExW, FindNextFileW,
-    GetFileAttributesW, GetFinalPathNameByHandleW, SetFileInformationByHandle, DELETE,
-    FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_REPARSE_POINT, FILE_FLAG_BACKUP_SEMANTICS,
-    FILE_FLAG_OPEN_REPARSE_POINT, FILE_NAME_NORMALIZED, FILE_SHARE_DELETE, FILE_SHARE_READ,
-    FILE_SHARE_WRITE, FINDEX_INFO_LEVELS, FINDEX_SEARCH_OPS, FIND_FIRST_EX_FLAGS,
-    INVALID_FILE_ATTRIBUTES, OPEN_EXISTING, WIN32_FIND_DATAW,
+    CreateFileW, FileDispositionInfo, FileDispositionInfoEx, FileStandardInfo, FindClose,
+    FindFirstFileExW, FindFirstStreamW, FindNextFileW, FindNextStreamW, FindStreamInfoStandard,
+    GetFileAttributesExW, GetFileAttributesW, GetFileExInfoStandard, GetFileInformationByHandle,
+    GetFileInformationByHandleEx, GetFinalPathNameByHandleW, SetFileAttributesW,
+    SetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, DELETE, FILE_ATTRIBUTE_DIRECTORY,
+    FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_READONLY, FILE_ATTRIBUTE_REPARSE_POINT,
+    FILE_DISPOSITION_INFO, FILE_FLAGS_AND_ATTRIBUTES, FILE_FLAG_BACKUP_SEMANTICS,
+    FILE_FLAG_OPEN_NO_RECALL, FILE_FLAG_OPEN_REPARSE_POINT, FILE_NAME_NORMALIZED,
+    FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, FILE_STANDARD_INFO, FINDEX_INFO_LEVELS,
+    FINDEX_SEARCH_OPS, FIND_FIRST_EX_FLAGS, INVALID_FILE_ATTRIBUTES, OPEN_EXISTING,
+    WIN32_FILE_ATTRIBUTE_DATA, WIN32_FIND_DATAW, WIN32_FIND_STREAM_DATA,
 };
 #[cfg(windows)]
 use windows::Win32::System::RestartManager::{
@@ -39,13 +46,96 @@ use windows::Win32::System::RestartManager::{
 };
 #[cfg(windows)]
 use windows::Win32::System::Threading::{
-    GetCurrentProcess, OpenProcess, TerminateProcess, PROCESS_DUP_HANDLE,
-    PROCESS_QUERY_INFORMATION, PROCESS_TERMINATE,
+    GetCurrentProcess, OpenProcess, TerminateProcess, WaitForSingleObject, PROCESS_DUP_HANDLE,
+    PROCESS_QUERY_INFORMATION, PROCESS_SYNCHRONIZE, PROCESS_TERMINATE,
+};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetWindowThreadProcessId, PostMessageW, WM_CLOSE,
 };
 
-const MAX_RETRIES: u32 = 4;
+/// Delay before each retry; the last one repeats for further retries
 const RETRY_DELAYS_MS: [u64; 4] = [0, 1, 5, 10];
 
+/// SMB redirectors report sharing violations for a while after the last
+/// remote handle closes, so network paths back off much further.
+const NETWORK_RETRY_DELAYS_MS: [u64; 5] = [0, 10, 50, 100, 250];
+const NETWORK_MAX_RETRIES: u32 = 8;
+
+/// How `delete_file_with` / `remove_dir_with` retry sharing and lock
+/// violations (antivirus scanners, indexers, slow network redirectors).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 fails on the first error
+    pub max_retries: u32,
+    /// Delay in milliseconds before retry 1, 2, ...; the last entry repeats
+    pub delays_ms: Vec<u64>,
+    /// Plain delete-on-close disposition instead of POSIX semantics, which
+    /// SMB servers don't reliably honor
+    pub network: bool,
+}
+
+impl RetryPolicy {
+    /// Slower, safer policy for UNC paths and mapped network drives
+    pub fn network() -> Self {
+        Self {
+            max_retries: NETWORK_MAX_RETRIES,
+            delays_ms: NETWORK_RETRY_DELAYS_MS.to_vec(),
+            network: true,
+        }
+    }
+
+    /// [`RetryPolicy::network`] if `path` is on a network share, the default otherwise
+    pub fn for_path(path: &Path) -> Self {
+        if is_network_path(path) {
+            Self::network()
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Delay before retry number `retry` (1-based)
+    pub fn delay_before(&self, retry: u32) -> Duration {
+        let i = (retry.max(1) as usize - 1).min(self.delays_ms.len().saturating_sub(1));
+        Duration::from_millis(self.delays_ms.get(i).copied().unwrap_or(0))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            delays_ms: RETRY_DELAYS_MS.to_vec(),
+            network: false,
+        }
+    }
+}
+
+/// Run `op` until it succeeds, fails with an error `retryable` rejects, or
+/// `policy` runs out of retries. Returns the last error.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn with_retries<F, R>(policy: &RetryPolicy, retryable: R, mut op: F) -> io::Result<()>
+where
+    F: FnMut() -> io::Result<()>,
+    R: Fn(&io::Error) -> bool,
+{
+    let mut last_error = None;
+    for attempt in 0..=policy.max_retries {
+        if attempt > 0 {
+            let delay = policy.delay_before(attempt);
+            if !delay.is_zero() {
+                thread::sleep(delay);
+            }
+        }
+        match op() {
+            Ok(()) => return Ok(()),
+            Err(e) if retryable(&e) => last_error = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::other("max retries exceeded")))
+}
+
 /// POSIX delete on hardlinked files (pnpm node_modules) can return Ok() while
 /// NTFS directory entry removal is still pending. Passive retry isn't enough —
 /// we must actively re-enumerate and re-delete remaining entries.
@@ -128,131 +218,661 @@ pub fn is_directory(path: &Path) -> bool {
     path.is_dir()
 }
 
+/// Combined existence/type/size information for a single path
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathStat {
+    pub exists: bool,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Stat a path in a single call without following reparse points.
+///
+/// Falls back to `FindFirstFileExW` when attribute queries are denied
+/// (e.g. files opened exclusively such as `pagefile.sys`).
+#[cfg(windows)]
+pub fn stat_path(path: &Path) -> PathStat {
+    let wide_path = path_to_wide(path);
+    unsafe {
+        let mut data: WIN32_FILE_ATTRIBUTE_DATA = std::mem::zeroed();
+        if GetFileAttributesExW(
+            PCWSTR(wide_path.as_ptr()),
+            GetFileExInfoStandard,
+            &mut data as *mut _ as *mut c_void,
+        )
+        .is_ok()
+        {
+            // Only the directory listing carries the reparse tag
+            let reparse_tag = if data.dwFileAttributes & FILE_ATTRIBUTE_REPARSE_POINT.0 != 0 {
+                find_reparse_tag(&wide_path)
+            } else {
+                None
+            };
+            return attributes_to_stat(
+                data.dwFileAttributes,
+                reparse_tag,
+                data.nFileSizeHigh,
+                data.nFileSizeLow,
+                filetime_to_system_time(
+                    data.ftLastWriteTime.dwHighDateTime,
+                    data.ftLastWriteTime.dwLowDateTime,
+                ),
+            );
+        }
+
+        let mut find_data: WIN32_FIND_DATAW = std::mem::zeroed();
+        match FindFirstFileExW(
+            PCWSTR(wide_path.as_ptr()),
+            FINDEX_INFO_LEVELS(1),
+            &mut find_data as *mut _ as *mut _,
+            FINDEX_SEARCH_OPS(0),
+            None,
+            FIND_FIRST_EX_FLAGS(0),
+        ) {
+            Ok(handle) => {
+                let _ = FindClose(handle);
+                attributes_to_stat(
+                    find_data.dwFileAttributes,
+                    Some(find_data.dwReserved0),
+                    find_data.nFileSizeHigh,
+                    find_data.nFileSizeLow,
+                    filetime_to_system_time(
+                        find_data.ftLastWriteTime.dwHighDateTime,
+                        find_data.ftLastWriteTime.dwLowDateTime,
+                    ),
+                )
+            }
+            Err(_) => PathStat::default(),
+        }
+    }
+}
+
+/// `dwReserved0` of the path's own directory entry, which holds the reparse
+/// tag of reparse points
+#[cfg(windows)]
+unsafe fn find_reparse_tag(wide_path: &[u16]) -> Option<u32> {
+    let mut find_data: WIN32_FIND_DATAW = std::mem::zeroed();
+    let handle = FindFirstFileExW(
+        PCWSTR(wide_path.as_ptr()),
+        FINDEX_INFO_LEVELS(1),
+        &mut find_data as *mut _ as *mut _,
+        FINDEX_SEARCH_OPS(0),
+        None,
+        FIND_FIRST_EX_FLAGS(0),
+    )
+    .ok()?;
+    let _ = FindClose(handle);
+    Some(find_data.dwReserved0)
+}
+
+#[cfg(windows)]
+fn attributes_to_stat(
+    attrs: u32,
+    reparse_tag: Option<u32>,
+    size_high: u32,
+    size_low: u32,
+    modified: Option<SystemTime>,
+) -> PathStat {
+    let is_dir = (attrs & FILE_ATTRIBUTE_DIRECTORY.0) != 0;
+    PathStat {
+        exists: true,
+        is_dir,
+        is_symlink: (attrs & FILE_ATTRIBUTE_REPARSE_POINT.0) != 0
+            && !reparse_tag.is_some_and(is_cloud_reparse_tag),
+        size: if is_dir {
+            0
+        } else {
+            ((size_high as u64) << 32) | (size_low as u64)
+        },
+        modified,
+    }
+}
+
+#[cfg(not(windows))]
+pub fn stat_path(path: &Path) -> PathStat {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) => {
+            let is_symlink = meta.file_type().is_symlink();
+            // Report symlinked directories as directories, like Windows reparse points
+            let is_dir = meta.is_dir() || (is_symlink && path.is_dir());
+            PathStat {
+                exists: true,
+                is_dir,
+                is_symlink,
+                size: if is_dir || is_symlink { 0 } else { meta.len() },
+                modified: meta.modified().ok(),
+            }
+        }
+        Err(_) => PathStat::default(),
+    }
+}
+
+/// Physical identity of a file, used to detect hard links
+#[derive(Debug, Clone, Copy)]
+pub struct FileLinkInfo {
+    pub volume: u64,
+    pub file_id: u64,
+    /// Number of directory entries (hard links) pointing at the file data
+    pub links: u32,
+    pub size: u64,
+}
+
+/// Query link count and file ID without following reparse points.
+#[cfg(windows)]
+pub fn file_link_info(path: &Path) -> io::Result<FileLinkInfo> {
+    const FILE_READ_ATTRIBUTES: u32 = 0x80;
+    let wide_path = path_to_wide(path);
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR(wide_path.as_ptr()),
+            FILE_READ_ATTRIBUTES,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT,
+            HANDLE::default(),
+        )
+        .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+        let result = GetFileInformationByHandle(handle, &mut info);
+        CloseHandle(handle).ok();
+        result.map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+        Ok(FileLinkInfo {
+            volume: info.dwVolumeSerialNumber as u64,
+            file_id: ((info.nFileIndexHigh as u64) << 32) | (info.nFileIndexLow as u64),
+            links: info.nNumberOfLinks,
+            size: ((info.nFileSizeHigh as u64) << 32) | (info.nFileSizeLow as u64),
+        })
+    }
+}
+
+#[cfg(unix)]
+pub fn file_link_info(path: &Path) -> io::Result<FileLinkInfo> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::symlink_metadata(path)?;
+    Ok(FileLinkInfo {
+        volume: meta.dev(),
+        file_id: meta.ino(),
+        links: meta.nlink() as u32,
+        size: meta.len(),
+    })
+}
+
+#[cfg(not(any(windows, unix)))]
+pub fn file_link_info(path: &Path) -> io::Result<FileLinkInfo> {
+    let meta = std::fs::symlink_metadata(path)?;
+    Ok(FileLinkInfo {
+        volume: 0,
+        file_id: 0,
+        links: 1,
+        size: meta.len(),
+    })
+}
+
+/// Serial number of the volume `path` resolves to. Junctions, mount points
+/// and symlinks are followed, so a mount point reports the mounted volume.
+#[cfg(windows)]
+pub fn volume_serial(path: &Path) -> io::Result<u64> {
+    const FILE_READ_ATTRIBUTES: u32 = 0x80;
+    let wide_path = path_to_wide(path);
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR(wide_path.as_ptr()),
+            FILE_READ_ATTRIBUTES,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS,
+            HANDLE::default(),
+        )
+        .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+        let result = GetFileInformationByHandle(handle, &mut info);
+        CloseHandle(handle).ok();
+        result.map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+        Ok(info.dwVolumeSerialNumber as u64)
+    }
+}
+
+#[cfg(unix)]
+pub fn volume_serial(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(std::fs::metadata(path)?.dev())
+}
+
+#[cfg(not(any(windows, unix)))]
+pub fn volume_serial(path: &Path) -> io::Result<u64> {
+    std::fs::metadata(path).map(|_| 0)
+}
+
+/// Bytes actually allocated on disk for `path` (cluster rounding, sparse and
+/// compressed files), without following reparse points.
+#[cfg(windows)]
+pub fn allocated_size(path: &Path) -> io::Result<u64> {
+    const FILE_READ_ATTRIBUTES: u32 = 0x80;
+    let wide_path = path_to_wide(path);
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR(wide_path.as_ptr()),
+            FILE_READ_ATTRIBUTES,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT,
+            HANDLE::default(),
+        )
+        .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+        let mut info = FILE_STANDARD_INFO::default();
+        let result = GetFileInformationByHandleEx(
+            handle,
+            FileStandardInfo,
+            &mut info as *mut _ as *mut c_void,
+            std::mem::size_of::<FILE_STANDARD_INFO>() as u32,
+        );
+        CloseHandle(handle).ok();
+        result.map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+        Ok(info.AllocationSize.max(0) as u64)
+    }
+}
+
+#[cfg(unix)]
+pub fn allocated_size(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(std::fs::symlink_metadata(path)?.blocks() * 512)
+}
+
+#[cfg(not(any(windows, unix)))]
+pub fn allocated_size(path: &Path) -> io::Result<u64> {
+    Ok(std::fs::symlink_metadata(path)?.len())
+}
+
+/// Write size of one `overwrite_file` chunk
+const OVERWRITE_CHUNK: u64 = 1024 * 1024;
+
+/// Overwrite a file's contents in place with `passes` rounds of
+/// pseudo-random bytes, flushing each pass to disk, ahead of deletion.
+///
+/// Returns `Ok(false)` without writing where overwriting is meaningless or
+/// harmful: symlinks and other non-regular entries, hard-linked files (the
+/// data is shared with other names), and sparse or compressed files.
+/// Read-only files are made writable first; they are about to be deleted.
+pub fn overwrite_file(path: &Path, passes: u8) -> io::Result<bool> {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let meta = std::fs::symlink_metadata(path)?;
+    if is_cloud_placeholder(&meta) {
+        // Opening it for writing would download it first
+        return Ok(false);
+    }
+    if !meta.file_type().is_file() || is_sparse_or_compressed(&meta) {
+        return Ok(false);
+    }
+    if file_link_info(path)?.links > 1 {
+        return Ok(false);
+    }
+
+    if meta.permissions().readonly() {
+        make_writable(path, &meta)?;
+    }
+
+    let len = meta.len();
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let mut buf = vec![0u8; len.min(OVERWRITE_CHUNK) as usize];
+    let mut state = random_seed();
+    for _ in 0..passes {
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            let n = remaining.min(buf.len() as u64) as usize;
+            fill_random(&mut buf[..n], &mut state);
+            file.write_all(&buf[..n])?;
+            remaining -= n as u64;
+        }
+        file.sync_all()?;
+    }
+    Ok(true)
+}
+
+#[cfg(windows)]
+fn make_writable(path: &Path, _meta: &std::fs::Metadata) -> io::Result<()> {
+    let wide_path = checked_path_to_wide(path)?;
+    unsafe {
+        let attrs = GetFileAttributesW(PCWSTR(wide_path.as_ptr()));
+        if attrs == INVALID_FILE_ATTRIBUTES {
+            return Err(io::Error::last_os_error());
+        }
+        SetFileAttributesW(
+            PCWSTR(wide_path.as_ptr()),
+            FILE_FLAGS_AND_ATTRIBUTES(attrs & !FILE_ATTRIBUTE_READONLY.0),
+        )
+        .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))
+    }
+}
+
+/// Owner write only; group and other permissions are left as they were
+#[cfg(unix)]
+fn make_writable(path: &Path, meta: &std::fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = meta.permissions().mode() | 0o200;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(any(windows, unix)))]
+fn make_writable(_path: &Path, _meta: &std::fs::Metadata) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::PermissionDenied))
+}
+
+#[cfg(windows)]
+fn is_sparse_or_compressed(meta: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_SPARSE_FILE: u32 = 0x200;
+    const FILE_ATTRIBUTE_COMPRESSED: u32 = 0x800;
+    meta.file_attributes() & (FILE_ATTRIBUTE_SPARSE_FILE | FILE_ATTRIBUTE_COMPRESSED) != 0
+}
+
+#[cfg(windows)]
+fn is_cloud_placeholder(meta: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    meta.file_attributes() & (FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN)
+        != 0
+}
+
+#[cfg(not(windows))]
+fn is_cloud_placeholder(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn is_sparse_or_compressed(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // Small files stored inline in the inode report no blocks at all, so
+    // only count real holes
+    const HOLE_SLACK: u64 = 64 * 1024;
+    meta.blocks() * 512 + HOLE_SLACK < meta.len()
+}
+
+fn random_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    // xorshift never leaves zero
+    hasher.finish() | 1
+}
+
+/// xorshift64; not cryptographic, only needs to not be the original data
+fn fill_random(buf: &mut [u8], state: &mut u64) {
+    for chunk in buf.chunks_mut(8) {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        chunk.copy_from_slice(&state.to_le_bytes()[..chunk.len()]);
+    }
+}
+
 #[cfg(windows)]
 fn path_to_wide(path: &Path) -> Vec<u16> {
+    let mut wide = Vec::new();
+    path_to_wide_into(path, &mut wide);
+    wide
+}
+
+/// [`path_to_wide`] into `wide`, reusing its allocation
+#[cfg(windows)]
+fn path_to_wide_into(path: &Path, wide: &mut Vec<u16>) {
     let path_str = path.to_string_lossy();
+    if needs_full_path(&path_str) {
+        // GetFullPathNameW: resolves against the CWD and collapses `.`/`..`
+        if let Ok(full) = std::path::absolute(path) {
+            return normalize_path_wide(&full.to_string_lossy(), wide);
+        }
+    }
+    normalize_path_wide(&path_str, wide)
+}
 
-    // Check if already has \\?\ prefix
-    let has_prefix = path_str.starts_with(r"\\?\");
+/// Longest path Windows can address, `\\?\` prefix included, in UTF-16
+/// code units (`UNICODE_STRING` lengths are 16-bit byte counts)
+pub const MAX_WIDE_PATH: usize = 32_767;
 
-    // Check if path is absolute (C:\ or C:/)
-    let is_absolute = has_prefix || {
-        let bytes = path_str.as_bytes();
-        bytes.len() >= 3
-            && bytes[0].is_ascii_alphabetic()
-            && bytes[1] == b':'
-            && (bytes[2] == b'\\' || bytes[2] == b'/')
-    };
+/// [`path_to_wide`] for the delete and enumerate calls: a path too long for
+/// any Win32 call fails up front with a [`PathTooLongError`] naming its
+/// length instead of an opaque OS error.
+///
+/// [`PathTooLongError`]: crate::error::PathTooLongError
+#[cfg(windows)]
+fn checked_path_to_wide(path: &Path) -> io::Result<Vec<u16>> {
+    let wide = path_to_wide(path);
+    // Minus the terminating NUL
+    check_wide_len(wide.len() - 1)?;
+    Ok(wide)
+}
 
-    let needs_prefix = is_absolute && !has_prefix;
+/// Run `f` on the [`checked_path_to_wide`] form of `path`, encoded into a
+/// per-thread buffer instead of a fresh allocation per entry. Nested calls
+/// (directory cleanup deletes leftovers while its own path is in use) fall
+/// back to allocating.
+#[cfg(windows)]
+fn with_checked_wide<T>(path: &Path, f: impl FnOnce(&[u16]) -> io::Result<T>) -> io::Result<T> {
+    use std::cell::RefCell;
+    thread_local! {
+        static WIDE_BUFFER: RefCell<Vec<u16>> = const { RefCell::new(Vec::new()) };
+    }
+    WIDE_BUFFER.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut wide) => {
+            path_to_wide_into(path, &mut wide);
+            check_wide_len(wide.len() - 1)?;
+            f(&wide)
+        }
+        Err(_) => f(&checked_path_to_wide(path)?),
+    })
+}
 
-    // Pre-allocate: path length + optional \\?\ prefix (4 chars) + null terminator
-    let capacity = path_str.len() + if needs_prefix { 5 } else { 1 };
-    let mut wide = Vec::with_capacity(capacity);
+#[cfg_attr(not(windows), allow(dead_code))]
+fn check_wide_len(len: usize) -> io::Result<()> {
+    if len > MAX_WIDE_PATH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidFilename,
+            crate::error::PathTooLongError {
+                len,
+                max: MAX_WIDE_PATH,
+            },
+        ));
+    }
+    Ok(())
+}
 
-    if needs_prefix {
-        wide.extend_from_slice(&[0x5C, 0x5C, 0x3F, 0x5C]); // \\?\
+/// `\\?\` turns off Win32 path normalization, so relative paths can't get
+/// the prefix and `.`/`..` components would reach the file system verbatim.
+/// Such paths are made absolute first. Already-prefixed and device paths
+/// are passed through as given.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn needs_full_path(path_str: &str) -> bool {
+    let is_sep = |c: char| c == '\\' || c == '/';
+    if path_str.starts_with(r"\\?\") || path_str.starts_with(r"\\.\") {
+        return false;
     }
+    let absolute = !long_path_prefix(path_str).0.is_empty();
+    !absolute || path_str.split(is_sep).any(|c| c == "." || c == "..")
+}
+
+/// The same form [`path_to_wide`] hands to the Win32 API, as a string:
+/// `/` becomes `\` and absolute paths get the long-path prefix.
+pub fn normalize_path_str(path_str: &str) -> String {
+    let (prefix, rest) = long_path_prefix(path_str);
+    let mut normalized = String::with_capacity(prefix.len() + rest.len());
+    normalized.push_str(prefix);
+    normalized.extend(rest.chars().map(|c| if c == '/' { '\\' } else { c }));
+    normalized
+}
+
+/// Absolute paths get the long-path prefix: `C:\x` becomes `\\?\C:\x` and
+/// `\\server\share\x` becomes `\\?\UNC\server\share\x`. Relative paths,
+/// device paths (`\\.\`) and already-prefixed paths are left alone.
+/// Returns the prefix to add and the remainder of `path_str`.
+fn long_path_prefix(path_str: &str) -> (&'static str, &str) {
+    let bytes = path_str.as_bytes();
+    let is_sep = |b: u8| b == b'\\' || b == b'/';
+
+    // Check if already has \\?\ prefix
+    let has_prefix = path_str.starts_with(r"\\?\");
 
-    // Encode to UTF-16 in a single pass, normalizing '/' to '\' inline.
-    // Avoids the intermediate String allocation from replace('/','\\').
-    for c in path_str.encode_utf16() {
-        wide.push(if c == 0x2F { 0x5C } else { c });
+    // C:\ or C:/
+    let is_drive_absolute =
+        bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && is_sep(bytes[2]);
+
+    // \\server\share, but not \\?\ or \\.\ (in either slash direction)
+    let is_device = bytes.len() >= 4 && matches!(bytes[2], b'?' | b'.') && is_sep(bytes[3]);
+    let is_unc = !has_prefix
+        && bytes.len() >= 3
+        && is_sep(bytes[0])
+        && is_sep(bytes[1])
+        && !is_sep(bytes[2])
+        && !is_device;
+
+    if has_prefix {
+        ("", path_str)
+    } else if is_drive_absolute {
+        (r"\\?\", path_str)
+    } else if is_unc {
+        (r"\\?\UNC\", &path_str[2..])
+    } else {
+        ("", path_str)
     }
-    wide.push(0);
+}
+
+/// Null-terminated UTF-16 for the Win32 API, with `/` normalized to `\`
+/// and the long-path prefix from [`long_path_prefix`].
+#[cfg_attr(not(windows), allow(dead_code))]
+fn str_to_wide(path_str: &str) -> Vec<u16> {
+    let mut wide = Vec::new();
+    normalize_path_wide(path_str, &mut wide);
     wide
 }
 
+/// [`normalize_path_str`] as null-terminated UTF-16, written over `wide` so
+/// callers converting many paths can reuse one buffer.
+pub fn normalize_path_wide(path_str: &str, wide: &mut Vec<u16>) {
+    let (prefix, rest) = long_path_prefix(path_str);
+
+    wide.clear();
+    // Path length + prefix + null terminator
+    wide.reserve(prefix.len() + rest.len() + 1);
+    wide.extend(prefix.encode_utf16());
+
+    // ASCII (nearly every build tree) widens byte by byte, which the
+    // compiler vectorizes; '/' is only looked for if the path has one
+    let has_slash = rest.contains('/');
+    if rest.is_ascii() {
+        if has_slash {
+            wide.extend(
+                rest.bytes()
+                    .map(|b| if b == b'/' { 0x5C } else { b as u16 }),
+            );
+        } else {
+            wide.extend(rest.bytes().map(u16::from));
+        }
+    } else if has_slash {
+        wide.extend(
+            rest.encode_utf16()
+                .map(|c| if c == 0x2F { 0x5C } else { c }),
+        );
+    } else {
+        wide.extend(rest.encode_utf16());
+    }
+    wide.push(0);
+}
+
 #[cfg(windows)]
-fn is_retryable_error(code: i32) -> bool {
+fn is_retryable_error(error: &io::Error) -> bool {
     const ERROR_SHARING_VIOLATION: i32 = 32;
     const ERROR_LOCK_VIOLATION: i32 = 33;
     const ERROR_ACCESS_DENIED: i32 = 5;
     const ERROR_DIR_NOT_EMPTY: i32 = 145;
 
     matches!(
-        code,
+        error.raw_os_error().unwrap_or(0),
         ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION | ERROR_ACCESS_DENIED | ERROR_DIR_NOT_EMPTY
     )
 }
 
 #[cfg(windows)]
 pub fn delete_file(path: &Path) -> io::Result<()> {
-    let wide_path = path_to_wide(path);
-    let mut last_error = None;
+    delete_file_with(path, &RetryPolicy::default())
+}
 
-    for (i, &delay_ms) in RETRY_DELAYS_MS
-        .iter()
-        .enumerate()
-        .take(MAX_RETRIES as usize)
-    {
-        match unsafe { posix_delete_file(&wide_path) } {
-            Ok(()) => return Ok(()),
-            Err(e) => {
-                if !is_retryable_error(e.raw_os_error().unwrap_or(0)) {
-                    return Err(e);
-                }
-                last_error = Some(e);
-                if i < MAX_RETRIES as usize - 1 && delay_ms > 0 {
-                    thread::sleep(Duration::from_millis(delay_ms));
-                }
-            }
+#[cfg(windows)]
+pub fn delete_file_with(path: &Path, policy: &RetryPolicy) -> io::Result<()> {
+    with_checked_wide(path, |wide_path| {
+        if policy.network {
+            return with_retries(policy, is_retryable_error, || unsafe {
+                network_delete(wide_path, false)
+            });
         }
-    }
-
-    Err(last_error.unwrap_or_else(|| io::Error::other("max retries exceeded")))
+        with_retries(policy, is_retryable_error, || unsafe {
+            posix_delete_file(wide_path)
+        })
+    })
 }
 
 #[cfg(windows)]
 pub fn remove_dir(path: &Path) -> io::Result<()> {
-    let wide_path = path_to_wide(path);
-    let mut last_error = None;
+    remove_dir_with(path, &RetryPolicy::default())
+}
+
+#[cfg(windows)]
+pub fn remove_dir_with(path: &Path, policy: &RetryPolicy) -> io::Result<()> {
+    with_checked_wide(path, |wide_path| remove_dir_wide(path, wide_path, policy))
+}
+
+#[cfg(windows)]
+fn remove_dir_wide(path: &Path, wide_path: &[u16], policy: &RetryPolicy) -> io::Result<()> {
+    if policy.network {
+        // No cleanup rounds: without POSIX semantics a non-empty directory
+        // really is non-empty, not waiting on pending hardlink removals
+        return with_retries(policy, is_retryable_error, || unsafe {
+            network_delete(wide_path, true)
+        });
+    }
+    let mut last_error = match with_retries(policy, is_retryable_error, || unsafe {
+        posix_delete_dir(wide_path)
+    }) {
+        Ok(()) => return Ok(()),
+        Err(e) if is_dir_not_empty_error(&e) => e,
+        Err(e) => return Err(e),
+    };
 
-    for (i, &delay_ms) in RETRY_DELAYS_MS
+    for &delay in DIR_NOT_EMPTY_CLEANUP_DELAYS_MS
         .iter()
-        .enumerate()
-        .take(MAX_RETRIES as usize)
+        .take(DIR_NOT_EMPTY_CLEANUP_ROUNDS)
     {
-        match unsafe { posix_delete_dir(&wide_path) } {
+        thread::sleep(Duration::from_millis(delay));
+
+        cleanup_remaining_entries(path);
+
+        match unsafe { posix_delete_dir(wide_path) } {
             Ok(()) => return Ok(()),
             Err(e) => {
-                if !is_retryable_error(e.raw_os_error().unwrap_or(0)) {
+                if !is_dir_not_empty_error(&e) && !is_retryable_error(&e) {
                     return Err(e);
                 }
-                last_error = Some(e);
-                if i < MAX_RETRIES as usize - 1 && delay_ms > 0 {
-                    thread::sleep(Duration::from_millis(delay_ms));
-                }
+                last_error = e;
             }
         }
     }
 
-    if let Some(ref e) = last_error {
-        if is_dir_not_empty_error(e) {
-            for &delay in DIR_NOT_EMPTY_CLEANUP_DELAYS_MS
-                .iter()
-                .take(DIR_NOT_EMPTY_CLEANUP_ROUNDS)
-            {
-                thread::sleep(Duration::from_millis(delay));
-
-                cleanup_remaining_entries(path);
-
-                match unsafe { posix_delete_dir(&wide_path) } {
-                    Ok(()) => return Ok(()),
-                    Err(e) => {
-                        if !is_dir_not_empty_error(&e)
-                            && !is_retryable_error(e.raw_os_error().unwrap_or(0))
-                        {
-                            return Err(e);
-                        }
-                        last_error = Some(e);
-                    }
-                }
-            }
-        }
-    }
-
-    Err(last_error.unwrap_or_else(|| io::Error::other("max retries exceeded")))
+    Err(last_error)
 }
 
 #[cfg(windows)]
@@ -273,11 +893,46 @@ fn cleanup_remaining_entries(path: &Path) {
 unsafe fn posix_delete_file(wide_path: &[u16]) -> io::Result<()> {
     let handle = CreateFileW(
         PCWSTR(wide_path.as_ptr()),
-        DELETE.0 | 0x00100000, // DELETE | SYNCHRONIZE: faster kernel code path per llfio research
+        DELETE.0 | 0x00100000, // DELETE | SYNCHRONIZE: faster kernel code path per llfio research
+        FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+        None,
+        OPEN_EXISTING,
+        // NO_RECALL: never download a cloud placeholder just to delete it
+        FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_OPEN_NO_RECALL,
+        HANDLE::default(),
+    )
+    .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+    let mut info = FILE_DISPOSITION_INFORMATION_EX {
+        Flags: FILE_DISPOSITION_INFORMATION_EX_FLAGS(
+            FILE_DISPOSITION_DELETE.0
+                | FILE_DISPOSITION_POSIX_SEMANTICS.0
+                | FILE_DISPOSITION_IGNORE_READONLY_ATTRIBUTE.0
+                | FILE_DISPOSITION_FORCE_IMAGE_SECTION_CHECK.0,
+        ),
+    };
+
+    let result = SetFileInformationByHandle(
+        handle,
+        FileDispositionInfoEx,
+        &mut info as *mut _ as *mut _,
+        std::mem::size_of::<FILE_DISPOSITION_INFORMATION_EX>() as u32,
+    );
+
+    CloseHandle(handle).ok();
+
+    result.map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))
+}
+
+#[cfg(windows)]
+unsafe fn posix_delete_dir(wide_path: &[u16]) -> io::Result<()> {
+    let handle = CreateFileW(
+        PCWSTR(wide_path.as_ptr()),
+        DELETE.0 | 0x00100000, // DELETE | SYNCHRONIZE
         FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
         None,
         OPEN_EXISTING,
-        FILE_FLAG_OPEN_REPARSE_POINT,
+        FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_OPEN_NO_RECALL,
         HANDLE::default(),
     )
     .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
@@ -303,33 +958,43 @@ unsafe fn posix_delete_file(wide_path: &[u16]) -> io::Result<()> {
     result.map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))
 }
 
+/// Classic `FileDispositionInfo` delete for network shares: the entry is
+/// removed when the last handle closes. Read-only entries have the
+/// attribute cleared first, since there is no IGNORE_READONLY flag here.
 #[cfg(windows)]
-unsafe fn posix_delete_dir(wide_path: &[u16]) -> io::Result<()> {
+unsafe fn network_delete(wide_path: &[u16], is_dir: bool) -> io::Result<()> {
+    let attrs = GetFileAttributesW(PCWSTR(wide_path.as_ptr()));
+    if attrs != INVALID_FILE_ATTRIBUTES && attrs & FILE_ATTRIBUTE_READONLY.0 != 0 {
+        let _ = SetFileAttributesW(
+            PCWSTR(wide_path.as_ptr()),
+            FILE_FLAGS_AND_ATTRIBUTES(attrs & !FILE_ATTRIBUTE_READONLY.0),
+        );
+    }
+
+    let flags = if is_dir {
+        FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT
+    } else {
+        FILE_FLAG_OPEN_REPARSE_POINT
+    };
     let handle = CreateFileW(
         PCWSTR(wide_path.as_ptr()),
         DELETE.0 | 0x00100000, // DELETE | SYNCHRONIZE
         FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
         None,
         OPEN_EXISTING,
-        FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT,
+        flags,
         HANDLE::default(),
     )
     .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
 
-    let mut info = FILE_DISPOSITION_INFORMATION_EX {
-        Flags: FILE_DISPOSITION_INFORMATION_EX_FLAGS(
-            FILE_DISPOSITION_DELETE.0
-                | FILE_DISPOSITION_POSIX_SEMANTICS.0
-                | FILE_DISPOSITION_IGNORE_READONLY_ATTRIBUTE.0
-                | FILE_DISPOSITION_FORCE_IMAGE_SECTION_CHECK.0,
-        ),
+    let mut info = FILE_DISPOSITION_INFO {
+        DeleteFile: BOOLEAN(1),
     };
-
     let result = SetFileInformationByHandle(
         handle,
-        FileDispositionInfoEx,
+        FileDispositionInfo,
         &mut info as *mut _ as *mut _,
-        std::mem::size_of::<FILE_DISPOSITION_INFORMATION_EX>() as u32,
+        std::mem::size_of::<FILE_DISPOSITION_INFO>() as u32,
     );
 
     CloseHandle(handle).ok();
@@ -337,22 +1002,617 @@ unsafe fn posix_delete_dir(wide_path: &[u16]) -> io::Result<()> {
     result.map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))
 }
 
+/// Open `path` with DELETE access and close it again without setting a
+/// disposition. Fails the same way a real delete would on locks or missing
+/// permissions, but never modifies anything.
+#[cfg(windows)]
+pub fn probe_deletable(path: &Path, is_dir: bool) -> io::Result<()> {
+    let wide_path = checked_path_to_wide(path)?;
+    let flags = if is_dir {
+        FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT
+    } else {
+        FILE_FLAG_OPEN_REPARSE_POINT
+    };
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR(wide_path.as_ptr()),
+            DELETE.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            flags,
+            HANDLE::default(),
+        )
+        .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+        CloseHandle(handle).ok();
+    }
+    Ok(())
+}
+
+/// Removing an entry needs write access to its parent directory.
+#[cfg(not(windows))]
+pub fn probe_deletable(path: &Path, _is_dir: bool) -> io::Result<()> {
+    std::fs::symlink_metadata(path)?;
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if std::fs::metadata(parent)?.permissions().readonly() {
+        return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+    }
+    Ok(())
+}
+
 #[cfg(not(windows))]
 pub fn delete_file(path: &Path) -> io::Result<()> {
     std::fs::remove_file(path)
 }
 
+#[cfg(not(windows))]
+pub fn delete_file_with(path: &Path, _policy: &RetryPolicy) -> io::Result<()> {
+    delete_file(path)
+}
+
 #[cfg(not(windows))]
 pub fn remove_dir(path: &Path) -> io::Result<()> {
     std::fs::remove_dir(path)
 }
 
+#[cfg(not(windows))]
+pub fn remove_dir_with(path: &Path, _policy: &RetryPolicy) -> io::Result<()> {
+    remove_dir(path)
+}
+
+/// Whether `path` lives on a local fixed volume, the only kind that reliably
+/// has a Recycle Bin (network shares, removable and optical media don't).
+#[cfg(windows)]
+pub fn has_recycle_bin(path: &Path) -> bool {
+    use windows::Win32::Storage::FileSystem::GetDriveTypeW;
+    const DRIVE_FIXED: u32 = 3;
+
+    let path_str = path.to_string_lossy();
+    let path_str = path_str.strip_prefix(r"\\?\").unwrap_or(&path_str);
+    let bytes = path_str.as_bytes();
+    if bytes.len() < 2 || !bytes[0].is_ascii_alphabetic() || bytes[1] != b':' {
+        // UNC paths and anything we can't map to a drive letter
+        return false;
+    }
+
+    let root: Vec<u16> = format!("{}:\\", bytes[0] as char)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe { GetDriveTypeW(PCWSTR(root.as_ptr())) == DRIVE_FIXED }
+}
+
+#[cfg(not(windows))]
+pub fn has_recycle_bin(_path: &Path) -> bool {
+    false
+}
+
+/// `\\server\share\...` and `\\?\UNC\...` paths
+fn is_unc_path(path: &str) -> bool {
+    if let Some(rest) = path.strip_prefix(r"\\?\") {
+        return rest.len() > 4
+            && rest
+                .get(..4)
+                .is_some_and(|p| p.eq_ignore_ascii_case(r"UNC\"));
+    }
+    path.starts_with(r"\\") && !path.starts_with(r"\\.\")
+}
+
+/// Whether `path` is a UNC path or lives on a mapped network drive.
+#[cfg(windows)]
+pub fn is_network_path(path: &Path) -> bool {
+    use windows::Win32::Storage::FileSystem::GetDriveTypeW;
+    const DRIVE_REMOTE: u32 = 4;
+
+    let path_str = path.to_string_lossy();
+    if is_unc_path(&path_str) {
+        return true;
+    }
+    let path_str = path_str.strip_prefix(r"\\?\").unwrap_or(&path_str);
+    let bytes = path_str.as_bytes();
+    if bytes.len() < 2 || !bytes[0].is_ascii_alphabetic() || bytes[1] != b':' {
+        return false;
+    }
+
+    let root: Vec<u16> = format!("{}:\\", bytes[0] as char)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe { GetDriveTypeW(PCWSTR(root.as_ptr())) == DRIVE_REMOTE }
+}
+
+#[cfg(not(windows))]
+pub fn is_network_path(path: &Path) -> bool {
+    is_unc_path(&path.to_string_lossy())
+}
+
+/// The shell's `IFileOperation` is limited to `MAX_PATH`.
+#[cfg(windows)]
+const SHELL_MAX_PATH: usize = 260;
+
+/// `path` as the shell wants it: no `\\?\` prefix, backslashes only.
+/// Fails for paths over [`SHELL_MAX_PATH`], naming `purpose` in the error.
+#[cfg(windows)]
+fn shell_wide_path(path: &Path, purpose: &str) -> io::Result<Vec<u16>> {
+    let path_str = path.to_string_lossy();
+    let path_str = path_str.strip_prefix(r"\\?\").unwrap_or(&path_str);
+    let wide: Vec<u16> = path_str
+        .encode_utf16()
+        .map(|c| if c == 0x2F { 0x5C } else { c })
+        .chain(std::iter::once(0))
+        .collect();
+    if wide.len() > SHELL_MAX_PATH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "'{}' is too long for {} (shell limit is {} characters)",
+                path_str,
+                purpose,
+                SHELL_MAX_PATH - 1
+            ),
+        ));
+    }
+    Ok(wide)
+}
+
+/// Win32 failures wrapped in an HRESULT keep their error code, so
+/// [`classify_error`] and the retry logic still recognize them.
+#[cfg(windows)]
+fn hresult_to_io(e: windows::core::Error) -> io::Error {
+    const FACILITY_WIN32_MASK: u32 = 0xFFFF_0000;
+    const FACILITY_WIN32: u32 = 0x8007_0000;
+    let code = e.code().0 as u32;
+    if code & FACILITY_WIN32_MASK == FACILITY_WIN32 {
+        io::Error::from_raw_os_error((code & 0xFFFF) as i32)
+    } else {
+        io::Error::other(e.message())
+    }
+}
+
+/// Keeps COM initialized for the lifetime of a thread that used the shell
+#[cfg(windows)]
+struct ComApartment {
+    initialized: bool,
+}
+
+#[cfg(windows)]
+impl Drop for ComApartment {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { windows::Win32::System::Com::CoUninitialize() };
+        }
+    }
+}
+
+/// Permanently delete one file or empty directory the way Explorer does,
+/// with `IFileOperation`: shell change notifications are sent, and the
+/// shell asks for elevation when the item needs it. Far slower per item
+/// than [`delete_file`].
+#[cfg(windows)]
+pub fn shell_delete(path: &Path) -> io::Result<()> {
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::UI::Shell::{
+        FileOperation, IFileOperation, IShellItem, SHCreateItemFromParsingName, FILEOPERATION_FLAGS,
+    };
+    const FOF_SILENT: u32 = 0x0004;
+    const FOF_NOCONFIRMATION: u32 = 0x0010;
+    const FOF_NOERRORUI: u32 = 0x0400;
+    const FOFX_SHOWELEVATIONPROMPT: u32 = 0x0004_0000;
+    const FOFX_EARLYFAILURE: u32 = 0x0010_0000;
+
+    thread_local! {
+        static COM: ComApartment = ComApartment {
+            initialized: unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) }.is_ok(),
+        };
+    }
+    COM.with(|_| {});
+
+    let wide = shell_wide_path(path, "the shell backend")?;
+    unsafe {
+        let op: IFileOperation =
+            CoCreateInstance(&FileOperation, None, CLSCTX_ALL).map_err(hresult_to_io)?;
+        // No FOF_ALLOWUNDO: delete permanently, never to the Recycle Bin
+        op.SetOperationFlags(FILEOPERATION_FLAGS(
+            FOF_SILENT
+                | FOF_NOCONFIRMATION
+                | FOF_NOERRORUI
+                | FOFX_SHOWELEVATIONPROMPT
+                | FOFX_EARLYFAILURE,
+        ))
+        .map_err(hresult_to_io)?;
+        let item: IShellItem =
+            SHCreateItemFromParsingName(PCWSTR(wide.as_ptr()), None).map_err(hresult_to_io)?;
+        op.DeleteItem(&item, None).map_err(hresult_to_io)?;
+        op.PerformOperations().map_err(hresult_to_io)?;
+        if op
+            .GetAnyOperationsAborted()
+            .map_err(hresult_to_io)?
+            .as_bool()
+        {
+            // Typically a declined elevation prompt
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the shell did not delete it (elevation declined or cancelled)",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// [`shell_delete`] with `policy`'s retries for locked items.
+#[cfg(windows)]
+pub fn shell_delete_with(path: &Path, policy: &RetryPolicy) -> io::Result<()> {
+    with_retries(policy, is_retryable_error, || shell_delete(path))
+}
+
+#[cfg(not(windows))]
+pub fn shell_delete_with(_path: &Path, _policy: &RetryPolicy) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the shell backend is only available on Windows",
+    ))
+}
+
+/// Move files and whole directories to the Recycle Bin with `IFileOperation`
+/// and `FOF_ALLOWUNDO`, as one shell operation. Check [`has_recycle_bin`]
+/// first: the shell deletes permanently on volumes without one.
+#[cfg(windows)]
+pub fn move_to_recycle_bin(paths: &[PathBuf]) -> io::Result<()> {
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::UI::Shell::{
+        FileOperation, IFileOperation, IShellItem, SHCreateItemFromParsingName, FILEOPERATION_FLAGS,
+    };
+    const FOF_SILENT: u32 = 0x0004;
+    const FOF_NOCONFIRMATION: u32 = 0x0010;
+    const FOF_ALLOWUNDO: u32 = 0x0040;
+    const FOF_NOERRORUI: u32 = 0x0400;
+    // Ask instead of nuking when an item is too large for the Recycle Bin
+    const FOF_WANTNUKEWARNING: u32 = 0x4000;
+    const FOFX_RECYCLEONDELETE: u32 = 0x0008_0000;
+
+    // The shell API doesn't understand \\?\ paths
+    let wide_paths = paths
+        .iter()
+        .map(|path| shell_wide_path(path, "the Recycle Bin"))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let to_io = |e: windows::core::Error| io::Error::other(e.message());
+
+    unsafe {
+        let initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok();
+        let result = (|| -> io::Result<()> {
+            let op: IFileOperation =
+                CoCreateInstance(&FileOperation, None, CLSCTX_ALL).map_err(to_io)?;
+            op.SetOperationFlags(FILEOPERATION_FLAGS(
+                FOF_SILENT
+                    | FOF_NOCONFIRMATION
+                    | FOF_ALLOWUNDO
+                    | FOF_NOERRORUI
+                    | FOF_WANTNUKEWARNING
+                    | FOFX_RECYCLEONDELETE,
+            ))
+            .map_err(to_io)?;
+
+            for wide in &wide_paths {
+                let item: IShellItem =
+                    SHCreateItemFromParsingName(PCWSTR(wide.as_ptr()), None).map_err(to_io)?;
+                op.DeleteItem(&item, None).map_err(to_io)?;
+            }
+
+            op.PerformOperations().map_err(to_io)?;
+            if op.GetAnyOperationsAborted().map_err(to_io)?.as_bool() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "move to Recycle Bin was cancelled",
+                ));
+            }
+            Ok(())
+        })();
+        if initialized {
+            CoUninitialize();
+        }
+        result
+    }
+}
+
+#[cfg(not(windows))]
+pub fn move_to_recycle_bin(_paths: &[PathBuf]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the Recycle Bin is only available on Windows",
+    ))
+}
+
+/// Hidden directory at each volume root holding renamed locked directories
+pub const PENDING_DIR: &str = ".rmx-pending";
+
+/// Unique name for `path` inside [`PENDING_DIR`]: the original name, the
+/// process id and a per-process counter.
+#[cfg(windows)]
+fn pending_name(path: &Path) -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!(
+        "{}.{}.{}",
+        name,
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Move a directory that is held open (and so can't be removed) into
+/// [`PENDING_DIR`] on the same volume, freeing its path at once. Returns
+/// the new location, to be deleted once the lock is gone.
+#[cfg(windows)]
+pub fn rename_for_deferred_delete(path: &Path) -> io::Result<PathBuf> {
+    use windows::Win32::Storage::FileSystem::{GetVolumePathNameW, MoveFileExW, MOVE_FILE_FLAGS};
+
+    let wide_path = checked_path_to_wide(path)?;
+    let mut root = vec![0u16; 32768];
+    unsafe { GetVolumePathNameW(PCWSTR(wide_path.as_ptr()), &mut root) }
+        .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+    let len = root.iter().position(|&c| c == 0).unwrap_or(root.len());
+    let pending = PathBuf::from(String::from_utf16_lossy(&root[..len])).join(PENDING_DIR);
+
+    if !path_exists(&pending) {
+        match std::fs::create_dir(&pending) {
+            Ok(()) => {
+                let wide_pending = path_to_wide(&pending);
+                unsafe {
+                    let _ =
+                        SetFileAttributesW(PCWSTR(wide_pending.as_ptr()), FILE_ATTRIBUTE_HIDDEN);
+                }
+            }
+            // Another worker created it first
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let target = pending.join(pending_name(path));
+    let wide_target = path_to_wide(&target);
+    // No MOVEFILE_COPY_ALLOWED: a rename, never a copy to another volume
+    unsafe {
+        MoveFileExW(
+            PCWSTR(wide_path.as_ptr()),
+            PCWSTR(wide_target.as_ptr()),
+            MOVE_FILE_FLAGS(0),
+        )
+    }
+    .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+    Ok(target)
+}
+
+/// Open directories never block `rmdir` outside Windows.
+#[cfg(not(windows))]
+pub fn rename_for_deferred_delete(_path: &Path) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "deferred deletion is only available on Windows",
+    ))
+}
+
 /// File entry information returned during enumeration
 pub struct FileEntry {
     pub path: std::path::PathBuf,
     pub is_dir: bool,
     pub is_symlink: bool,
+    /// `FILE_ATTRIBUTE_HIDDEN` on Windows, dot-prefixed name elsewhere
+    pub is_hidden: bool,
     pub size: u64,
+    /// Last write time, if the platform reported one
+    pub modified: Option<SystemTime>,
+    /// Reparse tag (`IO_REPARSE_TAG_*`) of reparse points; always `None`
+    /// outside Windows
+    pub reparse_tag: Option<u32>,
+    /// What kind of link this is, for entries with `is_symlink`
+    pub reparse_kind: Option<ReparseKind>,
+    /// Cloud placeholder (OneDrive Files-On-Demand and similar) whose data
+    /// is not stored locally; reading it would download it
+    pub is_placeholder: bool,
+}
+
+/// Set on cloud files whose contents are fetched on first access
+#[cfg(windows)]
+const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+#[cfg(windows)]
+const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+
+/// Volume mount points and directory junctions
+const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+const IO_REPARSE_TAG_SYMLINK: u32 = 0xA000_000C;
+
+/// The kinds of link rmx tells apart. Junctions and volume mount points
+/// share one reparse tag and differ only in their target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReparseKind {
+    Symlink,
+    /// Directory junction (`mklink /J`)
+    Junction,
+    /// Volume mounted into a folder
+    MountPoint,
+    /// App execution aliases, dedup, WSL and other reparse points
+    Other,
+}
+
+impl ReparseKind {
+    /// Classify by tag alone; mount-point tags are reported as junctions
+    /// (see [`reparse_kind`] for the exact kind).
+    pub fn from_tag(tag: u32) -> Self {
+        match tag {
+            IO_REPARSE_TAG_SYMLINK => Self::Symlink,
+            IO_REPARSE_TAG_MOUNT_POINT => Self::Junction,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Symlink => "symlink",
+            Self::Junction => "junction",
+            Self::MountPoint => "mount point",
+            Self::Other => "reparse point",
+        }
+    }
+}
+
+/// A mount-point reparse target naming a volume (`\??\Volume{guid}\`)
+/// rather than a folder
+#[cfg(any(windows, test))]
+fn is_volume_target(substitute_name: &[u16]) -> bool {
+    const VOLUME_PREFIX: &str = r"\??\Volume{";
+    let prefix: Vec<u16> = VOLUME_PREFIX.encode_utf16().collect();
+    substitute_name.starts_with(&prefix)
+}
+
+/// Kind of the reparse point at `path` carrying `tag`. Mount-point tags are
+/// told apart by reading the target with `FSCTL_GET_REPARSE_POINT`; if that
+/// fails they count as junctions.
+#[cfg(windows)]
+pub fn reparse_kind(path: &Path, tag: u32) -> ReparseKind {
+    if tag != IO_REPARSE_TAG_MOUNT_POINT {
+        return ReparseKind::from_tag(tag);
+    }
+    match read_mount_point_target(path) {
+        Some(target) if is_volume_target(&target) => ReparseKind::MountPoint,
+        _ => ReparseKind::Junction,
+    }
+}
+
+/// Substitute name of a mount-point reparse point
+#[cfg(windows)]
+fn read_mount_point_target(path: &Path) -> Option<Vec<u16>> {
+    use windows::Win32::System::IO::DeviceIoControl;
+    const FSCTL_GET_REPARSE_POINT: u32 = 0x0009_00A8;
+    const MAXIMUM_REPARSE_DATA_BUFFER_SIZE: usize = 16 * 1024;
+    // ReparseTag, ReparseDataLength, Reserved, then the four name offsets
+    const PATH_BUFFER_OFFSET: usize = 16;
+
+    let wide_path = path_to_wide(path);
+    let mut buffer = vec![0u8; MAXIMUM_REPARSE_DATA_BUFFER_SIZE];
+    let mut returned = 0u32;
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR(wide_path.as_ptr()),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT,
+            HANDLE::default(),
+        )
+        .ok()?;
+        let result = DeviceIoControl(
+            handle,
+            FSCTL_GET_REPARSE_POINT,
+            None,
+            0,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            buffer.len() as u32,
+            Some(&mut returned),
+            None,
+        );
+        CloseHandle(handle).ok();
+        result.ok()?;
+    }
+
+    let field = |at: usize| u16::from_le_bytes([buffer[at], buffer[at + 1]]) as usize;
+    let start = PATH_BUFFER_OFFSET + field(8);
+    let end = start + field(10);
+    if end > (returned as usize).min(buffer.len()) {
+        return None;
+    }
+    Some(
+        buffer[start..end]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect(),
+    )
+}
+
+/// Every file and folder under a cloud sync root (OneDrive, iCloud, ...)
+/// carries one of `IO_REPARSE_TAG_CLOUD`..`IO_REPARSE_TAG_CLOUD_F`, hydrated
+/// or not. They are ordinary entries to rmx, not links: folders are
+/// recursed into and files are deleted without being opened for data.
+#[cfg(windows)]
+fn is_cloud_reparse_tag(tag: u32) -> bool {
+    const IO_REPARSE_TAG_CLOUD: u32 = 0x9000_001A;
+    const IO_REPARSE_TAG_CLOUD_MASK: u32 = 0x0000_F000;
+    tag & !IO_REPARSE_TAG_CLOUD_MASK == IO_REPARSE_TAG_CLOUD
+}
+
+impl FileEntry {
+    /// Whether this directory may live on a different volume than its
+    /// parent. On Windows only junctions and mount points can; elsewhere any
+    /// real directory may be a mount point.
+    pub fn may_cross_volume(&self) -> bool {
+        if cfg!(windows) {
+            matches!(
+                self.reparse_kind,
+                Some(ReparseKind::Junction | ReparseKind::MountPoint)
+            )
+        } else {
+            self.is_dir && !self.is_symlink
+        }
+    }
+}
+
+/// Convert a FILETIME (100ns ticks since 1601-01-01) to `SystemTime`
+#[cfg(windows)]
+fn filetime_to_system_time(high: u32, low: u32) -> Option<SystemTime> {
+    const TICKS_TO_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+    let ticks = ((high as u64) << 32) | (low as u64);
+    if ticks == 0 {
+        return None;
+    }
+    if ticks >= TICKS_TO_UNIX_EPOCH {
+        let since_epoch = ticks - TICKS_TO_UNIX_EPOCH;
+        Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(since_epoch.saturating_mul(100)))
+    } else {
+        let before_epoch = TICKS_TO_UNIX_EPOCH - ticks;
+        SystemTime::UNIX_EPOCH.checked_sub(Duration::from_nanos(before_epoch.saturating_mul(100)))
+    }
+}
+
+/// Build the entry for `path` from what a directory listing reports about
+/// it; `tag` is only meaningful for reparse points.
+#[cfg(windows)]
+fn entry_from_attributes(
+    path: PathBuf,
+    attributes: u32,
+    tag: u32,
+    size: u64,
+    modified: Option<SystemTime>,
+) -> FileEntry {
+    let is_dir = (attributes & FILE_ATTRIBUTE_DIRECTORY.0) != 0;
+    let is_reparse = (attributes & FILE_ATTRIBUTE_REPARSE_POINT.0) != 0;
+    let is_symlink = is_reparse && !is_cloud_reparse_tag(tag);
+    let is_placeholder = !is_dir
+        && (attributes & (FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN))
+            != 0;
+    let reparse_kind = is_symlink.then(|| reparse_kind(&path, tag));
+    FileEntry {
+        path,
+        is_dir,
+        is_symlink,
+        is_hidden: (attributes & FILE_ATTRIBUTE_HIDDEN.0) != 0,
+        size: if is_dir { 0 } else { size },
+        modified,
+        reparse_tag: is_symlink.then_some(tag),
+        reparse_kind,
+        is_placeholder,
+    }
 }
 
 #[cfg(windows)]
@@ -361,7 +1621,7 @@ where
     F: FnMut(FileEntry) -> io::Result<()>,
 {
     let search_path = dir.join("*");
-    let wide_path = path_to_wide(&search_path);
+    let wide_path = checked_path_to_wide(&search_path)?;
 
     unsafe {
         let mut find_data: WIN32_FIND_DATAW = std::mem::zeroed();
@@ -410,24 +1670,21 @@ where
             let is_dotdot = name_len == 2 && name_slice[0] == 0x2E && name_slice[1] == 0x2E;
 
             if !is_dot && !is_dotdot {
-                let is_dir = (find_data.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY.0) != 0;
-                let is_symlink = (find_data.dwFileAttributes & FILE_ATTRIBUTE_REPARSE_POINT.0) != 0;
-                let size = if is_dir {
-                    0
-                } else {
-                    ((find_data.nFileSizeHigh as u64) << 32) | (find_data.nFileSizeLow as u64)
-                };
                 let filename = {
                     use std::os::windows::ffi::OsStringExt;
                     std::ffi::OsString::from_wide(name_slice)
                 };
-                let full_path = dir.join(&filename);
-                callback(FileEntry {
-                    path: full_path,
-                    is_dir,
-                    is_symlink,
-                    size,
-                })?;
+                callback(entry_from_attributes(
+                    dir.join(&filename),
+                    find_data.dwFileAttributes,
+                    // dwReserved0 holds the tag for reparse points
+                    find_data.dwReserved0,
+                    ((find_data.nFileSizeHigh as u64) << 32) | (find_data.nFileSizeLow as u64),
+                    filetime_to_system_time(
+                        find_data.ftLastWriteTime.dwHighDateTime,
+                        find_data.ftLastWriteTime.dwLowDateTime,
+                    ),
+                ))?;
             }
 
             if FindNextFileW(handle, &mut find_data).is_err() {
@@ -452,21 +1709,113 @@ where
         let file_type = entry.file_type()?;
         let is_dir = file_type.is_dir();
         let is_symlink = file_type.is_symlink();
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let metadata = entry.metadata().ok();
         let size = if is_dir || is_symlink {
             0
         } else {
-            entry.metadata().map(|m| m.len()).unwrap_or(0)
+            metadata.as_ref().map(|m| m.len()).unwrap_or(0)
         };
+        let modified = metadata.and_then(|m| m.modified().ok());
         callback(FileEntry {
             path,
             is_dir,
             is_symlink,
+            is_hidden,
             size,
+            modified,
+            reparse_tag: None,
+            reparse_kind: is_symlink.then_some(ReparseKind::Symlink),
+            is_placeholder: false,
         })?;
     }
     Ok(())
 }
 
+/// An NTFS alternate data stream of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlternateStream {
+    /// `Zone.Identifier` for `file:Zone.Identifier:$DATA`
+    pub name: String,
+    pub size: u64,
+}
+
+/// The alternate data streams of `path`, without the unnamed main stream.
+/// Empty on file systems without streams.
+#[cfg(windows)]
+pub fn list_streams(path: &Path) -> io::Result<Vec<AlternateStream>> {
+    const ERROR_HANDLE_EOF: i32 = 38;
+    const ERROR_INVALID_PARAMETER: i32 = 87;
+    let wide_path = path_to_wide(path);
+    let mut streams = Vec::new();
+
+    unsafe {
+        let mut data = WIN32_FIND_STREAM_DATA::default();
+        let handle = match FindFirstStreamW(
+            PCWSTR(wide_path.as_ptr()),
+            FindStreamInfoStandard,
+            &mut data as *mut _ as *mut c_void,
+            0,
+        ) {
+            Ok(h) => h,
+            Err(e) => {
+                let err = io::Error::from_raw_os_error(e.code().0 & 0xFFFF);
+                // No streams at all (directories), or a file system without them (FAT)
+                return match err.raw_os_error() {
+                    Some(ERROR_HANDLE_EOF) | Some(ERROR_INVALID_PARAMETER) => Ok(streams),
+                    _ => Err(err),
+                };
+            }
+        };
+
+        loop {
+            let len = data
+                .cStreamName
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(data.cStreamName.len());
+            let raw = String::from_utf16_lossy(&data.cStreamName[..len]);
+            if let Some(name) = alternate_stream_name(&raw) {
+                streams.push(AlternateStream {
+                    name: name.to_string(),
+                    size: data.StreamSize.max(0) as u64,
+                });
+            }
+
+            if FindNextStreamW(handle, &mut data as *mut _ as *mut c_void).is_err() {
+                break;
+            }
+        }
+
+        let _ = FindClose(handle);
+    }
+
+    Ok(streams)
+}
+
+/// Only NTFS (and ReFS) have alternate data streams
+#[cfg(not(windows))]
+pub fn list_streams(_path: &Path) -> io::Result<Vec<AlternateStream>> {
+    Ok(Vec::new())
+}
+
+/// `:Zone.Identifier:$DATA` -> `Zone.Identifier`; `None` for the unnamed
+/// main stream `::$DATA`.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn alternate_stream_name(raw: &str) -> Option<&str> {
+    let name = raw.strip_prefix(':')?;
+    let name = name.rsplit_once(':').map_or(name, |(name, _kind)| name);
+    (!name.is_empty()).then_some(name)
+}
+
+/// `file:name`, the path that opens stream `name` of `path`
+pub fn stream_path(path: &Path, name: &str) -> PathBuf {
+    let mut stream = path.as_os_str().to_owned();
+    stream.push(":");
+    stream.push(name);
+    PathBuf::from(stream)
+}
+
 /// Information about a process holding a file lock
 #[derive(Debug, Clone)]
 pub struct LockingProcess {
@@ -689,15 +2038,37 @@ pub fn find_locking_processes_batch(_paths: &[PathBuf]) -> io::Result<Vec<Lockin
     Ok(Vec::new())
 }
 
+/// How a locking process was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillOutcome {
+    /// Exited on its own after its windows were asked to close
+    Closed,
+    /// Ended with `TerminateProcess`
+    Terminated,
+}
+
+impl KillOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Terminated => "killed",
+        }
+    }
+}
+
+/// Stop every process in `processes` except the System ones. With a
+/// `grace` period each one is first asked to close (see
+/// [`kill_process_graceful`]), otherwise it is terminated right away.
 #[cfg(windows)]
-pub fn kill_locking_processes_batch(
-    paths: &[PathBuf],
+fn kill_each(
+    processes: Vec<LockingProcess>,
     verbose: bool,
-) -> io::Result<Vec<LockingProcess>> {
-    let processes = find_locking_processes_batch(paths)?;
+    grace: Option<Duration>,
+) -> Vec<(LockingProcess, KillOutcome)> {
     let mut killed = Vec::new();
 
-    for proc in &processes {
+    for proc in processes {
+        // Skip system-critical processes (PID 0 and 4 are System processes)
         if proc.pid == 0 || proc.pid == 4 {
             if verbose {
                 eprintln!(
@@ -708,12 +2079,20 @@ pub fn kill_locking_processes_batch(
             continue;
         }
 
-        match kill_process(proc.pid) {
-            Ok(()) => {
+        let result = match grace {
+            Some(grace) => kill_process_graceful(proc.pid, grace),
+            None => kill_process(proc.pid).map(|()| KillOutcome::Terminated),
+        };
+        match result {
+            Ok(outcome) => {
                 if verbose {
-                    eprintln!("Killed process '{}' (PID {})", proc.name, proc.pid);
+                    let verb = match outcome {
+                        KillOutcome::Closed => "Closed",
+                        KillOutcome::Terminated => "Killed",
+                    };
+                    eprintln!("{} process '{}' (PID {})", verb, proc.name, proc.pid);
                 }
-                killed.push(proc.clone());
+                killed.push((proc, outcome));
             }
             Err(e) => {
                 if verbose {
@@ -730,18 +2109,32 @@ pub fn kill_locking_processes_batch(
         thread::sleep(Duration::from_millis(50));
     }
 
-    Ok(killed)
+    killed
+}
+
+#[cfg(windows)]
+pub fn kill_locking_processes_batch(
+    paths: &[PathBuf],
+    verbose: bool,
+    grace: Option<Duration>,
+) -> io::Result<Vec<(LockingProcess, KillOutcome)>> {
+    Ok(kill_each(
+        find_locking_processes_batch(paths)?,
+        verbose,
+        grace,
+    ))
 }
 
 #[cfg(not(windows))]
 pub fn kill_locking_processes_batch(
     _paths: &[PathBuf],
     _verbose: bool,
-) -> io::Result<Vec<LockingProcess>> {
+    _grace: Option<Duration>,
+) -> io::Result<Vec<(LockingProcess, KillOutcome)>> {
     Ok(Vec::new())
 }
 
-/// Kill a process by PID
+/// Terminate a process by PID right away, without asking it to close
 #[cfg(windows)]
 pub fn kill_process(pid: u32) -> io::Result<()> {
     unsafe {
@@ -763,51 +2156,75 @@ pub fn kill_process(_pid: u32) -> io::Result<()> {
     ))
 }
 
-/// Kill all processes locking a file
-#[cfg(windows)]
-pub fn kill_locking_processes(path: &Path, verbose: bool) -> io::Result<Vec<LockingProcess>> {
-    let processes = find_locking_processes(path)?;
-    let mut killed = Vec::new();
-
-    for proc in &processes {
-        // Skip system-critical processes (PID 0 and 4 are System processes)
-        if proc.pid == 0 || proc.pid == 4 {
-            if verbose {
-                eprintln!(
-                    "Warning: Skipping system process {} (PID {})",
-                    proc.name, proc.pid
-                );
-            }
-            continue;
-        }
+/// How long `--graceful-kill` waits for a process to close its windows
+/// before terminating it
+pub const KILL_GRACE_PERIOD: Duration = Duration::from_secs(2);
 
-        match kill_process(proc.pid) {
-            Ok(()) => {
-                if verbose {
-                    eprintln!("Killed process '{}' (PID {})", proc.name, proc.pid);
-                }
-                killed.push(proc.clone());
-            }
-            Err(e) => {
-                if verbose {
-                    eprintln!(
-                        "Warning: Failed to kill '{}' (PID {}): {}",
-                        proc.name, proc.pid, e
-                    );
-                }
-            }
+/// Ask `pid` to exit by posting `WM_CLOSE` to its top-level windows, and
+/// only `TerminateProcess` it if it is still running after `grace`.
+/// Processes without windows are terminated right away.
+#[cfg(windows)]
+pub fn kill_process_graceful(pid: u32, grace: Duration) -> io::Result<KillOutcome> {
+    unsafe extern "system" fn post_close(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let (pid, posted) = &mut *(lparam.0 as *mut (u32, usize));
+        let mut owner = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut owner));
+        if owner == *pid && PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0)).is_ok() {
+            *posted += 1;
         }
+        BOOL(1)
     }
 
-    if !killed.is_empty() {
-        thread::sleep(Duration::from_millis(50));
+    unsafe {
+        let handle = OpenProcess(
+            PROCESS_TERMINATE | PROCESS_QUERY_INFORMATION | PROCESS_SYNCHRONIZE,
+            false,
+            pid,
+        )
+        .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))?;
+
+        let mut target = (pid, 0usize);
+        let _ = EnumWindows(Some(post_close), LPARAM(&mut target as *mut _ as isize));
+        let exited = target.1 > 0
+            && WaitForSingleObject(handle, grace.as_millis().min(u32::MAX as u128) as u32)
+                == WAIT_OBJECT_0;
+
+        let result = if exited {
+            Ok(KillOutcome::Closed)
+        } else {
+            TerminateProcess(handle, 1).map(|()| KillOutcome::Terminated)
+        };
+        CloseHandle(handle).ok();
+
+        result.map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))
     }
+}
+
+#[cfg(not(windows))]
+pub fn kill_process_graceful(_pid: u32, _grace: Duration) -> io::Result<KillOutcome> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Not supported on this platform",
+    ))
+}
 
-    Ok(killed)
+/// Kill all processes locking a file. With a `grace` period each one is
+/// first asked to close, otherwise it is terminated right away.
+#[cfg(windows)]
+pub fn kill_locking_processes(
+    path: &Path,
+    verbose: bool,
+    grace: Option<Duration>,
+) -> io::Result<Vec<(LockingProcess, KillOutcome)>> {
+    Ok(kill_each(find_locking_processes(path)?, verbose, grace))
 }
 
 #[cfg(not(windows))]
-pub fn kill_locking_processes(_path: &Path, _verbose: bool) -> io::Result<Vec<LockingProcess>> {
+pub fn kill_locking_processes(
+    _path: &Path,
+    _verbose: bool,
+    _grace: Option<Duration>,
+) -> io::Result<Vec<(LockingProcess, KillOutcome)>> {
     Ok(Vec::new())
 }
 
@@ -821,6 +2238,41 @@ pub fn is_file_in_use_error(error: &io::Error) -> bool {
     )
 }
 
+/// Sort a deletion failure into a [`ErrorKind`](crate::error::ErrorKind).
+pub fn classify_error(error: &io::Error) -> crate::error::ErrorKind {
+    use crate::error::ErrorKind;
+
+    if error
+        .get_ref()
+        .is_some_and(|e| e.is::<crate::error::PathTooLongError>())
+    {
+        return ErrorKind::PathTooLong;
+    }
+    if is_not_found_error(error) {
+        return ErrorKind::NotFound;
+    }
+    if is_dir_not_empty_error(error) || error.kind() == io::ErrorKind::DirectoryNotEmpty {
+        return ErrorKind::DirNotEmpty;
+    }
+    #[cfg(windows)]
+    {
+        const ERROR_ACCESS_DENIED: i32 = 5;
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+        const ERROR_LOCK_VIOLATION: i32 = 33;
+        const ERROR_FILENAME_EXCED_RANGE: i32 = 206;
+        match error.raw_os_error() {
+            Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION) => return ErrorKind::Locked,
+            Some(ERROR_ACCESS_DENIED) => return ErrorKind::AccessDenied,
+            Some(ERROR_FILENAME_EXCED_RANGE) => return ErrorKind::PathTooLong,
+            _ => {}
+        }
+    }
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => ErrorKind::AccessDenied,
+        _ => ErrorKind::Other(error.raw_os_error().unwrap_or(0)),
+    }
+}
+
 pub fn is_dir_not_empty_error(error: &io::Error) -> bool {
     const ERROR_DIR_NOT_EMPTY: i32 = 145;
     error.raw_os_error() == Some(ERROR_DIR_NOT_EMPTY)
@@ -879,155 +2331,461 @@ struct SystemHandleTableEntryInfo {
 ///
 /// Only releases locks — does NOT delete anything.
 /// Uses NtQuerySystemInformation + DuplicateHandle(DUPLICATE_CLOSE_SOURCE).
+/// For repeated calls during one run, use a shared [`HandleCloser`].
 ///
 /// # Safety concern
 /// Closing handles in another process may crash that process.
 /// Only call when user explicitly opted in (--kill-processes).
-#[cfg(windows)]
 pub fn force_close_file_handles(paths: &[PathBuf], verbose: bool) -> io::Result<usize> {
-    if paths.is_empty() {
-        return Ok(0);
-    }
+    HandleCloser::new(verbose).close_for(paths)
+}
 
-    let normalized_targets: Vec<String> = paths
-        .iter()
-        .filter_map(|p| {
-            let abs = std::fs::canonicalize(p).ok()?;
-            Some(abs.to_string_lossy().to_lowercase())
-        })
-        .collect();
+/// A file handle another process holds on one of the queried paths
+#[derive(Debug, Clone)]
+pub struct OpenFileHandle {
+    pub pid: u32,
+    /// Handle value inside that process
+    pub handle: u16,
+    /// The queried path it refers to
+    pub path: PathBuf,
+}
 
-    if normalized_targets.is_empty() {
-        return Ok(0);
-    }
+/// The handles [`force_close_file_handles`] would close, found the same way
+/// but left open (`--unlock --dry-run`).
+pub fn find_file_handles(paths: &[PathBuf], verbose: bool) -> io::Result<Vec<OpenFileHandle>> {
+    HandleCloser::new(verbose).handles_for(paths)
+}
 
-    let file_type_index = detect_file_object_type_index();
+/// How long a [`HandleCloser`] reuses its handle snapshot
+#[cfg_attr(not(windows), allow(dead_code))]
+const HANDLE_SNAPSHOT_TTL: Duration = Duration::from_secs(2);
 
-    let buf = query_system_handles()?;
-    let info = buf.as_ptr() as *const SystemHandleInformation;
-    let num_handles = unsafe { (*info).number_of_handles as usize };
+/// Force-closes file handles for many paths from one sweep of the system
+/// handle table, instead of one sweep per call like
+/// [`force_close_file_handles`]. The snapshot is taken on first use and
+/// retaken once it is older than a couple of seconds.
+pub struct HandleCloser {
+    #[cfg_attr(not(windows), allow(dead_code))]
+    verbose: bool,
+    #[cfg(windows)]
+    snapshot: parking_lot::Mutex<Option<HandleSnapshot>>,
+}
 
-    if verbose {
-        eprintln!(
-            "Scanning {} system handles for locked files...",
-            num_handles
-        );
+impl HandleCloser {
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose,
+            #[cfg(windows)]
+            snapshot: parking_lot::Mutex::new(None),
+        }
     }
 
-    let entries = unsafe { std::slice::from_raw_parts((*info).handles.as_ptr(), num_handles) };
+    /// Close every handle other processes hold on `paths`. Returns how many
+    /// were closed.
+    #[cfg(windows)]
+    pub fn close_for(&self, paths: &[PathBuf]) -> io::Result<usize> {
+        let normalized_targets = normalized_targets(paths);
+        if normalized_targets.is_empty() {
+            return Ok(0);
+        }
 
-    let current_pid = std::process::id() as u16;
-    let mut handles_closed = 0usize;
-    let mut proc_cache: std::collections::HashMap<u16, Option<HANDLE>> =
-        std::collections::HashMap::new();
-    let current_process = unsafe { GetCurrentProcess() };
+        let mut snapshot = self.snapshot.lock();
+        self.refresh(&mut snapshot)?;
+        let Some(snapshot) = snapshot.as_mut() else {
+            return Ok(0);
+        };
 
-    for entry in entries {
-        let pid = entry.unique_process_id;
-        if pid == current_pid || pid == 0 || pid == 4 || entry.granted_access == 0 {
-            continue;
-        }
+        let current_process = unsafe { GetCurrentProcess() };
+        let mut handles_closed = 0usize;
 
-        if let Some(file_idx) = file_type_index {
-            if entry.object_type_index != file_idx {
+        for (_, target) in &normalized_targets {
+            let Some(holders) = snapshot.by_path.remove(target) else {
                 continue;
+            };
+            for (pid, handle_value) in holders {
+                let Ok(proc_handle) =
+                    (unsafe { OpenProcess(PROCESS_DUP_HANDLE, false, pid as u32) })
+                else {
+                    continue;
+                };
+                let source_handle = HANDLE(handle_value as *mut c_void);
+
+                // The value may have been closed and reused since the snapshot
+                let still_match =
+                    duplicate_and_resolve(proc_handle, source_handle, current_process)
+                        .is_some_and(|p| p.to_lowercase() == *target);
+
+                let ok = still_match
+                    && unsafe {
+                        DuplicateHandle(
+                            proc_handle,
+                            source_handle,
+                            HANDLE::default(),
+                            std::ptr::null_mut(),
+                            0,
+                            false,
+                            DUPLICATE_CLOSE_SOURCE,
+                        )
+                    }
+                    .is_ok();
+                unsafe { CloseHandle(proc_handle).ok() };
+
+                if ok {
+                    handles_closed += 1;
+                    if self.verbose {
+                        eprintln!("  Closed handle 0x{:04X} in PID {}", handle_value, pid);
+                    }
+                }
             }
         }
 
-        let proc_handle = proc_cache
-            .entry(pid)
-            .or_insert_with(|| unsafe { OpenProcess(PROCESS_DUP_HANDLE, false, pid as u32).ok() });
+        if self.verbose && handles_closed > 0 {
+            eprintln!("Force-closed {} handle(s)", handles_closed);
+        }
+
+        Ok(handles_closed)
+    }
+
+    #[cfg(not(windows))]
+    pub fn close_for(&self, _paths: &[PathBuf]) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    /// The handles [`close_for`](Self::close_for) would close, without
+    /// closing them
+    #[cfg(windows)]
+    pub fn handles_for(&self, paths: &[PathBuf]) -> io::Result<Vec<OpenFileHandle>> {
+        let normalized_targets = normalized_targets(paths);
+        if normalized_targets.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let proc_handle = match proc_handle {
-            Some(h) => *h,
-            None => continue,
+        let mut snapshot = self.snapshot.lock();
+        self.refresh(&mut snapshot)?;
+        let Some(snapshot) = snapshot.as_ref() else {
+            return Ok(Vec::new());
         };
 
-        let source_handle = HANDLE(entry.handle_value as *mut c_void);
-        let mut dup_handle = HANDLE::default();
-
-        if unsafe {
-            DuplicateHandle(
-                proc_handle,
-                source_handle,
-                current_process,
-                &mut dup_handle,
-                0,
-                false,
-                DUPLICATE_SAME_ACCESS,
-            )
+        let mut found = Vec::new();
+        for (path, target) in normalized_targets {
+            for &(pid, handle) in snapshot.by_path.get(&target).into_iter().flatten() {
+                found.push(OpenFileHandle {
+                    pid: pid as u32,
+                    handle,
+                    path: path.clone(),
+                });
+            }
         }
-        .is_err()
+        Ok(found)
+    }
+
+    #[cfg(not(windows))]
+    pub fn handles_for(&self, _paths: &[PathBuf]) -> io::Result<Vec<OpenFileHandle>> {
+        Ok(Vec::new())
+    }
+
+    /// Take a new snapshot unless the current one is recent enough
+    #[cfg(windows)]
+    fn refresh(&self, snapshot: &mut Option<HandleSnapshot>) -> io::Result<()> {
+        if snapshot
+            .as_ref()
+            .is_none_or(|s| s.taken.elapsed() > HANDLE_SNAPSHOT_TTL)
         {
-            continue;
+            *snapshot = Some(HandleSnapshot::take(self.verbose)?);
+        }
+        Ok(())
+    }
+}
+
+/// Each of `paths` that still exists, with the lowercased canonical form
+/// handle snapshots are keyed by
+#[cfg(windows)]
+fn normalized_targets(paths: &[PathBuf]) -> Vec<(&PathBuf, String)> {
+    paths
+        .iter()
+        .filter_map(|p| {
+            let abs = std::fs::canonicalize(p).ok()?;
+            Some((p, abs.to_string_lossy().to_lowercase()))
+        })
+        .collect()
+}
+
+/// Every file handle of other processes, keyed by the lowercased path it
+/// resolved to when the snapshot was taken
+#[cfg(windows)]
+struct HandleSnapshot {
+    taken: std::time::Instant,
+    by_path: std::collections::HashMap<String, Vec<(u16, u16)>>,
+}
+
+#[cfg(windows)]
+impl HandleSnapshot {
+    fn take(verbose: bool) -> io::Result<Self> {
+        let taken = std::time::Instant::now();
+        let file_type_index = file_object_type_index();
+
+        let buf = query_system_handles()?;
+        let info = buf.as_ptr() as *const SystemHandleInformation;
+        let num_handles = unsafe { (*info).number_of_handles as usize };
+
+        if verbose {
+            eprintln!(
+                "Scanning {} system handles for locked files...",
+                num_handles
+            );
         }
 
-        let is_match = resolve_handle_path_with_timeout(dup_handle)
-            .map(|p| normalized_targets.contains(&p.to_lowercase()))
-            .unwrap_or(false);
+        let entries = unsafe { std::slice::from_raw_parts((*info).handles.as_ptr(), num_handles) };
+
+        let current_pid = std::process::id() as u16;
+        // (pid, handle value in that process, our duplicate)
+        let mut candidates: Vec<(u16, u16, usize)> = Vec::new();
+        let mut proc_cache: std::collections::HashMap<u16, Option<HANDLE>> =
+            std::collections::HashMap::new();
+        let current_process = unsafe { GetCurrentProcess() };
+
+        for entry in entries {
+            let pid = entry.unique_process_id;
+            if pid == current_pid || pid == 0 || pid == 4 || entry.granted_access == 0 {
+                continue;
+            }
+
+            if let Some(file_idx) = file_type_index {
+                if entry.object_type_index != file_idx {
+                    continue;
+                }
+            }
 
-        unsafe { CloseHandle(dup_handle).ok() };
+            let proc_handle = proc_cache.entry(pid).or_insert_with(|| unsafe {
+                OpenProcess(PROCESS_DUP_HANDLE, false, pid as u32).ok()
+            });
 
-        if is_match {
-            let ok = unsafe {
+            let proc_handle = match proc_handle {
+                Some(h) => *h,
+                None => continue,
+            };
+
+            let source_handle = HANDLE(entry.handle_value as *mut c_void);
+            let mut dup_handle = HANDLE::default();
+            if unsafe {
                 DuplicateHandle(
                     proc_handle,
                     source_handle,
-                    HANDLE::default(),
-                    std::ptr::null_mut(),
+                    current_process,
+                    &mut dup_handle,
                     0,
                     false,
-                    DUPLICATE_CLOSE_SOURCE,
+                    DUPLICATE_SAME_ACCESS,
                 )
             }
-            .is_ok();
+            .is_ok()
+            {
+                candidates.push((pid, entry.handle_value, dup_handle.0 as usize));
+            }
+        }
 
-            if ok {
-                handles_closed += 1;
-                if verbose {
-                    eprintln!(
-                        "  Closed handle 0x{:04X} in PID {}",
-                        entry.handle_value, pid
-                    );
-                }
+        for (_, h) in proc_cache {
+            if let Some(h) = h {
+                unsafe { CloseHandle(h).ok() };
             }
         }
-    }
 
-    for (_, h) in proc_cache {
-        if let Some(h) = h {
-            unsafe { CloseHandle(h).ok() };
+        let paths = resolve_concurrently(
+            candidates.iter().map(|&(_, _, dup)| dup).collect(),
+            final_path_name,
+            RESOLVE_TIMEOUT,
+            RESOLVE_DEADLINE,
+        );
+
+        let mut by_path: std::collections::HashMap<String, Vec<(u16, u16)>> =
+            std::collections::HashMap::new();
+        for ((pid, handle_value, dup), path) in candidates.into_iter().zip(paths) {
+            unsafe { CloseHandle(HANDLE(dup as *mut c_void)).ok() };
+            if let Some(path) = path {
+                by_path
+                    .entry(path.to_lowercase())
+                    .or_default()
+                    .push((pid, handle_value));
+            }
         }
+
+        Ok(Self { taken, by_path })
     }
+}
 
-    if verbose && handles_closed > 0 {
-        eprintln!("Force-closed {} handle(s)", handles_closed);
+/// Copy `source_handle` out of the process behind `proc_handle` and return
+/// the path it refers to
+#[cfg(windows)]
+fn duplicate_and_resolve(
+    proc_handle: HANDLE,
+    source_handle: HANDLE,
+    current_process: HANDLE,
+) -> Option<String> {
+    let mut dup_handle = HANDLE::default();
+    unsafe {
+        DuplicateHandle(
+            proc_handle,
+            source_handle,
+            current_process,
+            &mut dup_handle,
+            0,
+            false,
+            DUPLICATE_SAME_ACCESS,
+        )
     }
+    .ok()?;
 
-    Ok(handles_closed)
+    let path = resolve_handle_path_with_timeout(dup_handle);
+    unsafe { CloseHandle(dup_handle).ok() };
+    path
 }
 
 const RESOLVE_TIMEOUT: Duration = Duration::from_millis(200);
 
+/// Threads resolving the handles of a snapshot side by side
+const RESOLVE_THREADS: usize = 8;
+
+/// Upper bound for resolving a whole snapshot, however many handles hang
+#[cfg_attr(not(windows), allow(dead_code))]
+const RESOLVE_DEADLINE: Duration = Duration::from_secs(10);
+
 #[cfg(windows)]
 fn resolve_handle_path_with_timeout(handle: HANDLE) -> Option<String> {
     let handle_val = handle.0 as usize;
     let (tx, rx) = std::sync::mpsc::channel();
 
     thread::spawn(move || {
-        let h = HANDLE(handle_val as *mut c_void);
-        let mut buf = [0u16; 1024];
-        let len = unsafe { GetFinalPathNameByHandleW(h, &mut buf, FILE_NAME_NORMALIZED) };
-        if len > 0 && (len as usize) < buf.len() {
-            let _ = tx.send(Some(String::from_utf16_lossy(&buf[..len as usize])));
-        } else {
-            let _ = tx.send(None);
-        }
+        let _ = tx.send(final_path_name(handle_val));
     });
 
     rx.recv_timeout(RESOLVE_TIMEOUT).ok().flatten()
 }
 
+/// `GetFinalPathNameByHandleW`. May block forever (synchronous pipes), so
+/// only call it on a thread that can be abandoned.
+#[cfg(windows)]
+fn final_path_name(handle_val: usize) -> Option<String> {
+    let h = HANDLE(handle_val as *mut c_void);
+    let mut buf = [0u16; 1024];
+    let len = unsafe { GetFinalPathNameByHandleW(h, &mut buf, FILE_NAME_NORMALIZED) };
+    if len > 0 && (len as usize) < buf.len() {
+        Some(String::from_utf16_lossy(&buf[..len as usize]))
+    } else {
+        None
+    }
+}
+
+enum ResolveEvent {
+    Started(usize),
+    Done(usize, Option<String>),
+}
+
+/// Run `resolve` over `items` on [`RESOLVE_THREADS`] detached threads.
+/// An item still running after `per_item` is given up on (`None`) and its
+/// thread replaced, so a hung call only ever costs one thread. Returns by
+/// `deadline` at the latest.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn resolve_concurrently<F>(
+    items: Vec<usize>,
+    resolve: F,
+    per_item: Duration,
+    deadline: Duration,
+) -> Vec<Option<String>>
+where
+    F: Fn(usize) -> Option<String> + Send + Sync + 'static,
+{
+    use crossbeam_channel::RecvTimeoutError;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    let count = items.len();
+    let mut results = vec![None; count];
+    if count == 0 {
+        return results;
+    }
+    let deadline = Instant::now() + deadline;
+
+    let (job_tx, job_rx) = crossbeam_channel::unbounded();
+    for job in items.into_iter().enumerate() {
+        let _ = job_tx.send(job);
+    }
+    drop(job_tx);
+    let (event_tx, event_rx) = crossbeam_channel::unbounded();
+    let stop = Arc::new(AtomicBool::new(false));
+    let resolve = Arc::new(resolve);
+
+    let spawn_worker = || {
+        let job_rx = job_rx.clone();
+        let event_tx = event_tx.clone();
+        let stop = stop.clone();
+        let resolve = resolve.clone();
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                let Ok((index, item)) = job_rx.try_recv() else {
+                    break;
+                };
+                if event_tx.send(ResolveEvent::Started(index)).is_err() {
+                    break;
+                }
+                let _ = event_tx.send(ResolveEvent::Done(index, resolve(item)));
+            }
+        });
+    };
+    for _ in 0..RESOLVE_THREADS.min(count) {
+        spawn_worker();
+    }
+
+    let mut running: std::collections::HashMap<usize, Instant> = std::collections::HashMap::new();
+    let mut finished = 0;
+    while finished < count {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        let wait = running
+            .values()
+            .map(|started| (*started + per_item).saturating_duration_since(now))
+            .min()
+            .unwrap_or(per_item)
+            .min(deadline - now);
+
+        match event_rx.recv_timeout(wait) {
+            Ok(ResolveEvent::Started(index)) => {
+                running.insert(index, Instant::now());
+            }
+            Ok(ResolveEvent::Done(index, path)) => {
+                // Late answers for abandoned items are dropped
+                if running.remove(&index).is_some() {
+                    results[index] = path;
+                    finished += 1;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        let before = running.len();
+        running.retain(|_, started| now.duration_since(*started) < per_item);
+        let hung = before - running.len();
+        finished += hung;
+        for _ in 0..hung {
+            spawn_worker();
+        }
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    results
+}
+
+/// The File object type index never changes while the system is up
+#[cfg(windows)]
+fn file_object_type_index() -> Option<u8> {
+    static FILE_TYPE_INDEX: std::sync::OnceLock<Option<u8>> = std::sync::OnceLock::new();
+    *FILE_TYPE_INDEX.get_or_init(detect_file_object_type_index)
+}
+
 /// 运行时检测 File 对象的 object_type_index（不同 Windows 版本值不同）。
 /// 通过打开 NUL 设备获取一个已知的 File 句柄，然后在系统句柄表中找到它的 type index。
 #[cfg(windows)]
@@ -1103,11 +2861,6 @@ fn query_system_handles() -> io::Result<Vec<u8>> {
     ))
 }
 
-#[cfg(not(windows))]
-pub fn force_close_file_handles(_paths: &[PathBuf], _verbose: bool) -> io::Result<usize> {
-    Ok(0)
-}
-
 // ============================================================================
 // SSD / HDD detection via IOCTL_STORAGE_QUERY_PROPERTY
 // ============================================================================
@@ -1193,3 +2946,261 @@ pub fn is_ssd_drive(path: &Path) -> bool {
 pub fn is_ssd_drive(_path: &Path) -> bool {
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_retry_policy_delays() {
+        let ramp = RetryPolicy::default();
+        let delays: Vec<u64> = (1..=5)
+            .map(|retry| ramp.delay_before(retry).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![0, 1, 5, 10, 10]);
+
+        let empty = RetryPolicy {
+            max_retries: 2,
+            delays_ms: Vec::new(),
+            network: false,
+        };
+        assert_eq!(empty.delay_before(2), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_unc_path_detection() {
+        assert!(is_unc_path(r"\\server\share\dir"));
+        assert!(is_unc_path(r"\\?\UNC\server\share"));
+        assert!(is_unc_path(r"\\?\unc\server\share"));
+        assert!(!is_unc_path(r"\\?\C:\dir"));
+        assert!(!is_unc_path(r"\\.\PhysicalDrive0"));
+        assert!(!is_unc_path(r"C:\dir"));
+        assert!(!is_unc_path("/tmp/dir"));
+
+        assert!(RetryPolicy::for_path(Path::new(r"\\server\share")).network);
+        assert!(!RetryPolicy::for_path(Path::new("/tmp/dir")).network);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_cloud_reparse_tags_are_not_links() {
+        assert!(is_cloud_reparse_tag(0x9000_001A));
+        assert!(is_cloud_reparse_tag(0x9000_301A)); // OneDrive
+        assert!(is_cloud_reparse_tag(0x9000_F01A));
+        assert!(!is_cloud_reparse_tag(IO_REPARSE_TAG_MOUNT_POINT));
+        assert!(!is_cloud_reparse_tag(0xA000_000C)); // symlink
+    }
+
+    #[test]
+    fn test_reparse_kinds() {
+        assert_eq!(ReparseKind::from_tag(0xA000_000C), ReparseKind::Symlink);
+        assert_eq!(ReparseKind::from_tag(0xA000_0003), ReparseKind::Junction);
+        assert_eq!(ReparseKind::from_tag(0x8000_001B), ReparseKind::Other); // app exec link
+        let wide = |s: &str| s.encode_utf16().collect::<Vec<u16>>();
+        assert!(is_volume_target(&wide(
+            r"\??\Volume{2eca078d-5cbc-43d3-aff8-7e8511f60d0e}\"
+        )));
+        assert!(!is_volume_target(&wide(r"\??\C:\Users\dev\src")));
+    }
+
+    #[test]
+    fn test_classify_error() {
+        use crate::error::ErrorKind;
+
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(classify_error(&denied), ErrorKind::AccessDenied);
+        let missing = io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(classify_error(&missing), ErrorKind::NotFound);
+        let not_empty = io::Error::from(io::ErrorKind::DirectoryNotEmpty);
+        assert_eq!(classify_error(&not_empty), ErrorKind::DirNotEmpty);
+        let other = io::Error::other("boom");
+        assert_eq!(classify_error(&other), ErrorKind::Other(0));
+
+        assert!(check_wide_len(MAX_WIDE_PATH).is_ok());
+        let too_long = check_wide_len(MAX_WIDE_PATH + 1).unwrap_err();
+        assert_eq!(classify_error(&too_long), ErrorKind::PathTooLong);
+        assert!(too_long.to_string().contains("32768 characters"));
+
+        #[cfg(windows)]
+        {
+            let code = |c| classify_error(&io::Error::from_raw_os_error(c));
+            assert_eq!(code(32), ErrorKind::Locked);
+            assert_eq!(code(33), ErrorKind::Locked);
+            assert_eq!(code(5), ErrorKind::AccessDenied);
+            assert_eq!(code(206), ErrorKind::PathTooLong);
+            assert_eq!(code(3), ErrorKind::NotFound);
+            assert_eq!(code(145), ErrorKind::DirNotEmpty);
+            assert_eq!(code(1117), ErrorKind::Other(1117));
+        }
+    }
+
+    fn wide(path: &str) -> String {
+        let wide = str_to_wide(path);
+        assert_eq!(wide.last(), Some(&0));
+        String::from_utf16(&wide[..wide.len() - 1]).unwrap()
+    }
+
+    #[test]
+    fn test_str_to_wide_prefixes() {
+        // Drive paths
+        assert_eq!(wide(r"C:\a\b"), r"\\?\C:\a\b");
+        assert_eq!(wide("C:/a/b"), r"\\?\C:\a\b");
+        // UNC paths
+        assert_eq!(wide(r"\\server\share\dir"), r"\\?\UNC\server\share\dir");
+        assert_eq!(wide("//server/share/dir"), r"\\?\UNC\server\share\dir");
+        // Already prefixed
+        assert_eq!(wide(r"\\?\C:\a"), r"\\?\C:\a");
+        assert_eq!(
+            wide(r"\\?\UNC\server\share\dir"),
+            r"\\?\UNC\server\share\dir"
+        );
+        // Left alone: relative, drive-relative and device paths
+        assert_eq!(wide(r"a\b"), r"a\b");
+        assert_eq!(wide("C:a"), "C:a");
+        assert_eq!(wide(r"\\.\NUL"), r"\\.\NUL");
+        assert_eq!(wide(r"\a\b"), r"\a\b");
+        // The string form matches
+        assert_eq!(
+            normalize_path_str("//server/share"),
+            r"\\?\UNC\server\share"
+        );
+        assert_eq!(normalize_path_str("C:a"), "C:a");
+        // Non-ASCII, and a reused buffer holds only the latest path
+        assert_eq!(wide("C:/données/日本"), r"\\?\C:\données\日本");
+        let mut buffer = Vec::new();
+        normalize_path_wide(r"C:\a\much\longer\path", &mut buffer);
+        normalize_path_wide("C:/b", &mut buffer);
+        assert_eq!(String::from_utf16(&buffer).unwrap(), "\\\\?\\C:\\b\0");
+    }
+
+    #[test]
+    fn test_needs_full_path() {
+        assert!(!needs_full_path(r"C:\a\b"));
+        assert!(!needs_full_path(r"\\server\share\dir"));
+        assert!(!needs_full_path(r"\\?\C:\a\..\b"));
+        assert!(!needs_full_path(r"\\.\NUL"));
+        // A file name that merely starts with dots is fine
+        assert!(!needs_full_path(r"C:\a\..b\.git"));
+        assert!(needs_full_path(r"C:\a\..\b"));
+        assert!(needs_full_path("C:/a/./b"));
+        assert!(needs_full_path(r"a\b"));
+        assert!(needs_full_path("C:a"));
+        assert!(needs_full_path(r"\a\b"));
+    }
+
+    #[test]
+    fn test_overwrite_file_replaces_contents() {
+        let temp = std::env::temp_dir().join("rmx_overwrite_test");
+        let _ = std::fs::remove_dir_all(&temp);
+        std::fs::create_dir_all(&temp).unwrap();
+        let file = temp.join("secret.bin");
+        let original = vec![0x5Au8; 3 * 4096 + 5];
+        std::fs::write(&file, &original).unwrap();
+
+        assert!(overwrite_file(&file, 2).unwrap());
+        let overwritten = std::fs::read(&file).unwrap();
+        assert_eq!(overwritten.len(), original.len());
+        assert_ne!(overwritten, original);
+
+        // The data behind a second hard link is left alone
+        let linked = temp.join("linked.bin");
+        std::fs::write(&linked, &original).unwrap();
+        std::fs::hard_link(&linked, temp.join("other_name.bin")).unwrap();
+        assert!(!overwrite_file(&linked, 1).unwrap());
+        assert_eq!(std::fs::read(&linked).unwrap(), original);
+
+        // Read-only files are made writable rather than failing the shred
+        let readonly = temp.join("readonly.bin");
+        std::fs::write(&readonly, &original).unwrap();
+        let mut perms = std::fs::metadata(&readonly).unwrap().permissions();
+        perms.set_readonly(true);
+        std::fs::set_permissions(&readonly, perms).unwrap();
+        assert!(overwrite_file(&readonly, 1).unwrap());
+        assert_ne!(std::fs::read(&readonly).unwrap(), original);
+
+        let _ = std::fs::remove_dir_all(&temp);
+    }
+
+    /// An operation that fails with a retryable error `failures` times
+    fn flaky(failures: u32, calls: &Cell<u32>) -> impl FnMut() -> io::Result<()> + '_ {
+        move || {
+            calls.set(calls.get() + 1);
+            if calls.get() <= failures {
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_retries_honors_max_retries() {
+        let retryable = |e: &io::Error| e.kind() == io::ErrorKind::WouldBlock;
+        let policy = |max_retries| RetryPolicy {
+            max_retries,
+            delays_ms: vec![0],
+            network: false,
+        };
+
+        // Fails N-1 times, succeeds on attempt N = 1 + max_retries
+        let calls = Cell::new(0);
+        assert!(with_retries(&policy(3), retryable, flaky(3, &calls)).is_ok());
+        assert_eq!(calls.get(), 4);
+
+        // One retry short
+        let calls = Cell::new(0);
+        assert!(with_retries(&policy(2), retryable, flaky(3, &calls)).is_err());
+        assert_eq!(calls.get(), 3);
+
+        // No retries at all
+        let calls = Cell::new(0);
+        assert!(with_retries(&policy(0), retryable, flaky(1, &calls)).is_err());
+        assert_eq!(calls.get(), 1);
+
+        // Non-retryable errors are returned immediately
+        let calls = Cell::new(0);
+        let result = with_retries(&policy(3), |_: &io::Error| false, flaky(3, &calls));
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_resolve_concurrently_abandons_hung_items() {
+        let start = std::time::Instant::now();
+        let results = resolve_concurrently(
+            (0..40).collect(),
+            |item| {
+                if item % 10 == 3 {
+                    thread::sleep(Duration::from_secs(30));
+                }
+                Some(item.to_string())
+            },
+            Duration::from_millis(50),
+            Duration::from_secs(20),
+        );
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        for (item, result) in results.iter().enumerate() {
+            if item % 10 == 3 {
+                assert_eq!(result, &None);
+            } else {
+                assert_eq!(result.as_deref(), Some(item.to_string().as_str()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_alternate_stream_names() {
+        assert_eq!(
+            alternate_stream_name(":Zone.Identifier:$DATA"),
+            Some("Zone.Identifier")
+        );
+        assert_eq!(alternate_stream_name("::$DATA"), None);
+        assert_eq!(alternate_stream_name(":bare"), Some("bare"));
+        assert_eq!(
+            stream_path(Path::new("dl/setup.exe"), "Zone.Identifier"),
+            Path::new("dl/setup.exe:Zone.Identifier")
+        );
+    }
+}
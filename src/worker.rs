@@ -1,21 +1,192 @@
+use crate::backend::{DeleteBackend, PosixBackend};
 use crate::broker::{Broker, WorkItem};
 use crate::error::FailedItem;
+use crate::journal::Journal;
+use crate::profile::{Profiler, WorkerStats};
 use crate::winapi::{
-    delete_file, force_close_file_handles, is_file_in_use_error, is_not_found_error,
-    kill_locking_processes, kill_locking_processes_batch, remove_dir,
+    classify_error, find_locking_processes, is_file_in_use_error, is_not_found_error,
+    kill_locking_processes, kill_locking_processes_batch, overwrite_file,
+    rename_for_deferred_delete, HandleCloser, LockingProcess, RetryPolicy,
 };
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 use crossbeam_queue::SegQueue;
 use rayon::prelude::*;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Backoff after force-closing handles: the owner (e.g. a file watcher) may
+/// reopen the file before a single immediate retry lands.
+const HANDLE_CLOSE_RETRY_DELAYS_MS: [u64; 4] = [0, 10, 50, 150];
+
+/// How often an idle worker wakes up to check for cancellation.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Progress of a deletion, reported as it happens
+#[derive(Debug, Clone, Copy)]
+pub enum ProgressEvent<'a> {
+    /// A directory was removed
+    DirCompleted {
+        path: &'a Path,
+    },
+    FileDeleted {
+        path: &'a Path,
+    },
+    /// Only seen by progress callbacks; a [`ProgressSink`] gets the full
+    /// [`FailedItem`] through [`ProgressSink::on_error`]
+    Failed {
+        path: &'a Path,
+        error: &'a str,
+    },
+    /// Something neither deleted nor failed that is worth showing with
+    /// `--verbose`: the scan starting, a stream left to its file, ...
+    Notice {
+        path: &'a Path,
+        message: &'a str,
+    },
+}
+
+/// Called from worker threads, inline with the deletion
+pub type ProgressCallback = Arc<dyn Fn(ProgressEvent<'_>) + Send + Sync>;
+
+/// Receives the progress of a deletion, for embedding rmx in a UI.
+///
+/// Every progress report goes through a sink; a progress callback is a sink
+/// that only implements [`on_event`](Self::on_event) and
+/// [`on_error`](Self::on_error). `on_dir_complete` and `is_cancelled` are
+/// polled from a monitor thread a few times per second; `on_event` and
+/// `on_error` are called from worker threads as entries go.
+pub trait ProgressSink: Send + Sync {
+    /// `completed` of `total` directories have been removed
+    fn on_dir_complete(&self, completed: usize, total: usize);
+
+    /// An entry was deleted. Runs inline with the deletion, so it should
+    /// return quickly.
+    fn on_event(&self, _event: ProgressEvent<'_>) {}
+
+    fn on_error(&self, _failure: &FailedItem) {}
+
+    /// Return `true` to stop the deletion; it then fails with
+    /// [`crate::error::Error::Cancelled`]
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    /// All workers have stopped
+    fn on_finish(&self) {}
+}
+
+/// A [`ProgressCallback`] as a [`ProgressSink`]; directory counts are not
+/// part of its events
+pub(crate) struct CallbackSink(pub ProgressCallback);
+
+impl ProgressSink for CallbackSink {
+    fn on_dir_complete(&self, _completed: usize, _total: usize) {}
+
+    fn on_event(&self, event: ProgressEvent<'_>) {
+        (self.0)(event);
+    }
+
+    fn on_error(&self, failure: &FailedItem) {
+        (self.0)(ProgressEvent::Failed {
+            path: &failure.path,
+            error: &failure.error,
+        });
+    }
+}
+
+/// Every sink of a deletion behind one; cancelled as soon as any is
+pub(crate) struct SinkSet(pub Vec<Arc<dyn ProgressSink>>);
+
+impl ProgressSink for SinkSet {
+    fn on_dir_complete(&self, completed: usize, total: usize) {
+        self.0
+            .iter()
+            .for_each(|s| s.on_dir_complete(completed, total));
+    }
+
+    fn on_event(&self, event: ProgressEvent<'_>) {
+        self.0.iter().for_each(|s| s.on_event(event));
+    }
+
+    fn on_error(&self, failure: &FailedItem) {
+        self.0.iter().for_each(|s| s.on_error(failure));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.iter().any(|s| s.is_cancelled())
+    }
+
+    fn on_finish(&self) {
+        self.0.iter().for_each(|s| s.on_finish());
+    }
+}
 
 #[derive(Clone)]
 pub struct WorkerConfig {
     pub verbose: bool,
+    /// `false` stops scheduling new work after the first failure (`--fail-fast`)
     pub ignore_errors: bool,
     pub kill_processes: bool,
+    /// Grace period for locking processes to close (`--graceful-kill`);
+    /// `None` terminates them right away
+    pub kill_grace: Option<Duration>,
+    /// Shared by all workers so the system handle table is swept once per
+    /// run rather than once per locked entry
+    pub handle_closer: Arc<HandleCloser>,
+    /// Set to stop all workers; pending work items are dropped unprocessed
+    pub cancel: Arc<AtomicBool>,
+    /// Per-directory timing, only with `--profile`
+    pub profiler: Option<Arc<Profiler>>,
+    /// Items and idle time per worker, only with `--worker-stats`
+    pub worker_stats: Option<Arc<WorkerStats>>,
+    /// Retries for transient sharing violations in `delete_file`/`remove_dir`
+    pub retry: RetryPolicy,
+    /// Overwrite passes before each file is deleted (`--shred`)
+    pub shred: Option<u8>,
+    /// Receives every deleted entry and failure as it happens
+    pub sink: Option<Arc<dyn ProgressSink>>,
+    /// Finished directories are appended here (`--journal`)
+    pub journal: Option<Arc<Journal>>,
+    /// Locked directories renamed aside as `(original, new location)`;
+    /// `None` unless `--rename-locked`
+    pub deferred: Option<Arc<SegQueue<(PathBuf, PathBuf)>>>,
+    /// Cloud placeholder files of the tree being deleted
+    pub placeholders: Arc<HashSet<PathBuf>>,
+    /// How many of `placeholders` were actually deleted
+    pub placeholders_deleted: Arc<AtomicUsize>,
+    /// File lists at least this long are deleted on the rayon pool
+    pub parallel_threshold: usize,
+    /// Minimum files per rayon task when deleting in parallel
+    pub min_chunk_size: usize,
+    /// Removes each file and directory (`--backend`)
+    pub backend: Arc<dyn DeleteBackend>,
+}
+
+impl WorkerConfig {
+    #[inline]
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Count and report a deleted file
+    fn file_deleted(&self, path: &Path) {
+        if self.placeholders.contains(path) {
+            self.placeholders_deleted.fetch_add(1, Ordering::Relaxed);
+        }
+        self.report(ProgressEvent::FileDeleted { path });
+    }
+
+    #[inline]
+    fn report(&self, event: ProgressEvent<'_>) {
+        if let Some(sink) = &self.sink {
+            sink.on_event(event);
+        }
+    }
 }
 
 impl Default for WorkerConfig {
@@ -24,25 +195,59 @@ impl Default for WorkerConfig {
             verbose: false,
             ignore_errors: true,
             kill_processes: false,
+            kill_grace: None,
+            handle_closer: Arc::new(HandleCloser::new(false)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            profiler: None,
+            worker_stats: None,
+            retry: RetryPolicy::default(),
+            shred: None,
+            sink: None,
+            journal: None,
+            deferred: None,
+            placeholders: Arc::default(),
+            placeholders_deleted: Arc::default(),
+            parallel_threshold: parallel_threshold(),
+            min_chunk_size: min_chunk_size(),
+            backend: Arc::new(PosixBackend),
         }
     }
 }
 
 pub struct ErrorTracker {
     failures: SegQueue<FailedItem>,
+    /// Receives a copy of every failure as soon as it is recorded
+    sink: Option<Sender<FailedItem>>,
 }
 
 impl ErrorTracker {
     pub fn new() -> Self {
         Self {
             failures: SegQueue::new(),
+            sink: None,
+        }
+    }
+
+    /// Also forward each failure to `sink` as it happens (for streaming output)
+    pub fn with_sink(sink: Sender<FailedItem>) -> Self {
+        Self {
+            failures: SegQueue::new(),
+            sink: Some(sink),
         }
     }
 
     pub fn record_failure(&self, item: FailedItem) {
+        if let Some(sink) = &self.sink {
+            // The receiver going away only stops streaming, not tracking
+            let _ = sink.send(item.clone());
+        }
         self.failures.push(item);
     }
 
+    pub fn has_failures(&self) -> bool {
+        !self.failures.is_empty()
+    }
+
     pub fn get_failures(&self) -> Vec<FailedItem> {
         let mut result = Vec::new();
         while let Some(item) = self.failures.pop() {
@@ -73,22 +278,49 @@ pub fn spawn_workers(
             let error_tracker = error_tracker.clone();
             thread::Builder::new()
                 .name(format!("worker-{}", i))
-                .spawn(move || worker_thread(rx, broker, config, error_tracker))
+                .spawn(move || worker_thread(i, rx, broker, config, error_tracker))
                 .expect("Failed to spawn worker thread")
         })
         .collect()
 }
 
 fn worker_thread(
+    index: usize,
     rx: Receiver<WorkItem>,
     broker: Arc<Broker>,
     config: WorkerConfig,
     error_tracker: Arc<ErrorTracker>,
 ) {
-    while let Ok(item) = rx.recv() {
+    loop {
+        if config.is_cancelled() || broker.is_aborted() {
+            // Drain without acting; nothing new is scheduled once stopped
+            while rx.try_recv().is_ok() {}
+            return;
+        }
+
+        let wait = Instant::now();
+        let item = match rx.recv_timeout(CANCEL_POLL_INTERVAL) {
+            Ok(item) => item,
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(stats) = &config.worker_stats {
+                    stats.record_idle(index, wait.elapsed());
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        let idle = wait.elapsed();
+
         match item {
             WorkItem::DeleteFiles { files, parent_dir } => {
-                delete_files_from_list(&files, &config, &error_tracker);
+                match &config.profiler {
+                    None => delete_files_from_list(&files, &config, &error_tracker),
+                    Some(profiler) => {
+                        let start = Instant::now();
+                        delete_files_from_list(&files, &config, &error_tracker);
+                        profiler.record_files(&parent_dir, start.elapsed());
+                    }
+                }
                 broker.mark_batch_complete(&parent_dir);
             }
             WorkItem::ProcessDir(dir) => {
@@ -96,6 +328,13 @@ fn worker_thread(
             }
             WorkItem::Shutdown => break,
         }
+        if let Some(stats) = &config.worker_stats {
+            stats.record_item(index, idle, wait.elapsed() - idle);
+        }
+
+        if !config.ignore_errors && error_tracker.has_failures() {
+            broker.abort();
+        }
     }
 }
 
@@ -105,51 +344,114 @@ fn process_directory(
     config: &WorkerConfig,
     error_tracker: &Arc<ErrorTracker>,
 ) {
+    let Some(profiler) = &config.profiler else {
+        if let Some(files) = broker.take_files(dir) {
+            delete_files_from_list(&files, config, error_tracker);
+        }
+        remove_directory(dir, broker, config, error_tracker);
+        return;
+    };
+
+    let start = Instant::now();
     if let Some(files) = broker.take_files(dir) {
         delete_files_from_list(&files, config, error_tracker);
     }
+    let files_time = start.elapsed();
+    let attempted = remove_directory(dir, broker, config, error_tracker);
+    let remove_time = attempted.then(|| start.elapsed() - files_time);
+    profiler.record_dir(dir, files_time, remove_time);
+}
+
+/// Returns `false` if `dir` was kept or the run was cancelled, i.e. no
+/// removal was attempted.
+fn remove_directory(
+    dir: &PathBuf,
+    broker: &Arc<Broker>,
+    config: &WorkerConfig,
+    error_tracker: &Arc<ErrorTracker>,
+) -> bool {
+    if broker.is_kept(dir) {
+        // Its selected contents are gone; only the kept entries remain
+        if let Some(journal) = &config.journal {
+            journal.record(dir);
+        }
+        broker.mark_complete(dir.clone());
+        return false;
+    }
+
+    if config.is_cancelled() {
+        return false;
+    }
 
-    if let Err(e) = remove_dir(dir) {
+    if let Err(e) = config.backend.remove_dir(dir, &config.retry) {
         if is_not_found_error(&e) {
+            if let Some(journal) = &config.journal {
+                journal.record(dir);
+            }
             broker.mark_complete(dir.clone());
-            return;
+            return true;
         }
 
         if config.kill_processes && is_file_in_use_error(&e) {
-            let _ = kill_locking_processes(dir, config.verbose);
-            if let Ok(()) = remove_dir(dir) {
-                broker.mark_complete(dir.clone());
-                return;
+            let _ = kill_locking_processes(dir, config.verbose, config.kill_grace);
+            if let Ok(()) = config.backend.remove_dir(dir, &config.retry) {
+                dir_removed(dir, broker, config);
+                return true;
             }
 
-            let _ = force_close_file_handles(std::slice::from_ref(dir), config.verbose);
-            match remove_dir(dir) {
+            let _ = config.handle_closer.close_for(std::slice::from_ref(dir));
+            match retry_after_handle_close(dir, |p| config.backend.remove_dir(p, &config.retry)) {
                 Ok(()) => {
-                    broker.mark_complete(dir.clone());
-                    return;
+                    dir_removed(dir, broker, config);
+                    return true;
                 }
-                Err(retry_err) if is_not_found_error(&retry_err) => {
+                Err(retry_err) if is_file_in_use_error(&retry_err) => {
+                    if defer_locked(dir, broker, config) {
+                        return true;
+                    }
+                    let msg = reacquired_message(&retry_err, &locking_processes(dir));
+                    record_failure(dir, &retry_err, msg, true, config, error_tracker);
                     broker.mark_complete(dir.clone());
-                    return;
+                    return true;
                 }
                 _ => {}
             }
         }
 
-        let msg = e.to_string();
-        if config.verbose {
-            eprintln!("Warning: Failed to remove {}: {}", dir.display(), msg);
+        if is_file_in_use_error(&e) && defer_locked(dir, broker, config) {
+            return true;
         }
-        error_tracker.record_failure(FailedItem {
-            path: dir.clone(),
-            error: msg,
-            is_dir: true,
-        });
 
+        record_failure(dir, &e, e.to_string(), true, config, error_tracker);
         broker.mark_complete(dir.clone());
-        return;
+        return true;
     }
 
+    dir_removed(dir, broker, config);
+    true
+}
+
+/// With `--rename-locked`, move the locked `dir` out of the way so its path
+/// (and its parent) can be freed now. Returns `false` if that isn't possible.
+fn defer_locked(dir: &PathBuf, broker: &Arc<Broker>, config: &WorkerConfig) -> bool {
+    let Some(deferred) = &config.deferred else {
+        return false;
+    };
+    match rename_for_deferred_delete(dir) {
+        Ok(moved) => {
+            deferred.push((dir.clone(), moved));
+            dir_removed(dir, broker, config);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+fn dir_removed(dir: &PathBuf, broker: &Arc<Broker>, config: &WorkerConfig) {
+    config.report(ProgressEvent::DirCompleted { path: dir });
+    if let Some(journal) = &config.journal {
+        journal.record(dir);
+    }
     broker.mark_complete(dir.clone());
 }
 
@@ -162,7 +464,8 @@ fn cpu_count() -> usize {
     })
 }
 
-fn parallel_threshold() -> usize {
+/// Default [`WorkerConfig::parallel_threshold`] for this machine
+pub fn parallel_threshold() -> usize {
     let cpus = cpu_count();
 
     match cpus {
@@ -173,7 +476,8 @@ fn parallel_threshold() -> usize {
     }
 }
 
-fn min_chunk_size() -> usize {
+/// Default [`WorkerConfig::min_chunk_size`] for this machine
+pub fn min_chunk_size() -> usize {
     let cpus = cpu_count();
     (cpus * 2).clamp(4, 16)
 }
@@ -187,13 +491,30 @@ fn delete_files_from_list(
         return;
     }
 
-    if files.len() < parallel_threshold() {
+    if files.len() < config.parallel_threshold {
         delete_files_sequential(files, config, error_tracker);
     } else {
         delete_files_parallel(files, config, error_tracker);
     }
 }
 
+/// Delete a single file, overwriting its contents first with `--shred`.
+fn delete_one(path: &Path, config: &WorkerConfig) -> io::Result<()> {
+    if let Some(passes) = config.shred {
+        if !overwrite_file(path, passes)? {
+            let message = format!(
+                "not shredded '{}' (link, sparse, compressed or cloud-only)",
+                path.display()
+            );
+            config.report(ProgressEvent::Notice {
+                path,
+                message: &message,
+            });
+        }
+    }
+    config.backend.delete_file(path, &config.retry)
+}
+
 fn delete_files_sequential(
     files: &[PathBuf],
     config: &WorkerConfig,
@@ -202,15 +523,13 @@ fn delete_files_sequential(
     let mut locked_files = Vec::new();
 
     for path in files {
-        if let Err(e) = delete_file(path) {
-            if is_not_found_error(&e) {
-                continue;
-            }
-            if config.kill_processes && is_file_in_use_error(&e) {
+        match delete_one(path, config) {
+            Ok(()) => config.file_deleted(path),
+            Err(e) if is_not_found_error(&e) => {}
+            Err(e) if config.kill_processes && is_file_in_use_error(&e) => {
                 locked_files.push((path.clone(), e));
-            } else {
-                record_file_error(path, &e, config, error_tracker);
             }
+            Err(e) => record_file_error(path, &e, config, error_tracker),
         }
     }
 
@@ -224,9 +543,12 @@ fn delete_files_parallel(
 ) {
     let locked_files: Vec<(PathBuf, std::io::Error)> = files
         .par_iter()
-        .with_min_len(min_chunk_size())
-        .filter_map(|path| match delete_file(path) {
-            Ok(()) => None,
+        .with_min_len(config.min_chunk_size.max(1))
+        .filter_map(|path| match delete_one(path, config) {
+            Ok(()) => {
+                config.file_deleted(path);
+                None
+            }
             Err(e) if is_not_found_error(&e) => None,
             Err(e) => {
                 if config.kill_processes && is_file_in_use_error(&e) {
@@ -249,15 +571,76 @@ fn record_file_error(
     config: &WorkerConfig,
     error_tracker: &Arc<ErrorTracker>,
 ) {
-    let msg = error.to_string();
+    record_failure(path, error, error.to_string(), false, config, error_tracker);
+}
+
+fn record_failure(
+    path: &Path,
+    error: &io::Error,
+    msg: String,
+    is_dir: bool,
+    config: &WorkerConfig,
+    error_tracker: &Arc<ErrorTracker>,
+) {
     if config.verbose {
-        eprintln!("Warning: Failed to delete {}: {}", path.display(), msg);
+        let action = if is_dir { "remove" } else { "delete" };
+        eprintln!("Warning: Failed to {} {}: {}", action, path.display(), msg);
+    }
+    if let Some(journal) = &config.journal {
+        journal.record_failure(path);
     }
-    error_tracker.record_failure(FailedItem {
+    let item = FailedItem {
         path: path.to_path_buf(),
         error: msg,
-        is_dir: false,
-    });
+        is_dir,
+        kind: classify_error(error),
+    };
+    if let Some(sink) = &config.sink {
+        sink.on_error(&item);
+    }
+    error_tracker.record_failure(item);
+}
+
+/// Retry `op` with backoff after handles on `path` were force-closed.
+/// Gives up early on anything other than a lock error.
+fn retry_after_handle_close<F>(path: &Path, mut op: F) -> io::Result<()>
+where
+    F: FnMut(&Path) -> io::Result<()>,
+{
+    let mut last_error = None;
+    for &delay_ms in &HANDLE_CLOSE_RETRY_DELAYS_MS {
+        if delay_ms > 0 {
+            thread::sleep(Duration::from_millis(delay_ms));
+        }
+        match op(path) {
+            Ok(()) => return Ok(()),
+            Err(e) if is_not_found_error(&e) => return Ok(()),
+            Err(e) if is_file_in_use_error(&e) => last_error = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::other("max retries exceeded")))
+}
+
+fn locking_processes(path: &Path) -> Vec<LockingProcess> {
+    find_locking_processes(path).unwrap_or_default()
+}
+
+/// Failure message for a path whose handle was closed but reopened
+/// before the retry could delete it.
+fn reacquired_message(error: &io::Error, holders: &[LockingProcess]) -> String {
+    if holders.is_empty() {
+        return format!("handle closed but re-acquired: {}", error);
+    }
+    let names: Vec<String> = holders
+        .iter()
+        .map(|p| format!("{} (PID {})", p.name, p.pid))
+        .collect();
+    format!(
+        "handle closed but re-acquired by {}: {}",
+        names.join(", "),
+        error
+    )
 }
 
 fn handle_locked_files(
@@ -271,10 +654,13 @@ fn handle_locked_files(
 
     let mut paths: Vec<PathBuf> = locked_files.into_iter().map(|(p, _)| p).collect();
 
-    let _ = kill_locking_processes_batch(&paths, config.verbose);
+    let _ = kill_locking_processes_batch(&paths, config.verbose, config.kill_grace);
 
-    paths.retain(|path| match delete_file(path) {
-        Ok(()) => false,
+    paths.retain(|path| match delete_one(path, config) {
+        Ok(()) => {
+            config.file_deleted(path);
+            false
+        }
         Err(e) if is_not_found_error(&e) => false,
         Err(e) if is_file_in_use_error(&e) => true,
         Err(e) => {
@@ -287,13 +673,185 @@ fn handle_locked_files(
         return;
     }
 
-    let _ = force_close_file_handles(&paths, config.verbose);
+    let _ = config.handle_closer.close_for(&paths);
 
     for path in &paths {
-        if let Err(e) = delete_file(path) {
-            if !is_not_found_error(&e) {
-                record_file_error(path, &e, config, error_tracker);
+        match retry_after_handle_close(path, |p| delete_one(p, config)) {
+            Ok(()) => config.file_deleted(path),
+            Err(e) if is_file_in_use_error(&e) => {
+                let msg = reacquired_message(&e, &locking_processes(path));
+                record_failure(path, &e, msg, false, config, error_tracker);
             }
+            Err(e) => record_file_error(path, &e, config, error_tracker),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn sharing_violation() -> io::Error {
+        io::Error::from_raw_os_error(32)
+    }
+
+    #[test]
+    fn test_retry_after_handle_close_survives_brief_reacquire() {
+        let attempts = Cell::new(0);
+        let result = retry_after_handle_close(Path::new("x"), |_| {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 3 {
+                Err(sharing_violation())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn test_cancelled_workers_exit_without_deleting() {
+        let temp = std::env::temp_dir().join("rmx_worker_cancel_test");
+        let _ = std::fs::remove_dir_all(&temp);
+        std::fs::create_dir_all(temp.join("a/b")).unwrap();
+        std::fs::write(temp.join("a/b/file.txt"), "x").unwrap();
+
+        let tree = crate::tree::discover_tree(&temp).unwrap();
+        let (broker, rx) = Broker::new(tree, 2);
+        let cancel = Arc::new(AtomicBool::new(true));
+        let broker = Arc::new(broker.with_cancel(cancel.clone()));
+        let config = WorkerConfig {
+            cancel,
+            ..Default::default()
+        };
+
+        let handles = spawn_workers(2, rx, broker, config, Arc::new(ErrorTracker::new()));
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!(temp.join("a/b/file.txt").exists());
+        let _ = std::fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_only_deleted_placeholders_are_counted() {
+        let temp = std::env::temp_dir().join("rmx_worker_placeholder_test");
+        let _ = std::fs::remove_dir_all(&temp);
+        std::fs::create_dir_all(&temp).unwrap();
+        let (present, gone) = (temp.join("present.txt"), temp.join("gone.txt"));
+        std::fs::write(&present, "x").unwrap();
+
+        let config = WorkerConfig {
+            placeholders: Arc::new([present.clone(), gone.clone()].into_iter().collect()),
+            ..Default::default()
+        };
+        delete_files_from_list(&[present, gone], &config, &Arc::new(ErrorTracker::new()));
+
+        assert_eq!(config.placeholders_deleted.load(Ordering::Relaxed), 1);
+        let _ = std::fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn test_reacquired_message_names_holder() {
+        let attempts = Cell::new(0);
+        let err = retry_after_handle_close(Path::new("x"), |_| {
+            attempts.set(attempts.get() + 1);
+            Err(sharing_violation())
+        })
+        .unwrap_err();
+        assert_eq!(attempts.get(), HANDLE_CLOSE_RETRY_DELAYS_MS.len());
+
+        let holders = vec![LockingProcess {
+            pid: 4242,
+            name: "watcher.exe".to_string(),
+            exe_path: None,
+        }];
+        let msg = reacquired_message(&err, &holders);
+        assert!(
+            msg.starts_with("handle closed but re-acquired by watcher.exe (PID 4242)"),
+            "{}",
+            msg
+        );
+    }
+
+    /// A real process that grabs the file again as soon as its handle is
+    /// closed: the retries must give up and name it as the holder.
+    #[cfg(windows)]
+    #[test]
+    fn test_handle_reacquired_by_child_process() {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+        use std::sync::mpsc;
+
+        let temp = std::env::temp_dir().join("rmx_worker_reacquire_test");
+        let _ = std::fs::remove_dir_all(&temp);
+        std::fs::create_dir_all(&temp).unwrap();
+        let file = temp.join("held.txt");
+        std::fs::write(&file, "x").unwrap();
+
+        // Opens the file without sharing, says so, and polls the handle;
+        // once it is closed from outside, the next poll fails and it reopens
+        let script = format!(
+            r#"
+            $p = '{}'
+            while ($true) {{
+                try {{ $f = [IO.File]::Open($p, 'Open', 'ReadWrite', 'None') }}
+                catch {{ Start-Sleep -Milliseconds 5; continue }}
+                [Console]::Out.WriteLine('open'); [Console]::Out.Flush()
+                while ($true) {{
+                    try {{ $null = $f.Length; Start-Sleep -Milliseconds 5 }} catch {{ break }}
+                }}
+            }}
+        "#,
+            file.display().to_string().replace('\'', "''")
+        );
+        let mut child = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to start powershell");
+
+        let (tx, rx) = mpsc::channel();
+        let stdout = child.stdout.take().unwrap();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let wait_open = || rx.recv_timeout(Duration::from_secs(30)).ok();
+
+        let result = (|| {
+            wait_open().ok_or("child never opened the file")?;
+            let closer = HandleCloser::new(false);
+            let closed = closer.close_for(std::slice::from_ref(&file));
+            if !matches!(closed, Ok(n) if n > 0) {
+                return Err("no handle of the child was closed");
+            }
+            // The child noticed and holds the file again
+            wait_open().ok_or("child never reopened the file")?;
+            let config = WorkerConfig::default();
+            let err = retry_after_handle_close(&file, |p| delete_one(p, &config))
+                .err()
+                .ok_or("deleted a file the child holds")?;
+            assert!(is_file_in_use_error(&err), "{}", err);
+            Ok(reacquired_message(&err, &locking_processes(&file)))
+        })();
+
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = std::fs::remove_dir_all(&temp);
+
+        let msg = result.unwrap();
+        assert!(
+            msg.starts_with("handle closed but re-acquired by "),
+            "{}",
+            msg
+        );
+        assert!(msg.contains(&format!("(PID {})", child.id())), "{}", msg);
+    }
+}
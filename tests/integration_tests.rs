@@ -158,6 +158,105 @@ fn test_system_directory_protected() {
     }
 }
 
+#[test]
+fn test_rmx_protect_blocks_listed_paths() {
+    let test_dir = create_test_dir("rmx_protect");
+    let shared = test_dir.join("shared");
+    let repo = test_dir.join("repo");
+    fs::create_dir_all(&shared).unwrap();
+    fs::create_dir_all(repo.join("src")).unwrap();
+    fs::write(repo.join("src/main.rs"), "fn main() {}").unwrap();
+    let protect = std::env::join_paths([
+        shared.clone(),
+        PathBuf::from(format!("{}{}*", repo.display(), std::path::MAIN_SEPARATOR)),
+    ])
+    .unwrap();
+
+    for target in [shared.clone(), repo.join("src"), repo.join("src/main.rs")] {
+        let output = Command::new(rmx_path())
+            .args(["-rf"])
+            .arg(&target)
+            .env("RMX_PROTECT", &protect)
+            .output()
+            .expect("Failed to execute rmx");
+        assert!(!output.status.success(), "{}", target.display());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("RMX_PROTECT"));
+        assert!(target.exists());
+    }
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--no-preserve-root"])
+        .arg(&shared)
+        .env("RMX_PROTECT", &protect)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!shared.exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_check_subcommand_reports_verdicts() {
+    let test_dir = create_test_dir("check_subcommand");
+    let missing = test_dir.join("missing");
+
+    let output = Command::new(rmx_path())
+        .arg("check")
+        .arg(&test_dir)
+        .arg(&missing)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(" (dir): safe"), "{}", stdout);
+    assert!(stdout.contains("missing: not found"), "{}", stdout);
+    assert!(test_dir.exists(), "check must not delete");
+
+    #[cfg(windows)]
+    let system = PathBuf::from("C:\\Windows");
+    #[cfg(not(windows))]
+    let system = PathBuf::from("/usr");
+    let output = Command::new(rmx_path())
+        .arg("check")
+        .arg(&test_dir)
+        .arg(&system)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(cannot override)"));
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_ignore_nonexistent_keeps_prompt() {
+    let test_dir = create_test_dir("ignore_nonexistent");
+    let missing = test_dir.join("missing");
+    let file = test_dir.join("file.txt");
+    fs::write(&file, "keep").unwrap();
+
+    let output = Command::new(rmx_path())
+        .arg("--ignore-nonexistent")
+        .arg(&missing)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+
+    // Without -f the existing file is still confirmed; closed stdin declines
+    let output = Command::new(rmx_path())
+        .arg("--ignore-nonexistent")
+        .arg(&missing)
+        .arg(&file)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(file.exists());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("No such file"));
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
 #[test]
 fn test_multiple_directories() {
     let dir1 = create_test_dir("multi1");
@@ -324,3 +423,1583 @@ fn test_directory_requires_recursive() {
     assert!(test_dir.exists(), "Directory should still exist");
     fs::remove_dir_all(&test_dir).ok();
 }
+
+#[test]
+fn test_probe_json_does_not_delete() {
+    let test_dir = create_test_dir("probe");
+    let file_path = test_dir.join("a.txt");
+    fs::write(&file_path, "hello").unwrap();
+    let sub_dir = test_dir.join("sub");
+    fs::create_dir_all(&sub_dir).unwrap();
+    let missing = test_dir.join("missing");
+
+    let list = test_dir.join("list.txt");
+    fs::write(
+        &list,
+        format!(
+            "{}\n{}\n\n{}\n",
+            file_path.display(),
+            sub_dir.display(),
+            missing.display()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["probe", "--format", "json", "--files-from"])
+        .arg(&list)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["kind"], "file");
+    assert_eq!(records[0]["size"], 5);
+    assert_eq!(records[1]["kind"], "dir");
+    assert_eq!(records[2]["exists"], false);
+    assert!(records[2]["kind"].is_null());
+
+    assert!(file_path.exists() && sub_dir.exists());
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+fn set_mtime(path: &PathBuf, age: std::time::Duration) {
+    let f = File::options().write(true).open(path).unwrap();
+    f.set_modified(std::time::SystemTime::now() - age).unwrap();
+}
+
+#[test]
+fn test_older_than_file_keeps_newer_entries() {
+    let test_dir = create_test_dir("older_than_file");
+    let hour = std::time::Duration::from_secs(3600);
+
+    let old_file = test_dir.join("old.txt");
+    let new_file = test_dir.join("new.txt");
+    let old_sub = test_dir.join("old_sub");
+    let mixed_sub = test_dir.join("mixed_sub");
+    fs::create_dir_all(&old_sub).unwrap();
+    fs::create_dir_all(&mixed_sub).unwrap();
+    for f in [
+        &old_file,
+        &new_file,
+        &old_sub.join("a.txt"),
+        &mixed_sub.join("old.txt"),
+        &mixed_sub.join("new.txt"),
+    ] {
+        fs::write(f, "x").unwrap();
+    }
+    set_mtime(&old_file, hour * 3);
+    set_mtime(&old_sub.join("a.txt"), hour * 3);
+    set_mtime(&mixed_sub.join("old.txt"), hour * 3);
+
+    let marker = std::env::temp_dir().join("rmx_test_older_than_file.stamp");
+    fs::write(&marker, "stamp").unwrap();
+    set_mtime(&marker, hour);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--older-than-file"])
+        .arg(&marker)
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    assert!(!old_file.exists());
+    assert!(!old_sub.exists(), "fully old directory should be removed");
+    assert!(!mixed_sub.join("old.txt").exists());
+    assert!(new_file.exists());
+    assert!(mixed_sub.join("new.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+    fs::remove_file(&marker).ok();
+}
+
+#[test]
+fn test_older_than_age_keeps_recent_files() {
+    let test_dir = create_test_dir("older_than_age");
+    let day = std::time::Duration::from_secs(24 * 3600);
+
+    let stale_sub = test_dir.join("stale");
+    let mixed_sub = test_dir.join("mixed");
+    fs::create_dir_all(&stale_sub).unwrap();
+    fs::create_dir_all(&mixed_sub).unwrap();
+    for f in [
+        &stale_sub.join("a.bin"),
+        &mixed_sub.join("old.bin"),
+        &mixed_sub.join("fresh.bin"),
+    ] {
+        fs::write(f, "x").unwrap();
+    }
+    set_mtime(&stale_sub.join("a.bin"), day * 10);
+    set_mtime(&mixed_sub.join("old.bin"), day * 8);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--older-than", "7d"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    assert!(!stale_sub.exists(), "emptied directory should be removed");
+    assert!(!mixed_sub.join("old.bin").exists());
+    assert!(mixed_sub.join("fresh.bin").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--older-than", "7 days"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(mixed_sub.join("fresh.bin").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_size_filters_select_files_by_size() {
+    let test_dir = create_test_dir("size_filters");
+    let big_sub = test_dir.join("big");
+    let mixed_sub = test_dir.join("mixed");
+    fs::create_dir_all(&big_sub).unwrap();
+    fs::create_dir_all(&mixed_sub).unwrap();
+    fs::write(big_sub.join("a.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(mixed_sub.join("large.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(mixed_sub.join("small.txt"), "x").unwrap();
+    fs::write(mixed_sub.join("medium.txt"), vec![0u8; 1024]).unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--larger-than", "1K"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!big_sub.exists(), "emptied directory should be removed");
+    assert!(!mixed_sub.join("large.bin").exists());
+    // The limit itself is not "larger than"
+    assert!(mixed_sub.join("medium.txt").exists());
+    assert!(mixed_sub.join("small.txt").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--smaller-than", "1k"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!mixed_sub.join("small.txt").exists());
+    assert!(mixed_sub.join("medium.txt").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--larger-than", "1.5M"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(mixed_sub.join("medium.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_no_recurse_hidden_keeps_hidden_dirs() {
+    let test_dir = create_test_dir("no_recurse_hidden");
+    let hidden_dir = test_dir.join(".config");
+    fs::create_dir_all(hidden_dir.join("nested")).unwrap();
+    fs::write(hidden_dir.join("settings.json"), "{}").unwrap();
+    fs::write(hidden_dir.join("nested/state"), "1").unwrap();
+    let hidden_file = test_dir.join(".stray");
+    fs::write(&hidden_file, "x").unwrap();
+    let visible_dir = test_dir.join("build");
+    fs::create_dir_all(&visible_dir).unwrap();
+    fs::write(visible_dir.join("out.o"), "o").unwrap();
+
+    #[cfg(windows)]
+    for p in [&hidden_dir, &hidden_file] {
+        Command::new("attrib").arg("+h").arg(p).status().unwrap();
+    }
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--no-recurse-hidden"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    assert!(!hidden_file.exists(), "hidden file should be removed");
+    assert!(!visible_dir.exists());
+    assert!(hidden_dir.join("settings.json").exists());
+    assert!(hidden_dir.join("nested/state").exists());
+    assert!(test_dir.exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_hard_link_aware_counts_shared_data_once() {
+    let test_dir = create_test_dir("hard_link_aware");
+    let outside = std::env::temp_dir().join("rmx_test_hard_link_outside.bin");
+    let _ = fs::remove_file(&outside);
+
+    fs::write(test_dir.join("a.bin"), vec![0u8; 100]).unwrap();
+    fs::hard_link(test_dir.join("a.bin"), test_dir.join("b.bin")).unwrap();
+    fs::write(test_dir.join("c.bin"), vec![0u8; 50]).unwrap();
+    fs::hard_link(test_dir.join("c.bin"), &outside).unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rfn", "--stats", "--hard-link-aware"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Size:        250 B"), "{}", stdout);
+    assert!(stdout.contains("Freed:       100 B"), "{}", stdout);
+
+    fs::remove_dir_all(&test_dir).ok();
+    fs::remove_file(&outside).ok();
+}
+
+#[test]
+fn test_size_by_dir_reports_sorted_totals() {
+    let test_dir = create_test_dir("size_by_dir");
+    fs::create_dir_all(test_dir.join("small")).unwrap();
+    fs::create_dir_all(test_dir.join("large/nested")).unwrap();
+    fs::create_dir_all(test_dir.join("medium")).unwrap();
+    fs::write(test_dir.join("small/a.bin"), vec![0u8; 10]).unwrap();
+    fs::write(test_dir.join("large/b.bin"), vec![0u8; 200]).unwrap();
+    fs::write(test_dir.join("large/nested/c.bin"), vec![0u8; 300]).unwrap();
+    fs::write(test_dir.join("medium/d.bin"), vec![0u8; 100]).unwrap();
+    fs::write(test_dir.join("root.bin"), vec![0u8; 5]).unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["size", "--by-dir"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Total:   615 B (5 files, 5 directories)"),
+        "{}",
+        stdout
+    );
+
+    let order: Vec<&str> = stdout
+        .lines()
+        .filter(|line| !line.starts_with("Total:"))
+        .map(|line| line.split_whitespace().last().unwrap())
+        .collect();
+    assert_eq!(order.len(), 3, "{}", stdout);
+    assert!(order[0].ends_with("large"));
+    assert!(order[1].ends_with("medium"));
+    assert!(order[2].ends_with("small"));
+    assert!(test_dir.join("root.bin").exists(), "size must not delete");
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_keep_preserves_named_children() {
+    let test_dir = create_test_dir("keep_names");
+    fs::create_dir_all(test_dir.join("cache/inner")).unwrap();
+    fs::create_dir_all(test_dir.join("build/out")).unwrap();
+    fs::write(test_dir.join("cache/inner/data.bin"), "keep").unwrap();
+    fs::write(test_dir.join("build/out/obj.o"), "drop").unwrap();
+    fs::write(test_dir.join("notes.txt"), "keep").unwrap();
+    fs::write(test_dir.join("log.txt"), "drop").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--keep", "cache", "--keep", "notes.txt"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(test_dir.join("cache/inner/data.bin").exists());
+    assert!(test_dir.join("notes.txt").exists());
+    assert!(!test_dir.join("build").exists());
+    assert!(!test_dir.join("log.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_contents_only_keeps_directory() {
+    let test_dir = create_test_dir("contents_only");
+    fs::create_dir_all(test_dir.join("out/nested")).unwrap();
+    fs::write(test_dir.join("out/nested/obj.o"), "drop").unwrap();
+    fs::write(test_dir.join("out.log"), "drop").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rfv", "--contents-only"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(kept directory"));
+    assert!(test_dir.is_dir());
+    assert_eq!(fs::read_dir(&test_dir).unwrap().count(), 0);
+
+    // A file operand has no contents to empty
+    let file = test_dir.join("file.txt");
+    fs::write(&file, "keep").unwrap();
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--contents-only"])
+        .arg(&file)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(file.exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_empty_only_prunes_empty_directories() {
+    let test_dir = create_test_dir("empty_only");
+    fs::create_dir_all(test_dir.join("empty/nested")).unwrap();
+    fs::create_dir_all(test_dir.join("full/hollow")).unwrap();
+    fs::write(test_dir.join("full/data.txt"), "keep").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--empty-only", "--dry-run"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("would remove 3 empty director(ies)"),
+        "{}",
+        stdout
+    );
+    assert!(test_dir.join("empty/nested").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--empty-only"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 non-empty left"), "{}", stdout);
+    assert!(!test_dir.join("empty").exists());
+    assert!(!test_dir.join("full/hollow").exists());
+    assert!(test_dir.join("full/data.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_clean_lists_then_deletes_artifacts() {
+    let test_dir = create_test_dir("clean");
+    fs::create_dir_all(test_dir.join("web/node_modules/pkg")).unwrap();
+    fs::write(test_dir.join("web/node_modules/pkg/index.js"), "js").unwrap();
+    fs::create_dir_all(test_dir.join("app/target/debug")).unwrap();
+    fs::create_dir_all(test_dir.join("app/src")).unwrap();
+    fs::write(test_dir.join("app/src/main.rs"), "fn main() {}").unwrap();
+
+    // Without -f and without a terminal, only the list is shown
+    let output = Command::new(rmx_path())
+        .arg("clean")
+        .arg(&test_dir)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 director(ies) found"), "{}", stdout);
+    assert!(test_dir.join("web/node_modules").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["clean", "-f", "--pattern", "target"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.join("app/target").exists());
+    assert!(test_dir.join("web/node_modules").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["clean", "-f"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.join("web/node_modules").exists());
+    assert!(test_dir.join("app/src/main.rs").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_relative_operand_into_long_path_tree() {
+    let test_dir = create_test_dir("relative_long_path");
+    let segment = "d".repeat(50);
+    let mut deep = test_dir.join("tree");
+    for _ in 0..6 {
+        deep.push(&segment);
+    }
+    fs::create_dir_all(&deep).unwrap();
+    fs::write(deep.join("file.txt"), "deep").unwrap();
+    assert!(deep.as_os_str().len() > 260);
+
+    // Both a plain relative operand and one with `.`/`..` components
+    fs::create_dir_all(test_dir.join("other")).unwrap();
+    for operand in ["tree", "other/../tree/."] {
+        if operand != "tree" {
+            fs::create_dir_all(&deep).unwrap();
+        }
+        let output = Command::new(rmx_path())
+            .current_dir(&test_dir)
+            .args(["-rf", operand])
+            .output()
+            .expect("Failed to execute rmx");
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            operand,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(!test_dir.join("tree").exists(), "{}", operand);
+    }
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_dry_run_tree_lists_sorted_entries() {
+    let test_dir = create_test_dir("dry_run_tree");
+    fs::create_dir_all(test_dir.join("b")).unwrap();
+    fs::create_dir_all(test_dir.join("a")).unwrap();
+    fs::write(test_dir.join("z.txt"), "z").unwrap();
+    fs::write(test_dir.join("b/y.txt"), "y").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rn", "--tree"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let listing: Vec<&str> = stdout.lines().skip(1).collect();
+    assert_eq!(
+        listing,
+        ["  a/", "  b/", "    y.txt", "  z.txt"],
+        "{}",
+        stdout
+    );
+
+    let output = Command::new(rmx_path())
+        .args(["-rn", "--tree", "--max-list", "1"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("  a/\n... and 3 more\n"), "{}", stdout);
+    assert!(test_dir.join("b/y.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_dry_run_check_flags_locked_file() {
+    let test_dir = create_test_dir("dry_run_check");
+    fs::create_dir_all(test_dir.join("sub")).unwrap();
+    fs::write(test_dir.join("sub/free.txt"), "free").unwrap();
+    fs::write(test_dir.join("sub/locked.txt"), "locked").unwrap();
+
+    // Exclusive open (no sharing) blocks DELETE access from other handles
+    #[cfg(windows)]
+    let _lock = {
+        use std::os::windows::fs::OpenOptionsExt;
+        fs::OpenOptions::new()
+            .read(true)
+            .share_mode(0)
+            .open(test_dir.join("sub/locked.txt"))
+            .unwrap()
+    };
+
+    let output = Command::new(rmx_path())
+        .args(["-rn", "--check", "--stats"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(test_dir.join("sub/locked.txt").exists());
+    assert!(test_dir.join("sub/free.txt").exists());
+    assert!(!stdout.contains("free.txt"), "{}", stdout);
+
+    #[cfg(windows)]
+    {
+        assert!(stdout.contains("would fail"), "{}", stdout);
+        assert!(stdout.contains("locked.txt"), "{}", stdout);
+        assert!(stdout.contains("Blocked:     1"), "{}", stdout);
+    }
+    #[cfg(not(windows))]
+    assert!(stdout.contains("Blocked:     0"), "{}", stdout);
+
+    #[cfg(windows)]
+    drop(_lock);
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_recycle_never_falls_back_to_permanent_delete() {
+    // Off Windows (and on volumes without a Recycle Bin) --recycle must
+    // refuse instead of deleting permanently.
+    #[cfg(not(windows))]
+    {
+        let test_dir = create_test_dir("recycle_refuse");
+        fs::write(test_dir.join("file.txt"), "data").unwrap();
+
+        let output = Command::new(rmx_path())
+            .args(["-rf", "--recycle"])
+            .arg(&test_dir)
+            .output()
+            .expect("Failed to execute rmx");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Recycle Bin"), "{}", stderr);
+        assert!(test_dir.join("file.txt").exists());
+
+        fs::remove_dir_all(&test_dir).ok();
+    }
+
+    // With a Recycle Bin (the temp directory is on a fixed drive), a move
+    // the shell can't complete must fail rather than delete: an exclusive
+    // handle keeps the file from being moved
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        let test_dir = create_test_dir("recycle_refuse");
+        let file = test_dir.join("file.txt");
+        fs::write(&file, "data").unwrap();
+        let lock = fs::OpenOptions::new()
+            .read(true)
+            .share_mode(0)
+            .open(&file)
+            .unwrap();
+
+        let output = Command::new(rmx_path())
+            .args(["-rf", "--recycle"])
+            .arg(&test_dir)
+            .output()
+            .expect("Failed to execute rmx");
+
+        assert!(
+            !output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        assert!(file.exists());
+
+        drop(lock);
+        fs::remove_dir_all(&test_dir).ok();
+    }
+}
+
+#[test]
+fn test_from_file_utf16_and_stdin_null_separated() {
+    let test_dir = create_test_dir("from_file");
+    let a = test_dir.join("a.txt");
+    let b = test_dir.join("dir b");
+    let c = test_dir.join("c.txt");
+    let keep = test_dir.join("keep.txt");
+    fs::write(&a, "a").unwrap();
+    fs::create_dir_all(&b).unwrap();
+    fs::write(b.join("inner.txt"), "b").unwrap();
+    fs::write(&c, "c").unwrap();
+    fs::write(&keep, "keep").unwrap();
+
+    // UTF-16LE with BOM and CRLF line endings, as Windows tools write it
+    let list = test_dir.join("list.txt");
+    let text = format!("{}\r\n\r\n{}\r\n", a.display(), b.display());
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_le_bytes());
+    }
+    fs::write(&list, bytes).unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--from-file"])
+        .arg(&list)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!a.exists());
+    assert!(!b.exists());
+    assert!(c.exists());
+
+    let mut child = Command::new(rmx_path())
+        .args(["-f", "-0", "-"])
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute rmx");
+    let input = format!("{}\0", c.display());
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+    assert!(!c.exists());
+    assert!(keep.exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_exclude_globs_preserve_matches() {
+    let test_dir = create_test_dir("exclude_globs");
+    fs::create_dir_all(test_dir.join("pkg/node_modules/.cache/babel")).unwrap();
+    fs::create_dir_all(test_dir.join("pkg/node_modules/lodash")).unwrap();
+    fs::create_dir_all(test_dir.join("out")).unwrap();
+    fs::write(test_dir.join("pkg/node_modules/.cache/babel/x.json"), "{}").unwrap();
+    fs::write(test_dir.join("pkg/node_modules/lodash/index.js"), "js").unwrap();
+    fs::write(test_dir.join("out/build.log"), "log").unwrap();
+    fs::write(test_dir.join("out/.gitkeep"), "").unwrap();
+    fs::write(test_dir.join("out/app.bin"), "bin").unwrap();
+
+    let excludes = [
+        "--exclude",
+        "**/node_modules/.cache/**",
+        "--exclude",
+        "*.log",
+        "--exclude",
+        ".gitkeep",
+    ];
+
+    let output = Command::new(rmx_path())
+        .args(["-rnv"])
+        .args(excludes)
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("build.log' (excluded)"), "{}", stdout);
+    assert!(stdout.contains(".gitkeep' (excluded)"), "{}", stdout);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf"])
+        .args(excludes)
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(test_dir
+        .join("pkg/node_modules/.cache/babel/x.json")
+        .exists());
+    assert!(test_dir.join("out/build.log").exists());
+    assert!(test_dir.join("out/.gitkeep").exists());
+    assert!(!test_dir.join("out/app.bin").exists());
+    assert!(!test_dir.join("pkg/node_modules/lodash").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_trash_conflicts_with_kill_processes() {
+    let test_dir = create_test_dir("trash_kill_conflict");
+    fs::write(test_dir.join("file.txt"), "data").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--trash", "--kill-processes"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{}", stderr);
+    assert!(test_dir.join("file.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_unlock_graceful_kill_reports_closed_and_killed() {
+    let test_dir = create_test_dir("unlock_graceful");
+    fs::write(test_dir.join("file.txt"), "data").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["--unlock", "--graceful-kill"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("0 process(es) closed gracefully, 0 killed"),
+        "{}",
+        stdout
+    );
+    assert!(test_dir.join("file.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_unlock_dry_run_changes_nothing() {
+    let test_dir = create_test_dir("unlock_dry_run");
+    fs::write(test_dir.join("file.txt"), "data").unwrap();
+
+    for path in [test_dir.clone(), test_dir.join("file.txt")] {
+        let output = Command::new(rmx_path())
+            .args(["--unlock", "--dry-run"])
+            .arg(&path)
+            .output()
+            .expect("Failed to execute rmx");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("would unlock"), "{}", stdout);
+        assert!(
+            stdout.contains("done: would kill 0 process(es), would close 0 handle(s)"),
+            "{}",
+            stdout
+        );
+    }
+    assert!(test_dir.join("file.txt").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_interactive_declined_file_is_skipped() {
+    let test_dir = create_test_dir("interactive");
+    fs::write(test_dir.join("a.txt"), "a").unwrap();
+    fs::write(test_dir.join("b.txt"), "b").unwrap();
+
+    // descend: y, a.txt: y, b.txt: n
+    let mut child = Command::new(rmx_path())
+        .args(["-ri"])
+        .arg(&test_dir)
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute rmx");
+    child.stdin.take().unwrap().write_all(b"y\ny\nn\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("remove file"), "{}", stderr);
+    assert!(!test_dir.join("a.txt").exists());
+    assert!(test_dir.join("b.txt").exists());
+
+    // Emptied directories get their own prompt: descend y, b.txt y, remove n
+    let mut child = Command::new(rmx_path())
+        .args(["-ri"])
+        .arg(&test_dir)
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute rmx");
+    child.stdin.take().unwrap().write_all(b"y\ny\nn\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("remove directory"), "{}", stderr);
+    assert!(!test_dir.join("b.txt").exists());
+    assert!(test_dir.exists());
+
+    // --force overrides -i: no prompts, nothing read from stdin
+    let output = Command::new(rmx_path())
+        .args(["-rif"])
+        .arg(&test_dir)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+}
+
+#[test]
+fn test_interactive_honors_scan_filters() {
+    let test_dir = create_test_dir("interactive_filters");
+    fs::write(test_dir.join("a.txt"), "a").unwrap();
+    fs::write(test_dir.join("keep.log"), "log").unwrap();
+
+    // Excluded files are never offered: descend y, a.txt y, nothing else
+    let mut child = Command::new(rmx_path())
+        .args(["-ri", "--exclude", "*.log"])
+        .arg(&test_dir)
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute rmx");
+    child.stdin.take().unwrap().write_all(b"y\ny\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(!stderr.contains("keep.log"), "{}", stderr);
+    assert!(!stderr.contains("remove directory"), "{}", stderr);
+    assert!(!test_dir.join("a.txt").exists());
+    assert!(test_dir.join("keep.log").exists());
+
+    // -i walks and deletes itself, so it cannot honor --recycle
+    let output = Command::new(rmx_path())
+        .args(["-ri", "--recycle"])
+        .arg(&test_dir)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(test_dir.join("keep.log").exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_interactive_counts_junctions_as_directories() {
+    let test_dir = create_test_dir("interactive_junction");
+    let target = create_test_dir("interactive_junction_target");
+    fs::write(target.join("kept.txt"), "kept").unwrap();
+    let made = Command::new("cmd")
+        .args(["/C", "mklink", "/J"])
+        .arg(test_dir.join("link"))
+        .arg(&target)
+        .output()
+        .expect("Failed to run mklink");
+    assert!(made.status.success());
+
+    // descend y, link y, the emptied directory y
+    let mut child = Command::new(rmx_path())
+        .args(["-ri", "--json"])
+        .arg(&test_dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute rmx");
+    child.stdin.take().unwrap().write_all(b"y\ny\ny\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["dirs_deleted"], 2);
+    assert_eq!(summary["files_deleted"], 0);
+    assert!(!test_dir.exists());
+    assert!(target.join("kept.txt").exists());
+
+    fs::remove_dir_all(&target).ok();
+}
+
+#[test]
+fn test_json_summary_is_valid_on_failure_and_empty_input() {
+    let test_dir = create_test_dir("json_output");
+    let file = test_dir.join("a.txt");
+    let dir = test_dir.join("sub");
+    fs::write(&file, "abc").unwrap();
+    fs::create_dir_all(&dir).unwrap();
+
+    // The directory fails without -r while the file is deleted
+    let output = Command::new(rmx_path())
+        .args(["-f", "--json"])
+        .arg(&file)
+        .arg(&dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["files_deleted"], 1);
+    assert_eq!(summary["dirs_deleted"], 0);
+    assert!(summary["elapsed_ms"].is_u64());
+    let failures = summary["failures"].as_array().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0]["path"], dir.to_string_lossy().as_ref());
+    assert_eq!(failures[0]["is_dir"], true);
+    assert_eq!(failures[0]["kind"], "other");
+    assert!(!file.exists());
+
+    // An empty path list still yields a parseable summary
+    let list = test_dir.join("empty.txt");
+    fs::write(&list, "").unwrap();
+    let output = Command::new(rmx_path())
+        .args(["--json-lines", "--from-file"])
+        .arg(&list)
+        .output()
+        .expect("Failed to execute rmx");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    let summary: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["failed"], 0);
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_json_summary_lists_skipped_entries() {
+    let test_dir = create_test_dir("json_skipped");
+    fs::write(test_dir.join("keep.log"), "keep").unwrap();
+    fs::write(test_dir.join("drop.txt"), "drop").unwrap();
+
+    for flag in ["--json", "--json-lines"] {
+        let output = Command::new(rmx_path())
+            .args(["-rn", flag, "--exclude", "*.log"])
+            .arg(&test_dir)
+            .output()
+            .expect("Failed to execute rmx");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let summary: serde_json::Value =
+            serde_json::from_str(stdout.lines().last().unwrap()).unwrap();
+        let skipped = summary["skipped"].as_array().unwrap();
+        assert_eq!(skipped.len(), 1, "{}", stdout);
+        assert_eq!(
+            skipped[0]["path"],
+            test_dir.join("keep.log").to_string_lossy().as_ref()
+        );
+        assert_eq!(skipped[0]["reason"], "excluded");
+    }
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_interactive_once_prompts_a_single_time() {
+    let test_dir = create_test_dir("interactive_once");
+    let files: Vec<PathBuf> = (0..4)
+        .map(|i| test_dir.join(format!("f{}.txt", i)))
+        .collect();
+    for f in &files {
+        fs::write(f, "x").unwrap();
+    }
+
+    let run = |input: &[u8]| {
+        let mut child = Command::new(rmx_path())
+            .arg("-I")
+            .args(&files)
+            .stdin(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to execute rmx");
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    let output = run(b"n\n");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("remove 4 arguments?"), "{}", stderr);
+    assert!(files.iter().all(|f| f.exists()));
+
+    // One answer covers every path; no per-file prompts follow
+    let output = run(b"y\n");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("[y/N]").count(), 1, "{}", stderr);
+    assert!(files.iter().all(|f| !f.exists()));
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_wildcard_operands_are_expanded() {
+    let test_dir = create_test_dir("wildcards");
+    fs::create_dir_all(test_dir.join("build/out")).unwrap();
+    fs::write(test_dir.join("build/out/x.o"), "x").unwrap();
+    fs::write(test_dir.join("build/a.log"), "a").unwrap();
+
+    // No shell involved: rmx sees the literal '*'
+    let output = Command::new(rmx_path())
+        .arg("-rf")
+        .arg(test_dir.join("build").join("*"))
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(test_dir.join("build").exists());
+    assert_eq!(fs::read_dir(test_dir.join("build")).unwrap().count(), 0);
+
+    // A pattern matching nothing is a missing path unless --force
+    let output = Command::new(rmx_path())
+        .arg(test_dir.join("*.none"))
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No such file or directory"), "{}", stderr);
+
+    let output = Command::new(rmx_path())
+        .arg("-f")
+        .arg(test_dir.join("*.none"))
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_rmxignore_is_honored_unless_no_ignore() {
+    let test_dir = create_test_dir("rmxignore");
+    fs::create_dir_all(test_dir.join("deps")).unwrap();
+    fs::write(
+        test_dir.join(".rmxignore"),
+        "# lock files survive a clean\n*.lock\n!old.lock\n",
+    )
+    .unwrap();
+    fs::write(test_dir.join("deps/yarn.lock"), "lock").unwrap();
+    fs::write(test_dir.join("deps/old.lock"), "old").unwrap();
+    fs::write(test_dir.join("deps/out.bin"), "bin").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--exclude", "*.bin"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(test_dir.join(".rmxignore").exists());
+    assert!(test_dir.join("deps/yarn.lock").exists());
+    assert!(test_dir.join("deps/out.bin").exists());
+    assert!(!test_dir.join("deps/old.lock").exists());
+
+    // The Recycle Bin would take the protected files along with the directory
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--recycle"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(".rmxignore"), "{}", stderr);
+    assert!(test_dir.join("deps/yarn.lock").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--no-ignore"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+}
+
+#[test]
+fn test_max_depth_dry_run_counts_only_reachable_entries() {
+    let test_dir = create_test_dir("max_depth");
+    fs::create_dir_all(test_dir.join("a/b")).unwrap();
+    fs::write(test_dir.join("top.txt"), "1").unwrap();
+    fs::write(test_dir.join("a/mid.txt"), "22").unwrap();
+    fs::write(test_dir.join("a/b/deep.txt"), "333").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rn", "--json", "--max-depth", "2"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["files_deleted"], 2);
+    assert_eq!(summary["dirs_deleted"], 0);
+    assert_eq!(summary["total_bytes"], 3);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--max-depth", "2"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.join("top.txt").exists());
+    assert!(!test_dir.join("a/mid.txt").exists());
+    assert!(test_dir.join("a/b/deep.txt").exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--max-depth", "0"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_from_stdin_merges_with_operands() {
+    let test_dir = create_test_dir("from_stdin");
+    let listed = test_dir.join("listed dir");
+    let quoted = test_dir.join("quoted.txt");
+    let operand = test_dir.join("operand.txt");
+    let keep = test_dir.join("keep.txt");
+    fs::create_dir_all(listed.join("node_modules")).unwrap();
+    fs::write(&quoted, "q").unwrap();
+    fs::write(&operand, "o").unwrap();
+    fs::write(&keep, "k").unwrap();
+
+    let run = |args: &[&str], extra: &[&PathBuf], input: &[u8]| {
+        let mut child = Command::new(rmx_path())
+            .args(args)
+            .args(extra)
+            .stdin(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to execute rmx");
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    // Trailing whitespace and enclosing quotes are stripped in line mode
+    let input = format!("{}  \n\"{}\"\n", listed.display(), quoted.display());
+    let output = run(&["-rf", "--from-stdin"], &[&operand], input.as_bytes());
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!listed.exists());
+    assert!(!quoted.exists());
+    assert!(!operand.exists());
+    assert!(keep.exists());
+
+    let output = run(&["-f", "--from-stdin", "-0"], &[], b"");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing operand"), "{}", stderr);
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_profile_reports_latency_percentiles() {
+    let test_dir = create_test_dir("profile");
+    create_nested_structure(&test_dir, 3, 2);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--profile"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("remove_dir latency (4 calls)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("p99"), "{}", stdout);
+    assert!(stdout.contains("level2"), "{}", stdout);
+    assert!(!test_dir.exists());
+}
+
+#[test]
+fn test_worker_stats_reports_every_worker() {
+    let test_dir = create_test_dir("worker_stats");
+    create_nested_structure(&test_dir, 3, 2);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--worker-stats", "-t", "3"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Workers (3):"), "{}", stdout);
+    assert!(stdout.contains("Imbalance"), "{}", stdout);
+    assert!(!test_dir.exists());
+}
+
+#[test]
+fn test_retry_flags_accept_zero_retries() {
+    let test_dir = create_test_dir("retry_flags");
+    create_nested_structure(&test_dir, 2, 2);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--max-retries", "0", "--retry-delay", "5"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+
+    create_nested_structure(&test_dir, 2, 2);
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--retries", "6", "--retry-backoff", "10,50,200"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+
+    // A fixed delay and a backoff list are mutually exclusive
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--retry-delay", "5", "--retry-backoff", "1,2"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_shred_deletes_files() {
+    let test_dir = create_test_dir("shred");
+    create_nested_structure(&test_dir, 2, 3);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--shred=2"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+
+    let file = create_test_dir("shred_single").join("secret.txt");
+    fs::write(&file, "secret").unwrap();
+    let output = Command::new(rmx_path())
+        .args(["--shred", "-f"])
+        .arg(&file)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!file.exists());
+    fs::remove_dir_all(file.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_fail_fast_skips_remaining_paths() {
+    let test_dir = create_test_dir("fail_fast");
+    // A directory without -r fails, ahead of a file that would be deleted
+    let dir = test_dir.join("sub");
+    let kept = test_dir.join("kept.txt");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&kept, "data").unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-f", "--fail-fast"])
+        .arg(&dir)
+        .arg(&kept)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(kept.exists());
+
+    // The later flag wins; continuing is the default
+    let output = Command::new(rmx_path())
+        .args(["-f", "--fail-fast", "--continue-on-error"])
+        .arg(&dir)
+        .arg(&kept)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(!kept.exists());
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_per_path_stats() {
+    let test_dir = create_test_dir("per_path_stats");
+    let first = test_dir.join("first");
+    let second = test_dir.join("second");
+    create_nested_structure(&first, 2, 3);
+    create_nested_structure(&second, 1, 1);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--stats=per-path"])
+        .arg(&first)
+        .arg(&second)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let row = |path: &PathBuf| {
+        stdout
+            .lines()
+            .find(|line| line.ends_with(&*path.to_string_lossy()))
+            .unwrap_or_else(|| panic!("no row for {}\n{}", path.display(), stdout))
+            .split_whitespace()
+            .next()
+            .unwrap()
+            .to_string()
+    };
+    // Root, two levels and six files; root, one level and one file
+    assert_eq!(row(&first), "9");
+    assert_eq!(row(&second), "3");
+    assert!(stdout.contains("Statistics:"));
+    assert!(stdout.contains("Total:       12"));
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_list_locks_deletes_nothing() {
+    let test_dir = create_test_dir("list_locks");
+    create_nested_structure(&test_dir, 2, 2);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--list-locks"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(test_dir.join("level0/level1/file1.txt").exists());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&*test_dir.to_string_lossy()), "{}", stdout);
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_exit_codes() {
+    let test_dir = create_test_dir("exit_codes");
+    let file = test_dir.join("a.txt");
+    fs::write(&file, "a").unwrap();
+
+    // Nothing to delete at that path
+    let output = Command::new(rmx_path())
+        .arg(test_dir.join("missing"))
+        .output()
+        .expect("Failed to execute rmx");
+    assert_eq!(output.status.code(), Some(1));
+
+    // A directory refused without -r, next to a file that is deleted
+    let output = Command::new(rmx_path())
+        .arg("-f")
+        .arg(&file)
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!file.exists());
+
+    // --summary-on-partial only forgives partial failures
+    let output = Command::new(rmx_path())
+        .arg("--summary-on-partial")
+        .arg(test_dir.join("missing"))
+        .output()
+        .expect("Failed to execute rmx");
+    assert_eq!(output.status.code(), Some(1));
+
+    // The path list itself can't be read
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--from-file"])
+        .arg(test_dir.join("no_list.txt"))
+        .output()
+        .expect("Failed to execute rmx");
+    assert_eq!(output.status.code(), Some(2));
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_streams_are_deleted_explicitly() {
+    let test_dir = create_test_dir("streams");
+    fs::create_dir_all(test_dir.join("downloads")).unwrap();
+    fs::write(test_dir.join("downloads/setup.exe"), "binary").unwrap();
+    // Writing to `file:name` creates an alternate data stream on NTFS
+    #[cfg(windows)]
+    fs::write(
+        test_dir.join("downloads/setup.exe:Zone.Identifier"),
+        "[ZoneTransfer]\r\nZoneId=3\r\n",
+    )
+    .unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--streams", "--stats"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    #[cfg(windows)]
+    assert!(stdout.contains("Streams:     1"), "{}", stdout);
+    #[cfg(not(windows))]
+    assert!(stdout.contains("Streams:     0"), "{}", stdout);
+}
+
+#[test]
+fn test_check_locks_requires_dry_run() {
+    let test_dir = create_test_dir("check_locks");
+    create_nested_structure(&test_dir, 2, 2);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--check-locks"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(test_dir.exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rn", "--check-locks", "--stats"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(test_dir.exists());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Locked:      0 (0 processes)"),
+        "{}",
+        stdout
+    );
+
+    fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_quiet_conflicts_with_verbose() {
+    let test_dir = create_test_dir("quiet");
+    create_nested_structure(&test_dir, 2, 2);
+
+    let output = Command::new(rmx_path())
+        .args(["-rfqv"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(!output.status.success());
+    assert!(test_dir.exists());
+
+    let output = Command::new(rmx_path())
+        .args(["-rfq"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert!(!test_dir.exists());
+}
+
+#[test]
+fn test_journal_resumes_interrupted_run() {
+    let test_dir = create_test_dir("journal");
+    let target = test_dir.join("target");
+    create_nested_structure(&target.join("done"), 2, 2);
+    create_nested_structure(&target.join("todo"), 2, 2);
+    let journal = test_dir.join("journal.txt");
+    // An earlier run got as far as finishing `done`
+    fs::write(&journal, format!("{}\n", target.join("done").display())).unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--journal"])
+        .arg(&journal)
+        .arg(&target)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(target.join("done/level0/file0.txt").exists());
+    assert!(!target.join("todo").exists());
+
+    let recorded = fs::read_to_string(&journal).unwrap();
+    assert!(recorded.contains(&target.join("todo").display().to_string()));
+
+    let _ = fs::remove_dir_all(&test_dir);
+}
+
+#[test]
+fn test_events_stream_one_json_object_per_action() {
+    let test_dir = create_test_dir("events");
+    create_nested_structure(&test_dir, 2, 3);
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--events"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let count = |kind: &str| events.iter().filter(|e| e["event"] == kind).count();
+    assert_eq!(count("file_deleted"), 6);
+    assert_eq!(count("dir_deleted"), 3);
+    for event in &events {
+        assert!(event["timestamp_ms"].as_u64().unwrap() > 0);
+        assert!(!event["thread"].as_str().unwrap().is_empty());
+    }
+    assert!(events
+        .iter()
+        .any(|e| e["path"] == test_dir.to_string_lossy().as_ref()));
+}
+
+#[test]
+fn test_on_disk_size_reports_allocated_bytes() {
+    let test_dir = create_test_dir("on_disk_size");
+    fs::write(test_dir.join("data.bin"), vec![7u8; 5000]).unwrap();
+
+    let output = Command::new(rmx_path())
+        .args(["-rf", "--json", "--on-disk-size"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["total_bytes"], 5000);
+    assert!(summary["on_disk_bytes"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn test_summary_only_prints_one_line() {
+    let test_dir = create_test_dir("summary_only");
+    create_nested_structure(&test_dir, 2, 3);
+
+    let output = Command::new(rmx_path())
+        .args(["-rfv", "--summary-only"])
+        .arg(&test_dir)
+        .output()
+        .expect("Failed to execute rmx");
+    assert!(output.status.success());
+    assert!(!test_dir.exists());
+    assert!(output.stderr.is_empty());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(
+        lines[0].starts_with("rmx: deleted 6 files, 3 dirs, "),
+        "{}",
+        lines[0]
+    );
+    assert!(lines[0].ends_with('s'));
+}